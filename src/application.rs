@@ -18,23 +18,36 @@
  * SPDX-License-Identifier: GPL-3.0-or-later
  */
 
-use adw::subclass::prelude::*;
+use adw::{prelude::*, subclass::prelude::*};
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 use ashpd::{desktop::background::Background, WindowIdentifier};
 use gettextrs::gettext;
 use gtk::{gio, glib, glib::Properties, prelude::*};
-use std::cell::RefCell;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashSet;
 use std::thread;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::caldav::{self, SyncResult};
+use crate::cli::{self, CliChange};
 use crate::config::{APPLICATION_ID, VERSION};
-use crate::db::models::{Project, Reminder, Task};
+use crate::db::models::{Project, Record, Reminder, Task};
 use crate::db::operations::{
-    read_project, read_reminder, read_reminders, read_task, update_reminder,
+    clear_history, is_blocked, purge_trash, read_dependents, read_project, read_projects,
+    read_record, read_reminder, read_reminders, read_task, redo_history, seal_history,
+    undo_history, update_reminder, without_history, HistoryStep,
 };
+use crate::dbus;
+use crate::timer::Timer;
 use crate::views::search::SearchWindow;
 use crate::views::task::TaskWindow;
-use crate::views::{ActionScope, BackupWindow, IPlanWindow, PreferencesWindow};
+use crate::views::{
+    ActionScope, BackupWindow, CompletedWindow, IPlanWindow, PreferencesWindow, ReportsWindow,
+    TrashWindow,
+};
+
+// Seconds between CalDAV syncs while the app is running
+const SYNC_INTERVAL: u32 = 900;
 
 mod imp {
     use super::*;
@@ -43,8 +56,15 @@ mod imp {
     #[properties(type_wrapper=super::IPlanApplication)]
     pub struct IPlanApplication {
         pub background_hold: RefCell<Option<ApplicationHoldGuard>>,
+        pub dbus_registration: RefCell<Option<gio::RegistrationId>>,
+        pub syncing: Cell<bool>,
+        pub timer: OnceCell<Timer>,
+        /// Projects with a reached goal by the start of the goal period
+        pub reached_goals: RefCell<HashSet<(i64, i64)>>,
         #[property(get, set)]
         pub settings: RefCell<Option<gio::Settings>>,
+        #[property(get, set)]
+        pub sync_status: RefCell<String>,
     }
 
     #[glib::object_subclass]
@@ -67,6 +87,8 @@ mod imp {
             obj.set_accels_for_action("app.search", &["<primary>f"]);
             obj.set_accels_for_action("app.modal-close", &["Escape"]);
             obj.set_accels_for_action("app.window-close", &["<primary>w"]);
+            obj.set_accels_for_action("app.undo", &["<primary>z"]);
+            obj.set_accels_for_action("app.redo", &["<primary><shift>z"]);
         }
         fn properties() -> &'static [glib::ParamSpec] {
             Self::derived_properties()
@@ -91,10 +113,17 @@ mod imp {
                 obj.request_background();
             }
 
+            obj.setup_timer();
+            // Undo is for the changes of this run
+            clear_history().expect("Failed to clear history");
+            obj.purge_old_trash();
+
             let reminders = read_reminders(None).unwrap();
             for reminder in reminders {
                 obj.send_reminder(reminder);
             }
+
+            obj.setup_sync();
         }
 
         fn activate(&self) {
@@ -110,9 +139,66 @@ mod imp {
             };
             window.present();
         }
+
+        fn local_command_line(
+            &self,
+            arguments: &mut gio::subclass::ArgumentList,
+        ) -> Option<glib::ExitCode> {
+            // Commands run in the invoked process to print their output in the terminal
+            let args = arguments
+                .iter()
+                .skip(1)
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect::<Vec<String>>();
+            if !cli::is_command(&args) {
+                return self.parent_local_command_line(arguments);
+            }
+
+            match cli::run(&args) {
+                Ok(changes) => {
+                    self.obj().send_cli_changes(changes);
+                    Some(glib::ExitCode::SUCCESS)
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    Some(glib::ExitCode::FAILURE)
+                }
+            }
+        }
+
+        fn dbus_register(
+            &self,
+            connection: &gio::DBusConnection,
+            object_path: &str,
+        ) -> Result<(), glib::Error> {
+            self.parent_dbus_register(connection, object_path)?;
+            let registration = dbus::register_object(&self.obj(), connection, object_path)?;
+            self.dbus_registration.replace(Some(registration));
+            Ok(())
+        }
+
+        fn dbus_unregister(&self, connection: &gio::DBusConnection, object_path: &str) {
+            if let Some(registration) = self.dbus_registration.take() {
+                connection
+                    .unregister_object(registration)
+                    .expect("Failed to unregister D-Bus object");
+            }
+            self.parent_dbus_unregister(connection, object_path);
+        }
+
+        fn command_line(&self, _command_line: &gio::ApplicationCommandLine) -> glib::ExitCode {
+            self.obj().activate();
+            glib::ExitCode::SUCCESS
+        }
     }
 
-    impl GtkApplicationImpl for IPlanApplication {}
+    impl GtkApplicationImpl for IPlanApplication {
+        fn window_removed(&self, window: &gtk::Window) {
+            self.parent_window_removed(window);
+            // Closing a dialog commits its changes
+            seal_history().expect("Failed to update history");
+        }
+    }
     impl AdwApplicationImpl for IPlanApplication {}
 }
 
@@ -122,6 +208,14 @@ glib::wrapper! {
         @implements gio::ActionGroup, gio::ActionMap;
 }
 
+impl Default for IPlanApplication {
+    fn default() -> Self {
+        gio::Application::default()
+            .and_downcast::<IPlanApplication>()
+            .unwrap()
+    }
+}
+
 impl IPlanApplication {
     pub fn new(application_id: &str, flags: &gio::ApplicationFlags) -> Self {
         glib::Object::builder()
@@ -141,12 +235,24 @@ impl IPlanApplication {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards");
-        let datetime = reminder.datetime_duration();
+        let mut datetime = reminder.datetime_duration();
 
         if datetime < now {
-            reminder.set_past(true);
-            update_reminder(&reminder).unwrap();
-            return;
+            if let Some(repeat) = reminder.repeat_rule() {
+                // Skip the occurrences missed while the app was not running
+                let now_datetime = glib::DateTime::now_local().unwrap();
+                let mut next = reminder.datetime_datetime();
+                while next.to_unix() <= now_datetime.to_unix() {
+                    next = repeat.next_date(&next, &now_datetime);
+                }
+                reminder.set_datetime(next.to_unix());
+                without_history(|| update_reminder(&reminder)).unwrap();
+                datetime = reminder.datetime_duration();
+            } else {
+                reminder.set_past(true);
+                without_history(|| update_reminder(&reminder)).unwrap();
+                return;
+            }
         }
 
         let remains = datetime - now;
@@ -155,23 +261,383 @@ impl IPlanApplication {
             if tx.send("").is_err() {}
         });
         rx.attach(None, glib::clone!(@weak self as obj => @default-return glib::ControlFlow::Break, move |_: &str| {
-            let fresh_reminder = read_reminder(reminder.id()).expect("Failed to read reminder");
+            // Removed with its task when the trash is emptied
+            let Ok(fresh_reminder) = read_reminder(reminder.id()) else {
+                return glib::ControlFlow::Break;
+            };
 
             if fresh_reminder.past() || fresh_reminder.datetime() != reminder.datetime() {
 				return glib::ControlFlow::Break;
             }
 
-            let task = read_task(fresh_reminder.task()).expect("Failed to read task");
+            // Tasks in the trash are not readable, restoring schedules the reminder again
+            let Ok(task) = read_task(fresh_reminder.task()) else {
+                return glib::ControlFlow::Break;
+            };
             let notification = gio::Notification::new(&task.name());
             notification.set_priority(gio::NotificationPriority::High);
             obj.send_notification(Some(&format!("reminder-{}", fresh_reminder.id())), &notification);
-            fresh_reminder.set_past(true);
-            update_reminder(&fresh_reminder).expect("Failed to update reminder");
+
+            if let Some(repeat) = fresh_reminder.repeat_rule() {
+                let datetime = fresh_reminder.datetime_datetime();
+                fresh_reminder.set_datetime(repeat.next_date(&datetime, &datetime).to_unix());
+                without_history(|| update_reminder(&fresh_reminder)).expect("Failed to update reminder");
+                obj.send_reminder(fresh_reminder);
+            } else {
+                fresh_reminder.set_past(true);
+                without_history(|| update_reminder(&fresh_reminder)).expect("Failed to update reminder");
+            }
 
             glib::ControlFlow::Break
         }));
     }
 
+    fn send_cli_changes(&self, changes: Vec<CliChange>) {
+        // Only notify a running instance and don't start the app for it
+        if changes.is_empty() || !self.is_running_elsewhere() {
+            return;
+        }
+        if self.register(gio::Cancellable::NONE).is_err() || !self.is_remote() {
+            return;
+        }
+
+        for change in changes {
+            let (action, id) = match change {
+                CliChange::Task(id) => ("task-changed", id),
+                CliChange::TimerStarted(id) => ("timer-started", id),
+                CliChange::TimerStopped(id) => ("timer-stopped", id),
+            };
+            self.activate_action(action, Some(&id.to_variant()));
+        }
+        if let Some(connection) = self.dbus_connection() {
+            connection.flush_sync(gio::Cancellable::NONE).ok();
+        }
+    }
+
+    fn is_running_elsewhere(&self) -> bool {
+        gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+            .and_then(|connection| {
+                connection.call_sync(
+                    Some("org.freedesktop.DBus"),
+                    "/org/freedesktop/DBus",
+                    "org.freedesktop.DBus",
+                    "NameHasOwner",
+                    Some(&(APPLICATION_ID,).to_variant()),
+                    Some(glib::VariantTy::new("(b)").unwrap()),
+                    gio::DBusCallFlags::NONE,
+                    -1,
+                    gio::Cancellable::NONE,
+                )
+            })
+            .map(|reply| reply.get::<(bool,)>().unwrap().0)
+            .unwrap_or(false)
+    }
+
+    pub fn main_window(&self) -> Option<IPlanWindow> {
+        self.window_by_name("IPlanWindow")
+            .and_downcast::<IPlanWindow>()
+    }
+
+    // Changes from outside of the window, the window actions emit the D-Bus signals
+    pub fn refresh_task(&self, task: &Task) {
+        if let Some(window) = self.main_window() {
+            window
+                .activate_action(
+                    "task.changed",
+                    Some(&glib::Variant::from((
+                        task.to_variant(),
+                        ActionScope::None.to_variant(),
+                    ))),
+                )
+                .unwrap();
+        } else {
+            self.emit_tasks_signal("TaskChanged", (task.id(),).to_variant());
+        }
+    }
+
+    fn refresh_duration(&self, task: &Task) {
+        if let Some(window) = self.main_window() {
+            window
+                .activate_action(
+                    "task.duration-changed",
+                    Some(&glib::Variant::from((
+                        task.to_variant(),
+                        ActionScope::None.to_variant(),
+                    ))),
+                )
+                .unwrap();
+        }
+    }
+
+    fn apply_history_step(&self, step: Option<HistoryStep>) {
+        let Some(step) = step else {
+            return;
+        };
+        if step.structure {
+            if let Some(window) = self.main_window() {
+                window.reset();
+            }
+        }
+        for (task_id, task_before) in step.tasks {
+            if let Ok(task) = read_task(task_id) {
+                self.refresh_task(&task);
+                if task_before.is_none() {
+                    self.send_task_reminders(task_id);
+                }
+            } else if let Some(task) = task_before {
+                // Removed by the step, suspended rows are hidden like the deleted ones
+                task.set_suspended(true);
+                self.refresh_task(&task);
+            }
+            self.reset_task_windows(task_id);
+        }
+        for task_id in step.records {
+            if let Ok(task) = read_task(task_id) {
+                self.refresh_duration(&task);
+            }
+            self.reset_task_windows(task_id);
+        }
+    }
+
+    /// Schedule the reminders of a task that is back from the trash or the undo history
+    pub fn send_task_reminders(&self, task_id: i64) {
+        for reminder in read_reminders(Some(task_id)).expect("Failed to read reminders") {
+            if !reminder.past() {
+                self.send_reminder(reminder);
+            }
+        }
+    }
+
+    /// Remove the items kept in the trash longer than the retention days
+    fn purge_old_trash(&self) {
+        let retention = self.settings().unwrap().int("trash-retention");
+        let before = glib::DateTime::now_local()
+            .unwrap()
+            .add_days(-retention)
+            .unwrap()
+            .to_unix();
+        without_history(|| purge_trash(before)).expect("Failed to purge trash");
+    }
+
+    fn reset_task_windows(&self, task_id: i64) {
+        for window in self.windows() {
+            if let Some(window) = window.downcast_ref::<TaskWindow>() {
+                window.reset_task(task_id);
+            }
+        }
+    }
+
+    pub fn timer(&self) -> Timer {
+        self.imp().timer.get().unwrap().clone()
+    }
+
+    fn setup_timer(&self) {
+        let timer = Timer::new();
+        timer.connect_closure(
+            "started",
+            false,
+            glib::closure_local!(@watch self as obj => move |_: Timer, task: Task, record: Record| {
+                obj.withdraw_notification("pomodoro");
+                obj.emit_tasks_signal("TimerStarted", (task.id(), record.id()).to_variant());
+            }),
+        );
+        timer.connect_closure(
+            "stopped",
+            false,
+            glib::closure_local!(@watch self as obj => move |_: Timer, task: Task, _: Record| {
+                obj.emit_tasks_signal("TimerStopped", (task.id(),).to_variant());
+                obj.refresh_duration(&task);
+                obj.check_project_goal(task.project(), true);
+            }),
+        );
+        timer.connect_closure(
+            "tick",
+            false,
+            glib::closure_local!(@watch self as obj => move |timer: Timer| {
+                for record in timer.running_records() {
+                    // Once a minute for each running timer
+                    if record.duration() % 60 != 0 {
+                        continue;
+                    }
+                    if let Ok(task) = read_task(record.task()) {
+                        obj.check_project_goal(task.project(), true);
+                    }
+                }
+            }),
+        );
+        timer.connect_closure(
+            "pomodoro-finished",
+            false,
+            glib::closure_local!(@watch self as obj => move |timer: Timer, task: Task, work: bool| {
+                obj.send_pomodoro_notification(&timer, &task, work);
+            }),
+        );
+        timer.connect_closure(
+            "idle-finished",
+            false,
+            glib::closure_local!(@watch self as obj => move |_: Timer, idle_start: i64| {
+                obj.show_idle_dialog(idle_start);
+            }),
+        );
+        self.imp().timer.set(timer.clone()).unwrap();
+        timer.load_records();
+
+        // Goals reached before the start are not notified again
+        for project in read_projects(true).expect("Failed to read projects") {
+            self.check_project_goal(project.id(), false);
+        }
+    }
+
+    /// Notify once in each goal period when the time spent on the project reaches its goal
+    fn check_project_goal(&self, project_id: i64, notify: bool) {
+        let Ok(project) = read_project(project_id) else {
+            return;
+        };
+        let goal = project.goal();
+        if goal == 0 {
+            return;
+        }
+
+        let mut progress = project.goal_progress();
+        for record in self.timer().running_records() {
+            if read_task(record.task()).is_ok_and(|task| task.project() == project_id) {
+                progress += record.duration();
+            }
+        }
+        let period_start = project.goal_period_start().to_unix();
+        if progress < goal
+            || !self
+                .imp()
+                .reached_goals
+                .borrow_mut()
+                .insert((project_id, period_start))
+            || !notify
+        {
+            return;
+        }
+
+        let notification = gio::Notification::new(&gettext("Goal Reached"));
+        notification.set_body(Some(&format!(
+            "{} {}: {}",
+            project.icon(),
+            project.name(),
+            Record::duration_display(progress)
+        )));
+        self.send_notification(Some(&format!("goal-{project_id}")), &notification);
+    }
+
+    fn send_pomodoro_notification(&self, timer: &Timer, task: &Task, work: bool) {
+        let notification = if work {
+            let notification = gio::Notification::new(&gettext("Work Interval Finished"));
+            if let Some(phase) = timer.pomodoro_phase() {
+                notification.add_button(
+                    &gettext("Start {}").replace("{}", &phase.display()),
+                    "app.pomodoro-break",
+                );
+            }
+            notification
+        } else {
+            gio::Notification::new(&gettext("Break Finished"))
+        };
+        notification.set_body(Some(&task.name()));
+        notification.set_priority(gio::NotificationPriority::High);
+        notification.add_button(&gettext("Continue"), "app.pomodoro-continue");
+        self.send_notification(Some("pomodoro"), &notification);
+    }
+
+    /// Refresh the tasks blocked by the task and notify the ones without other blockers
+    pub fn refresh_dependents(&self, task: &Task) {
+        for dependent in read_dependents(task.id()).expect("Failed to read tasks") {
+            self.refresh_task(&dependent);
+            if task.done() && !dependent.done() && !is_blocked(dependent.id()).unwrap() {
+                let notification = gio::Notification::new(&gettext("Task Unblocked"));
+                notification.set_body(Some(&dependent.name()));
+                self.send_notification(
+                    Some(&format!("unblocked-{}", dependent.id())),
+                    &notification,
+                );
+            }
+        }
+    }
+
+    pub fn remove_task_row(&self, task: &Task) {
+        if let Some(window) = self.main_window() {
+            window.remove_task_row(task);
+        }
+    }
+
+    pub fn emit_tasks_signal(&self, signal: &str, parameters: glib::Variant) {
+        if self.is_remote() {
+            return;
+        }
+        if let (Some(connection), Some(object_path)) =
+            (self.dbus_connection(), self.dbus_object_path())
+        {
+            // Listeners miss the change, the app keeps working
+            if let Err(err) = connection.emit_signal(
+                None,
+                &object_path,
+                dbus::INTERFACE_NAME,
+                signal,
+                Some(&parameters),
+            ) {
+                eprintln!("Failed to emit D-Bus signal {signal}: {err}");
+            }
+        }
+    }
+
+    fn setup_sync(&self) {
+        self.sync();
+        glib::timeout_add_seconds_local(
+            SYNC_INTERVAL,
+            glib::clone!(@weak self as obj => @default-return glib::ControlFlow::Break, move || {
+                obj.sync();
+                glib::ControlFlow::Continue
+            }),
+        );
+    }
+
+    pub fn sync(&self) {
+        let imp = self.imp();
+        let settings = self.settings().unwrap();
+        if imp.syncing.get() || !settings.boolean("caldav-enabled") {
+            return;
+        }
+
+        imp.syncing.set(true);
+        self.set_sync_status(gettext("Syncing…"));
+        glib::MainContext::default().spawn_local(glib::clone!(@weak self as obj => async move {
+            let status = match caldav::sync(&settings).await {
+                Ok(result) => {
+                    obj.apply_sync_result(&result);
+                    result.summary()
+                }
+                Err(err) => err,
+            };
+            obj.imp().syncing.set(false);
+            obj.set_sync_status(status);
+        }));
+    }
+
+    fn apply_sync_result(&self, result: &SyncResult) {
+        self.timer()
+            .remove_trashed_records()
+            .expect("Failed to stop timer");
+        for task in &result.changed {
+            self.refresh_task(task);
+        }
+        for task in &result.deleted {
+            if task.parent() == 0 {
+                self.remove_task_row(task);
+            } else if let Ok(parent) = read_task(task.parent()) {
+                self.refresh_task(&parent);
+            }
+        }
+        for reminder in &result.reminders {
+            self.send_reminder(reminder.clone());
+        }
+    }
+
     fn setup_settings(&self) {
         let settings = self.settings().unwrap();
         settings.connect_changed(
@@ -186,6 +652,10 @@ impl IPlanApplication {
                 }
             }),
         );
+        settings.connect_changed(
+            Some("caldav-enabled"),
+            glib::clone!(@weak self as obj => move |_, _| obj.sync()),
+        );
     }
 
     fn setup_gactions(&self) {
@@ -207,12 +677,82 @@ impl IPlanApplication {
         let backup_action = gio::ActionEntry::builder("backup")
             .activate(move |app: &Self, _, _| app.show_backup())
             .build();
+        let reports_action = gio::ActionEntry::builder("reports")
+            .activate(move |app: &Self, _, _| app.show_reports())
+            .build();
+        let trash_action = gio::ActionEntry::builder("trash")
+            .activate(move |app: &Self, _, _| app.show_trash())
+            .build();
+        let completed_action = gio::ActionEntry::builder("completed")
+            .activate(move |app: &Self, _, _| app.show_completed())
+            .build();
         let modal_close_action = gio::ActionEntry::builder("modal-close")
             .activate(move |app: &Self, _, _| app.close_modal())
             .build();
         let window_close_action = gio::ActionEntry::builder("window-close")
             .activate(move |app: &Self, _, _| app.close_window())
             .build();
+        let undo_action = gio::ActionEntry::builder("undo")
+            .activate(move |app: &Self, _, _| {
+                app.apply_history_step(undo_history().expect("Failed to undo"));
+            })
+            .build();
+        let redo_action = gio::ActionEntry::builder("redo")
+            .activate(move |app: &Self, _, _| {
+                app.apply_history_step(redo_history().expect("Failed to redo"));
+            })
+            .build();
+        let sync_action = gio::ActionEntry::builder("sync")
+            .activate(move |app: &Self, _, _| app.sync())
+            .build();
+        // Sent by the command-line interface from other processes
+        let task_changed_action = gio::ActionEntry::builder("task-changed")
+            .parameter_type(Some(glib::VariantTy::INT64))
+            .activate(move |app: &Self, _, value| {
+                let task_id: i64 = value.unwrap().get().unwrap();
+                if let Ok(task) = read_task(task_id) {
+                    app.refresh_task(&task);
+                }
+            })
+            .build();
+        let timer_started_action = gio::ActionEntry::builder("timer-started")
+            .parameter_type(Some(glib::VariantTy::INT64))
+            .activate(move |app: &Self, _, value| {
+                let record_id: i64 = value.unwrap().get().unwrap();
+                if let Ok(record) = read_record(record_id) {
+                    let task = read_task(record.task()).unwrap();
+                    app.timer().add_record(&task, record);
+                }
+            })
+            .build();
+        let timer_stopped_action = gio::ActionEntry::builder("timer-stopped")
+            .parameter_type(Some(glib::VariantTy::INT64))
+            .activate(move |app: &Self, _, value| {
+                let task_id: i64 = value.unwrap().get().unwrap();
+                if let Ok(task) = read_task(task_id) {
+                    let timer = app.timer();
+                    if let Some(record) = timer.record(task_id) {
+                        // Saved by the other process
+                        timer.remove_record(&task, &record);
+                    }
+                }
+            })
+            .build();
+        // Buttons of the Pomodoro notifications
+        let pomodoro_break_action = gio::ActionEntry::builder("pomodoro-break")
+            .activate(move |app: &Self, _, _| {
+                app.withdraw_notification("pomodoro");
+                app.timer().start_pomodoro_break();
+            })
+            .build();
+        let pomodoro_continue_action = gio::ActionEntry::builder("pomodoro-continue")
+            .activate(move |app: &Self, _, _| {
+                app.withdraw_notification("pomodoro");
+                app.timer()
+                    .continue_pomodoro()
+                    .expect("Failed to start timer");
+            })
+            .build();
         self.add_action_entries([
             quit_action,
             about_action,
@@ -220,8 +760,19 @@ impl IPlanApplication {
             shortcuts_action,
             search_action,
             backup_action,
+            reports_action,
+            trash_action,
+            completed_action,
             modal_close_action,
             window_close_action,
+            undo_action,
+            redo_action,
+            sync_action,
+            task_changed_action,
+            timer_started_action,
+            timer_stopped_action,
+            pomodoro_break_action,
+            pomodoro_continue_action,
         ]);
     }
 
@@ -327,6 +878,61 @@ impl IPlanApplication {
         backup_window.present();
     }
 
+    fn show_idle_dialog(&self, idle_start: i64) {
+        if self.active_window().is_none() {
+            self.activate();
+        }
+        let window = self.active_window().unwrap();
+        let idle_start_time = glib::DateTime::from_unix_local(idle_start)
+            .unwrap()
+            .format("%R")
+            .unwrap();
+        let body = gettext("The timer kept running while you were away since {}.")
+            .replace("{}", &idle_start_time);
+        let dialog =
+            adw::MessageDialog::new(Some(&window), Some(&gettext("Welcome Back")), Some(&body));
+        dialog.add_response("keep", &gettext("_Keep Time"));
+        dialog.add_response("discard", &gettext("_Discard Idle Time"));
+        dialog.add_response("stop", &gettext("_Stop Timer"));
+        dialog.set_response_appearance("discard", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("discard"));
+        dialog.set_close_response("keep");
+        dialog.connect_response(
+            None,
+            glib::clone!(@weak self as obj => move |_, response| {
+                let timer = obj.timer();
+                match response {
+                    "discard" => timer
+                        .discard_idle_time(idle_start)
+                        .expect("Failed to update records"),
+                    "stop" => timer
+                        .stop_at_idle_start(idle_start)
+                        .expect("Failed to update records"),
+                    _ => (),
+                }
+            }),
+        );
+        dialog.present();
+    }
+
+    fn show_reports(&self) {
+        let active_window = self.active_window().unwrap();
+        let reports_window = ReportsWindow::new(self, &active_window);
+        reports_window.present();
+    }
+
+    fn show_trash(&self) {
+        let active_window = self.active_window().unwrap();
+        let trash_window = TrashWindow::new(self, &active_window);
+        trash_window.present();
+    }
+
+    fn show_completed(&self) {
+        let active_window = self.active_window().unwrap();
+        let completed_window = CompletedWindow::new(self, &active_window);
+        completed_window.present();
+    }
+
     fn show_about(&self) {
         let window = self.active_window().unwrap();
         let about = adw::AboutWindow::builder()
@@ -0,0 +1,650 @@
+/* caldav.rs
+ *
+ * Copyright 2023 Iman Salmani
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use gettextrs::gettext;
+use gtk::{gio, glib, glib::translate::IntoGlib, prelude::*};
+use soup::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::config::APPLICATION_ID;
+use crate::db::ics::{find_task, parse_todos, task_calendar, task_uid, Todo};
+use crate::db::models::{Project, Reminder, Task};
+use crate::db::operations::{
+    create_next_occurrence, create_task, delete_caldav_item, new_subtask_position,
+    new_task_position, read_caldav_calendar, read_caldav_items, read_projects, read_reminders,
+    read_sections, read_task, read_tasks, set_caldav_calendar, set_caldav_item, set_task_uid,
+    trash_task, update_pulled_task, update_task, without_history, CalDavItem,
+};
+
+const XML_TYPE: &str = "application/xml; charset=utf-8";
+const CALENDAR_TYPE: &str = "text/calendar; charset=utf-8";
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VTODO"/>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+
+/// Side that is kept when a task changed both locally and on the server since the last sync
+#[derive(Clone, Copy, PartialEq)]
+pub enum ConflictPolicy {
+    Server,
+    Local,
+}
+
+impl ConflictPolicy {
+    pub fn from_settings(settings: &gio::Settings) -> Self {
+        if settings.string("caldav-conflict-policy") == "local" {
+            ConflictPolicy::Local
+        } else {
+            ConflictPolicy::Server
+        }
+    }
+}
+
+/// Changes of a sync that the window and reminders should know about
+pub struct SyncResult {
+    pub policy: ConflictPolicy,
+    /// Tasks created or changed from the server
+    pub changed: Vec<Task>,
+    /// Tasks moved to the trash because they are removed from the server
+    pub deleted: Vec<Task>,
+    pub reminders: Vec<Reminder>,
+    /// Name of tasks changed on both sides
+    pub conflicts: Vec<String>,
+}
+
+impl SyncResult {
+    pub fn summary(&self) -> String {
+        let time = glib::DateTime::now_local()
+            .unwrap()
+            .format("%H:%M")
+            .unwrap();
+        let mut summary = gettext("Last synced at {}").replace("{}", &time);
+        if !self.conflicts.is_empty() {
+            let kept = match self.policy {
+                ConflictPolicy::Server => gettext("Server version kept for"),
+                ConflictPolicy::Local => gettext("Local version kept for"),
+            };
+            summary.push_str(&format!("\n{kept} {}", self.conflicts.join(", ")));
+        }
+        summary
+    }
+}
+
+/// Pull changes of all projects from the server and then push the local ones.
+/// Each project is a calendar collection with the tasks as VTODO items.
+pub async fn sync(settings: &gio::Settings) -> Result<SyncResult, String> {
+    let url = settings.string("caldav-url");
+    if url.is_empty() {
+        return Err(gettext("Server URL is not set"));
+    }
+    let client = Client::new(
+        &url,
+        &settings.string("caldav-username"),
+        &password().await?,
+    );
+    let mut result = SyncResult {
+        policy: ConflictPolicy::from_settings(settings),
+        changed: vec![],
+        deleted: vec![],
+        reminders: vec![],
+        conflicts: vec![],
+    };
+
+    // Deleted tasks and tasks moved to another project leave their old calendar
+    for item in read_caldav_items(None).map_err(db_error)? {
+        let removed = match read_task(item.task) {
+            Ok(task) => task.project() != item.project,
+            Err(rusqlite::Error::QueryReturnedNoRows) => true,
+            Err(err) => return Err(db_error(err)),
+        };
+        if removed {
+            client.delete(&item, result.policy).await?;
+            delete_caldav_item(item.task).map_err(db_error)?;
+        }
+    }
+
+    for project in read_projects(true).map_err(db_error)? {
+        client.sync_project(&project, &mut result).await?;
+    }
+    Ok(result)
+}
+
+pub async fn store_password(password: &str) -> Result<(), String> {
+    let keyring = oo7::Keyring::new().await.map_err(|err| err.to_string())?;
+    keyring
+        .create_item(
+            &gettext("IPlan CalDAV Account"),
+            secret_attributes(),
+            password,
+            true,
+        )
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn password() -> Result<String, String> {
+    let keyring = oo7::Keyring::new().await.map_err(|err| err.to_string())?;
+    let items = keyring
+        .search_items(secret_attributes())
+        .await
+        .map_err(|err| err.to_string())?;
+    let item = items
+        .first()
+        .ok_or_else(|| gettext("Password is not set"))?;
+    let secret = item.secret().await.map_err(|err| err.to_string())?;
+    String::from_utf8(secret.to_vec()).map_err(|err| err.to_string())
+}
+
+fn secret_attributes() -> HashMap<&'static str, &'static str> {
+    HashMap::from([("application", APPLICATION_ID), ("type", "caldav")])
+}
+
+fn db_error(err: rusqlite::Error) -> String {
+    err.to_string()
+}
+
+fn status_error(status: u32) -> String {
+    gettext("Server responded with status {}").replace("{}", &status.to_string())
+}
+
+fn is_success(status: u32) -> bool {
+    (200..300).contains(&status)
+}
+
+struct Response {
+    status: u32,
+    body: String,
+    etag: Option<String>,
+}
+
+struct RemoteItem {
+    href: String,
+    etag: String,
+    todo: Todo,
+}
+
+struct Client {
+    session: soup::Session,
+    url: String,
+    authorization: String,
+}
+
+impl Client {
+    fn new(url: &str, username: &str, password: &str) -> Self {
+        let credentials = glib::base64_encode(format!("{username}:{password}").as_bytes());
+        let url = if url.ends_with('/') {
+            url.to_string()
+        } else {
+            format!("{url}/")
+        };
+        Self {
+            session: soup::Session::new(),
+            url,
+            authorization: format!("Basic {credentials}"),
+        }
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, String)],
+        body: Option<(&str, &str)>,
+    ) -> Result<Response, String> {
+        let uri = glib::Uri::parse(url, glib::UriFlags::ENCODED).map_err(|err| err.to_string())?;
+        let message = soup::Message::from_uri(method, &uri);
+        let request_headers = message.request_headers().unwrap();
+        request_headers.append("Authorization", &self.authorization);
+        for (name, value) in headers {
+            request_headers.append(name, value);
+        }
+        if let Some((content_type, body)) = body {
+            message.set_request_body_from_bytes(
+                Some(content_type),
+                Some(&glib::Bytes::from(body.as_bytes())),
+            );
+        }
+
+        let bytes = self
+            .session
+            .send_and_read_future(&message, glib::Priority::DEFAULT)
+            .await
+            .map_err(|err| err.to_string())?;
+        let status = message.status().into_glib() as u32;
+        if status == 401 {
+            return Err(gettext("Authentication failed"));
+        }
+        let etag = message
+            .response_headers()
+            .and_then(|headers| headers.one("ETag"))
+            .map(|etag| etag.to_string());
+        Ok(Response {
+            status,
+            body: String::from_utf8_lossy(&bytes).to_string(),
+            etag,
+        })
+    }
+
+    async fn calendar_href(&self, project: &Project) -> Result<String, String> {
+        if let Some(href) = read_caldav_calendar(project.id()).map_err(db_error)? {
+            return Ok(href);
+        }
+
+        let href = format!("{}iplan-{}/", self.url, project.id());
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:mkcalendar xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:set>
+    <d:prop>
+      <d:displayname>{}</d:displayname>
+      <c:supported-calendar-component-set>
+        <c:comp name="VTODO"/>
+      </c:supported-calendar-component-set>
+    </d:prop>
+  </d:set>
+</c:mkcalendar>"#,
+            xml_escape(&project.name())
+        );
+        let response = self
+            .send("MKCALENDAR", &href, &[], Some((XML_TYPE, &body)))
+            .await?;
+        // Not allowed means the calendar exists from a previous database
+        if !is_success(response.status) && response.status != 405 {
+            return Err(status_error(response.status));
+        }
+        set_caldav_calendar(project.id(), &href).map_err(db_error)?;
+        Ok(href)
+    }
+
+    async fn fetch_items(&self, calendar_href: &str) -> Result<Vec<RemoteItem>, String> {
+        let response = self
+            .send(
+                "REPORT",
+                calendar_href,
+                &[("Depth", "1".to_string())],
+                Some((XML_TYPE, CALENDAR_QUERY)),
+            )
+            .await?;
+        if response.status != 207 {
+            return Err(status_error(response.status));
+        }
+
+        let mut items = vec![];
+        for element in xml_elements(&response.body, "response") {
+            let (href, data) = match (
+                xml_elements(element, "href").first(),
+                xml_elements(element, "calendar-data").first(),
+            ) {
+                (Some(href), Some(data)) => (xml_text(href), xml_text(data)),
+                _ => continue,
+            };
+            let todo = if let Some(todo) = parse_todos(&data).into_iter().next() {
+                todo
+            } else {
+                continue;
+            };
+            let etag = xml_elements(element, "getetag")
+                .first()
+                .map(|etag| xml_text(etag))
+                .unwrap_or_default();
+            let href =
+                glib::Uri::resolve_relative(Some(calendar_href), &href, glib::UriFlags::ENCODED)
+                    .map_err(|err| err.to_string())?;
+            items.push(RemoteItem {
+                href: href.to_string(),
+                etag,
+                todo,
+            });
+        }
+        Ok(items)
+    }
+
+    async fn delete(&self, item: &CalDavItem, policy: ConflictPolicy) -> Result<(), String> {
+        let headers = if item.etag.is_empty() || policy == ConflictPolicy::Local {
+            vec![]
+        } else {
+            vec![("If-Match", item.etag.clone())]
+        };
+        let response = self.send("DELETE", &item.href, &headers, None).await?;
+        // When the item changed on the server it comes back in the pull
+        if is_success(response.status) || response.status == 404 || response.status == 412 {
+            Ok(())
+        } else {
+            Err(status_error(response.status))
+        }
+    }
+
+    async fn sync_project(&self, project: &Project, result: &mut SyncResult) -> Result<(), String> {
+        let calendar_href = self.calendar_href(project).await?;
+        let remote_items = self.fetch_items(&calendar_href).await?;
+        // Pulled changes are not user actions, the undo history skips them
+        without_history(|| Ok::<_, rusqlite::Error>(pull_items(project, remote_items, result)))
+            .map_err(db_error)??;
+
+        self.push_project(project, &calendar_href, result).await
+    }
+
+    async fn push_project(
+        &self,
+        project: &Project,
+        calendar_href: &str,
+        result: &mut SyncResult,
+    ) -> Result<(), String> {
+        let items: HashMap<i64, CalDavItem> = read_caldav_items(Some(project.id()))
+            .map_err(db_error)?
+            .into_iter()
+            .map(|item| (item.task, item))
+            .collect();
+        let tasks =
+            read_tasks(Some(project.id()), None, None, None, None, true).map_err(db_error)?;
+        for task in tasks {
+            let item = items.get(&task.id());
+            let (href, headers) = match item {
+                Some(item) if !item.dirty => continue,
+                Some(item) if item.etag.is_empty() => (item.href.clone(), vec![]),
+                Some(item) => (item.href.clone(), vec![("If-Match", item.etag.clone())]),
+                None => {
+                    let uid = task_uid(task.id()).map_err(db_error)?;
+                    (
+                        format!("{calendar_href}{}.ics", file_name(&uid)),
+                        vec![("If-None-Match", "*".to_string())],
+                    )
+                }
+            };
+            let body = task_calendar(&task).map_err(db_error)?;
+            let response = self
+                .send("PUT", &href, &headers, Some((CALENDAR_TYPE, &body)))
+                .await?;
+            if response.status == 412 {
+                // Changed on the server in the meantime, the next sync resolves it
+                result.conflicts.push(task.name());
+                continue;
+            } else if !is_success(response.status) {
+                return Err(status_error(response.status));
+            }
+            // Without the ETag the next pull downloads the item once
+            set_caldav_item(&CalDavItem {
+                task: task.id(),
+                project: project.id(),
+                href,
+                etag: response.etag.unwrap_or_default(),
+                dirty: false,
+            })
+            .map_err(db_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Apply the server changes of the project
+fn pull_items(
+    project: &Project,
+    remote_items: Vec<RemoteItem>,
+    result: &mut SyncResult,
+) -> Result<(), String> {
+    let mut items: HashMap<String, CalDavItem> = read_caldav_items(Some(project.id()))
+        .map_err(db_error)?
+        .into_iter()
+        .map(|item| (item.href.clone(), item))
+        .collect();
+    let synced_tasks: HashSet<i64> = items.values().map(|item| item.task).collect();
+
+    // Parents can come after their subtasks, so they linked after pulling all items
+    let mut parents: Vec<(Task, Option<String>)> = vec![];
+    for remote in remote_items {
+        if let Some(item) = items.remove(&remote.href) {
+            if item.etag == remote.etag {
+                continue;
+            }
+            let task = read_task(item.task).map_err(db_error)?;
+            if item.dirty {
+                result.conflicts.push(task.name());
+                if result.policy == ConflictPolicy::Local {
+                    // The push overwrites the server version
+                    set_caldav_item(&CalDavItem {
+                        etag: remote.etag,
+                        ..item
+                    })
+                    .map_err(db_error)?;
+                    continue;
+                }
+            }
+            apply_todo(&task, &remote.todo, result).map_err(db_error)?;
+            set_caldav_item(&CalDavItem {
+                etag: remote.etag,
+                dirty: false,
+                ..item
+            })
+            .map_err(db_error)?;
+            parents.push((task.clone(), remote.todo.parent_uid));
+            result.changed.push(task);
+            continue;
+        }
+
+        // Tasks from a previous sync of this database are linked again
+        let existing_task = match &remote.todo.uid {
+            Some(uid) => find_task(uid).map_err(db_error)?,
+            None => None,
+        }
+        .filter(|task_id| !synced_tasks.contains(task_id))
+        .and_then(|task_id| read_task(task_id).ok())
+        .filter(|task| task.project() == project.id());
+        let mut item = CalDavItem {
+            task: 0,
+            project: project.id(),
+            href: remote.href,
+            etag: remote.etag,
+            dirty: false,
+        };
+        if let Some(task) = existing_task {
+            item.task = task.id();
+            if result.policy == ConflictPolicy::Local {
+                item.dirty = true;
+            } else {
+                apply_todo(&task, &remote.todo, result).map_err(db_error)?;
+                parents.push((task.clone(), remote.todo.parent_uid));
+                result.changed.push(task);
+            }
+            set_caldav_item(&item).map_err(db_error)?;
+        } else if !remote.todo.summary.is_empty() {
+            let task = create_remote_task(project, &remote.todo).map_err(db_error)?;
+            item.task = task.id();
+            set_caldav_item(&item).map_err(db_error)?;
+            result
+                .reminders
+                .append(&mut remote.todo.create_reminders(&task).map_err(db_error)?);
+            parents.push((task.clone(), remote.todo.parent_uid));
+            result.changed.push(task);
+        }
+    }
+
+    // Remaining items are deleted from the server
+    for item in items.into_values() {
+        delete_caldav_item(item.task).map_err(db_error)?;
+        // Subtasks are already in the trash with their parent
+        let task = if let Ok(task) = read_task(item.task) {
+            task
+        } else {
+            continue;
+        };
+        if item.dirty {
+            result.conflicts.push(task.name());
+            if result.policy == ConflictPolicy::Local {
+                // Without the item the push uploads it again
+                continue;
+            }
+        }
+        trash_task(task.id()).map_err(db_error)?;
+        result.deleted.push(task);
+    }
+
+    for (task, parent_uid) in parents {
+        link_parent(project, &task, parent_uid).map_err(db_error)?;
+    }
+    Ok(())
+}
+
+fn create_remote_task(project: &Project, todo: &Todo) -> rusqlite::Result<Task> {
+    let section = read_sections(project.id())?
+        .into_iter()
+        .next()
+        .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let task = create_task(Task::new(&[
+        ("name", &todo.summary),
+        ("project", &project.id()),
+        ("section", &section.id()),
+        ("position", &new_task_position(section.id())),
+        ("description", &todo.description),
+        ("date", &todo.due),
+        ("priority", &todo.priority),
+    ]))?;
+    if todo.done {
+        task.set_done(true);
+        update_task(&task)?;
+    }
+    if let Some(uid) = &todo.uid {
+        set_task_uid(task.id(), uid)?;
+    }
+    Ok(task)
+}
+
+/// Update the task from the server, completing a repeating task creates its next occurrence
+fn apply_todo(task: &Task, todo: &Todo, result: &mut SyncResult) -> rusqlite::Result<()> {
+    let completed = todo.done && !task.done();
+    if !todo.summary.is_empty() {
+        task.set_name(todo.summary.clone());
+    }
+    task.set_description(todo.description.clone());
+    task.set_date(todo.due);
+    task.set_done(todo.done);
+    task.set_priority(todo.priority);
+    update_pulled_task(task)?;
+    if completed {
+        if let Some(next_task) = create_next_occurrence(task)? {
+            result
+                .reminders
+                .append(&mut read_reminders(Some(next_task.id()))?);
+            result.changed.push(next_task);
+        }
+    }
+    Ok(())
+}
+
+fn link_parent(project: &Project, task: &Task, parent_uid: Option<String>) -> rusqlite::Result<()> {
+    let parent = if let Some(uid) = parent_uid {
+        find_task(&uid)?
+            .and_then(|parent_id| read_task(parent_id).ok())
+            .filter(|parent| parent.project() == project.id() && parent.id() != task.id())
+    } else {
+        None
+    };
+    let parent_id = parent.as_ref().map(|parent| parent.id()).unwrap_or(0);
+    if parent_id == task.parent() {
+        return Ok(());
+    }
+
+    if let Some(parent) = parent {
+        task.set_position(new_subtask_position(parent.id()));
+        task.set_section(parent.section());
+    } else {
+        task.set_position(new_task_position(task.section()));
+    }
+    task.set_parent(parent_id);
+    update_pulled_task(task)
+}
+
+/// Keep only safe characters of the UID, so the href is the same on the server
+fn file_name(uid: &str) -> String {
+    uid.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Contents of the elements with the local name, namespace prefixes are ignored
+fn xml_elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut elements = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let tag_end = if let Some(tag_end) = rest.find('>') {
+            tag_end
+        } else {
+            break;
+        };
+        let tag = &rest[..tag_end];
+        let tag_name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if tag_name.rsplit(':').next() != Some(name) {
+            continue;
+        }
+
+        rest = &rest[tag_end + 1..];
+        if tag.ends_with('/') {
+            elements.push("");
+            continue;
+        }
+        let closing_tag = format!("</{tag_name}>");
+        if let Some(end) = rest.find(&closing_tag) {
+            elements.push(&rest[..end]);
+            rest = &rest[end + closing_tag.len()..];
+        }
+    }
+    elements
+}
+
+fn xml_text(content: &str) -> String {
+    let content = content.trim();
+    if let Some(data) = content
+        .strip_prefix("<![CDATA[")
+        .and_then(|content| content.strip_suffix("]]>"))
+    {
+        return data.to_string();
+    }
+    content
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
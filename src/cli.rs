@@ -0,0 +1,368 @@
+/* cli.rs
+ *
+ * Copyright 2023 Iman Salmani
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use gettextrs::gettext;
+use gtk::{gio, glib, prelude::*};
+use std::collections::HashMap;
+
+use crate::db::models::{Project, Record, Section, Task};
+use crate::db::operations::{
+    create_task, new_task_position, read_projects, read_records, read_sections, read_task,
+    read_tasks, start_record, update_record, without_history, TimerPolicy,
+};
+
+const COMMANDS: [&str; 6] = ["add", "list", "timer", "report", "help", "--help"];
+
+/// Changes made by a command that the running instance should show
+pub enum CliChange {
+    Task(i64),
+    TimerStarted(i64),
+    TimerStopped(i64),
+}
+
+pub fn is_command(args: &[String]) -> bool {
+    if let Some(command) = args.first() {
+        COMMANDS.contains(&command.as_str())
+    } else {
+        false
+    }
+}
+
+/// Run the command and print its output, args not include the program name.
+pub fn run(args: &[String]) -> Result<Vec<CliChange>, String> {
+    let (command, args) = args.split_first().unwrap();
+    // The running app doesn't undo commands, its pending changes stay as they were
+    without_history(|| {
+        Ok::<_, rusqlite::Error>(match command.as_str() {
+            "add" => add(args),
+            "list" => list(args),
+            "timer" => timer(args),
+            "report" => report(args),
+            _ => {
+                print_usage();
+                Ok(vec![])
+            }
+        })
+    })
+    .map_err(|err| err.to_string())?
+}
+
+fn print_usage() {
+    println!(
+        "{}:
+  iplan add <name> [--project <name>] [--section <name>] [--date <date>] [--description <text>]
+  iplan list [--today | --week | --date <date>] [--project <name>] [--done]
+  iplan timer start <task-id>
+  iplan timer stop [<task-id>]
+  iplan timer status
+  iplan report [--today | --week | --month] [--project <name>]
+
+{}
+{}",
+        gettext("Usage"),
+        gettext("Date can be today, tomorrow or in YYYY-MM-DD format"),
+        gettext("Reports cover today, the last 7 days or the last 30 days")
+    );
+}
+
+fn add(args: &[String]) -> Result<Vec<CliChange>, String> {
+    let (positional, options) =
+        parse_args(args, &[], &["project", "section", "date", "description"])?;
+    let name = positional
+        .first()
+        .ok_or_else(|| gettext("Task name is required"))?;
+    let project = find_project(options.get("project"))?;
+    let section = find_section(&project, options.get("section"))?;
+    let date = if let Some(date) = options.get("date") {
+        parse_date(date)?.to_unix()
+    } else {
+        0
+    };
+    let description = options.get("description").cloned().unwrap_or_default();
+
+    let task = create_task(Task::new(&[
+        ("name", name),
+        ("project", &project.id()),
+        ("section", &section.id()),
+        ("position", &new_task_position(section.id())),
+        ("description", &description),
+        ("date", &date),
+    ]))
+    .map_err(|err| err.to_string())?;
+    println!("{}", task_line(&task, &project));
+    Ok(vec![CliChange::Task(task.id())])
+}
+
+fn list(args: &[String]) -> Result<Vec<CliChange>, String> {
+    let (_, options) = parse_args(args, &["today", "week", "done"], &["date", "project"])?;
+    let project_id = if options.contains_key("project") {
+        Some(find_project(options.get("project"))?.id())
+    } else {
+        None
+    };
+    let today = today();
+    let time_range = if options.contains_key("today") {
+        Some((today.to_unix(), today.add_days(1).unwrap().to_unix()))
+    } else if options.contains_key("week") {
+        Some((today.to_unix(), today.add_days(7).unwrap().to_unix()))
+    } else if let Some(date) = options.get("date") {
+        let date = parse_date(date)?;
+        Some((date.to_unix(), date.add_days(1).unwrap().to_unix()))
+    } else {
+        None
+    };
+    let done = options.contains_key("done");
+
+    let projects = read_projects(true).map_err(|err| err.to_string())?;
+    let tasks = read_tasks(project_id, None, Some(done), None, time_range, false)
+        .map_err(|err| err.to_string())?;
+    for task in tasks {
+        if let Some(project) = projects
+            .iter()
+            .find(|project| project.id() == task.project())
+        {
+            println!("{}", task_line(&task, project));
+        }
+    }
+    Ok(vec![])
+}
+
+fn timer(args: &[String]) -> Result<Vec<CliChange>, String> {
+    let now = glib::DateTime::now_local().unwrap().to_unix();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("start") => {
+            let task =
+                read_task(parse_task_id(args.get(1))?).map_err(|_| gettext("Task not found"))?;
+            if task.incomplete_record().is_some() {
+                return Err(gettext("Timer is already running for this task"));
+            }
+            let settings = gio::Settings::new("ir.imansalmani.IPlan.State");
+            let policy = TimerPolicy::from_name(&settings.string("timer-policy"));
+            let (record, closed_records) =
+                start_record(task.id(), now, policy).map_err(|err| err.to_string())?;
+            let mut changes = vec![];
+            for closed_record in closed_records {
+                let closed_task = read_task(closed_record.task()).map_err(|err| err.to_string())?;
+                println!("{} {}", gettext("Timer stopped for"), closed_task.name());
+                changes.push(CliChange::TimerStopped(closed_task.id()));
+            }
+            println!("{} {}", gettext("Timer started for"), task.name());
+            changes.push(CliChange::TimerStarted(record.id()));
+            Ok(changes)
+        }
+        Some("stop") => {
+            let task_id = if args.get(1).is_some() {
+                Some(parse_task_id(args.get(1))?)
+            } else {
+                None
+            };
+            let records = read_records(task_id, true, None, None).map_err(|err| err.to_string())?;
+            if records.is_empty() {
+                return Err(gettext("No timer is running"));
+            }
+            let mut changes = vec![];
+            for record in records {
+                // Zero is for open records
+                record.set_duration((now - record.start()).max(1));
+                update_record(&record).map_err(|err| err.to_string())?;
+                let task = read_task(record.task()).map_err(|err| err.to_string())?;
+                println!(
+                    "{} {} ({})",
+                    gettext("Timer stopped for"),
+                    task.name(),
+                    Record::duration_display(record.duration())
+                );
+                changes.push(CliChange::TimerStopped(task.id()));
+            }
+            Ok(changes)
+        }
+        Some("status") => {
+            let records = read_records(None, true, None, None).map_err(|err| err.to_string())?;
+            if records.is_empty() {
+                println!("{}", gettext("No timer is running"));
+            }
+            for record in records {
+                let task = read_task(record.task()).map_err(|err| err.to_string())?;
+                println!(
+                    "{}\t{}\t{}",
+                    task.id(),
+                    task.name(),
+                    Record::duration_display(now - record.start())
+                );
+            }
+            Ok(vec![])
+        }
+        _ => Err(gettext("Use timer start, timer stop or timer status")),
+    }
+}
+
+fn report(args: &[String]) -> Result<Vec<CliChange>, String> {
+    let (_, options) = parse_args(args, &["today", "week", "month"], &["project"])?;
+    let days = if options.contains_key("month") {
+        30
+    } else if options.contains_key("week") {
+        7
+    } else {
+        1
+    };
+    let project_id = if options.contains_key("project") {
+        Some(find_project(options.get("project"))?.id())
+    } else {
+        None
+    };
+    let start = today().add_days(1 - days).unwrap().to_unix();
+
+    // Sum durations by project and then by task with keeping the order of projects
+    let records =
+        read_records(None, false, Some(start - 1), None).map_err(|err| err.to_string())?;
+    let mut tasks: HashMap<i64, (Task, i64)> = HashMap::new();
+    for record in records {
+        if let Some((_, duration)) = tasks.get_mut(&record.task()) {
+            *duration += record.duration();
+        } else if let Ok(task) = read_task(record.task()) {
+            tasks.insert(task.id(), (task, record.duration()));
+        }
+    }
+
+    let mut total = 0;
+    for project in read_projects(true).map_err(|err| err.to_string())? {
+        if project_id.is_some() && project_id != Some(project.id()) {
+            continue;
+        }
+        let mut project_tasks = tasks
+            .values()
+            .filter(|(task, _)| task.project() == project.id())
+            .collect::<Vec<&(Task, i64)>>();
+        if project_tasks.is_empty() {
+            continue;
+        }
+        project_tasks.sort_by(|a, b| b.1.cmp(&a.1));
+        let project_duration: i64 = project_tasks.iter().map(|(_, duration)| duration).sum();
+        total += project_duration;
+        println!(
+            "{}\t{}",
+            project.name(),
+            Record::duration_display(project_duration)
+        );
+        for (task, duration) in project_tasks {
+            println!("  {}\t{}", task.name(), Record::duration_display(*duration));
+        }
+    }
+    println!("{}\t{}", gettext("Total"), Record::duration_display(total));
+    Ok(vec![])
+}
+
+/// Split arguments to positional ones and options, flags are options without value.
+/// Options that are not in flags or value options are rejected.
+fn parse_args(
+    args: &[String],
+    flags: &[&str],
+    value_options: &[&str],
+) -> Result<(Vec<String>, HashMap<String, String>), String> {
+    let mut positional = vec![];
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(option) = arg.strip_prefix("--") {
+            if flags.contains(&option) {
+                options.insert(option.to_string(), String::new());
+            } else if !value_options.contains(&option) {
+                return Err(gettext("Unknown option --{}").replace("{}", option));
+            } else if let Some(value) = args.next() {
+                options.insert(option.to_string(), value.to_string());
+            } else {
+                return Err(gettext("Missing value for --{}").replace("{}", option));
+            }
+        } else {
+            positional.push(arg.to_string());
+        }
+    }
+    Ok((positional, options))
+}
+
+fn parse_task_id(arg: Option<&String>) -> Result<i64, String> {
+    arg.and_then(|arg| arg.parse::<i64>().ok())
+        .ok_or_else(|| gettext("Task id is required"))
+}
+
+fn today() -> glib::DateTime {
+    let now = glib::DateTime::now_local().unwrap();
+    glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0).unwrap()
+}
+
+fn parse_date(text: &str) -> Result<glib::DateTime, String> {
+    match text {
+        "today" => Ok(today()),
+        "tomorrow" => Ok(today().add_days(1).unwrap()),
+        _ => {
+            let parts = text
+                .split('-')
+                .map(|part| part.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .unwrap_or_default();
+            if parts.len() == 3 {
+                if let Ok(date) =
+                    glib::DateTime::from_local(parts[0], parts[1], parts[2], 0, 0, 0.0)
+                {
+                    return Ok(date);
+                }
+            }
+            Err(gettext("Invalid date “{}”").replace("{}", text))
+        }
+    }
+}
+
+fn find_project(name: Option<&String>) -> Result<Project, String> {
+    let projects = read_projects(true).map_err(|err| err.to_string())?;
+    let project = if let Some(name) = name {
+        projects
+            .into_iter()
+            .find(|project| project.name().to_lowercase() == name.to_lowercase())
+    } else {
+        projects.into_iter().next()
+    };
+    project.ok_or_else(|| gettext("Project not found"))
+}
+
+fn find_section(project: &Project, name: Option<&String>) -> Result<Section, String> {
+    let sections = read_sections(project.id()).map_err(|err| err.to_string())?;
+    let section = if let Some(name) = name {
+        sections
+            .into_iter()
+            .find(|section| section.name().to_lowercase() == name.to_lowercase())
+    } else {
+        sections.into_iter().next()
+    };
+    section.ok_or_else(|| gettext("Section not found"))
+}
+
+fn task_line(task: &Task, project: &Project) -> String {
+    let done = if task.done() { "x" } else { " " };
+    let date = task
+        .date_datetime()
+        .map(|date| date.format("%F").unwrap().to_string())
+        .unwrap_or_default();
+    format!(
+        "[{done}] {}\t{}\t{}\t{date}",
+        task.id(),
+        task.name(),
+        project.name()
+    )
+}
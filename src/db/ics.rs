@@ -0,0 +1,821 @@
+use gettextrs::gettext;
+use gtk::glib;
+use rusqlite::{Connection, Result};
+use std::collections::HashSet;
+
+use crate::db::get_connection;
+use crate::db::models::{Project, Reminder, Repeat, RepeatFrequency, Task};
+use crate::db::operations::{
+    create_reminder, read_projects, read_reminders, read_task_uid, read_tasks,
+};
+
+const PRODUCT_ID: &str = "-//Iman Salmani//IPlan//EN";
+const WEEKDAYS: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+
+/// Imported tasks keep their UID and the others use one based on the task id,
+/// so exporting again gives the same UIDs
+pub fn task_uid(task_id: i64) -> Result<String> {
+    if let Some(uid) = read_task_uid(task_id)? {
+        Ok(uid)
+    } else {
+        Ok(format!("iplan-task-{task_id}@imansalmani.ir"))
+    }
+}
+
+/// Find the task of a UID from previous imports or exports
+pub fn find_task(uid: &str) -> Result<Option<i64>> {
+    find_task_in(&get_connection(), uid)
+}
+
+fn find_task_in(conn: &Connection, uid: &str) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare("SELECT task FROM task_uids WHERE uid = ?")?;
+    let mut rows = stmt.query([uid])?;
+    if let Some(row) = rows.next()? {
+        return Ok(Some(row.get(0)?));
+    }
+    let task_id = uid
+        .strip_prefix("iplan-task-")
+        .and_then(|uid| uid.strip_suffix("@imansalmani.ir"))
+        .and_then(|id| id.parse::<i64>().ok());
+    if let Some(task_id) = task_id {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM tasks WHERE id = ? AND deleted_at = 0)",
+            [task_id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(Some(task_id));
+        }
+    }
+    Ok(None)
+}
+
+/// Export tasks with a due date as VTODO and their reminders as VALARM.
+/// Without project_id tasks of all projects included.
+pub fn export_ics(project_id: Option<i64>) -> Result<String> {
+    let projects = read_projects(true)?;
+    let tasks = read_tasks(project_id, None, None, None, None, false)?;
+    let tasks: Vec<&Task> = tasks.iter().filter(|task| task.date() != 0).collect();
+    let exported: HashSet<i64> = tasks.iter().map(|task| task.id()).collect();
+    let mut lines = vec![];
+    for task in tasks {
+        lines.append(&mut todo_lines(task, &projects, Some(&exported))?);
+    }
+    Ok(calendar(lines))
+}
+
+/// Calendar object of a single task for CalDAV, it is included even without a due date
+pub fn task_calendar(task: &Task) -> Result<String> {
+    let projects = read_projects(true)?;
+    // The parent is in the same calendar, as subtasks stay in the project of their parent
+    Ok(calendar(todo_lines(task, &projects, None)?))
+}
+
+fn calendar(components: Vec<String>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{PRODUCT_ID}"),
+    ];
+    lines.extend(components);
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold_line(&line));
+    }
+    ics
+}
+
+/// The parent is linked when it is in the `exported` tasks, or always without them
+fn todo_lines(
+    task: &Task,
+    projects: &[Project],
+    exported: Option<&HashSet<i64>>,
+) -> Result<Vec<String>> {
+    let now = utc_format(&glib::DateTime::now_utc().unwrap());
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", task_uid(task.id())?),
+        format!("DTSTAMP:{now}"),
+        format!("SUMMARY:{}", escape_text(&task.name())),
+    ];
+    if !task.description().is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(&task.description())));
+    }
+    if let Some(priority) = ics_priority(task.priority()) {
+        lines.push(format!("PRIORITY:{priority}"));
+    }
+    if task.done() {
+        lines.push("STATUS:COMPLETED".to_string());
+    } else {
+        lines.push("STATUS:NEEDS-ACTION".to_string());
+    }
+    let parent_exported = match exported {
+        Some(exported) => exported.contains(&task.parent()),
+        None => true,
+    };
+    if task.parent() != 0 && parent_exported {
+        lines.push(format!(
+            "RELATED-TO;RELTYPE=PARENT:{}",
+            task_uid(task.parent())?
+        ));
+    }
+    if let Some(project) = projects
+        .iter()
+        .find(|project| project.id() == task.project())
+    {
+        lines.push(format!("CATEGORIES:{}", escape_text(&project.name())));
+    }
+    if let Some(due) = task.date_datetime() {
+        lines.push(format!("DUE:{}", utc_format(&due)));
+        if let Some(rrule) = task.repeat_rule().and_then(|repeat| rrule(&repeat, &due)) {
+            lines.push(format!("RRULE:{rrule}"));
+        }
+        // Alarms without due date can't be relative and clients may not show them
+        for reminder in read_reminders(Some(task.id()))? {
+            lines.append(&mut alarm_lines(task, &reminder));
+        }
+    }
+    lines.push("END:VTODO".to_string());
+    Ok(lines)
+}
+
+/// iCalendar priorities are 1 for the highest and 9 for the lowest
+fn ics_priority(priority: u8) -> Option<u8> {
+    match priority {
+        1 => Some(9),
+        2 => Some(5),
+        3 => Some(1),
+        _ => None,
+    }
+}
+
+fn task_priority(ics_priority: u8) -> u8 {
+    match ics_priority {
+        1..=4 => 3,
+        5 => 2,
+        6..=9 => 1,
+        _ => 0,
+    }
+}
+
+fn alarm_lines(task: &Task, reminder: &Reminder) -> Vec<String> {
+    let trigger = if reminder.relative() {
+        let offset = reminder.date_offset();
+        if offset == 0 {
+            "TRIGGER;RELATED=END:PT0S".to_string()
+        } else {
+            format!("TRIGGER;RELATED=END:-PT{}M", offset / 60)
+        }
+    } else {
+        let datetime = glib::DateTime::from_unix_utc(reminder.datetime()).unwrap();
+        format!("TRIGGER;VALUE=DATE-TIME:{}", utc_format(&datetime))
+    };
+    vec![
+        "BEGIN:VALARM".to_string(),
+        "ACTION:DISPLAY".to_string(),
+        format!("DESCRIPTION:{}", escape_text(&task.name())),
+        trigger,
+        "END:VALARM".to_string(),
+    ]
+}
+
+/// Convert the repeat rule to RRULE, after completion frequency doesn't have an equivalent
+fn rrule(repeat: &Repeat, date: &glib::DateTime) -> Option<String> {
+    let interval = repeat.interval;
+    let rule = match repeat.frequency {
+        RepeatFrequency::Daily => format!("FREQ=DAILY;INTERVAL={interval}"),
+        RepeatFrequency::Weekly => {
+            let mut rule = format!("FREQ=WEEKLY;INTERVAL={interval}");
+            if repeat.weekdays != 0 {
+                let weekdays = (1..=7)
+                    .filter(|day| repeat.has_weekday(*day))
+                    .map(|day| WEEKDAYS[day as usize - 1])
+                    .collect::<Vec<&str>>()
+                    .join(",");
+                rule.push_str(&format!(";BYDAY={weekdays}"));
+            }
+            rule
+        }
+        RepeatFrequency::Monthly => format!("FREQ=MONTHLY;INTERVAL={interval}"),
+        RepeatFrequency::MonthlyWeekday => {
+            let nth = (date.day_of_month() - 1) / 7 + 1;
+            let weekday = WEEKDAYS[date.day_of_week() as usize - 1];
+            format!("FREQ=MONTHLY;INTERVAL={interval};BYDAY={nth}{weekday}")
+        }
+        RepeatFrequency::Yearly => format!("FREQ=YEARLY;INTERVAL={interval}"),
+        RepeatFrequency::AfterDone => return None,
+    };
+    Some(rule)
+}
+
+fn utc_format(datetime: &glib::DateTime) -> String {
+    datetime
+        .to_utc()
+        .unwrap()
+        .format("%Y%m%dT%H%M%SZ")
+        .unwrap()
+        .to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold lines longer than 75 octets and end them with CRLF
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Result of importing an iCalendar file
+#[derive(Default)]
+pub struct IcsImport {
+    pub tasks: Vec<Task>,
+    pub reminders: Vec<Reminder>,
+    /// Summary of the skipped item and the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Clone)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Clone, Default)]
+struct Component {
+    name: String,
+    properties: Vec<Property>,
+    children: Vec<Component>,
+}
+
+impl Component {
+    fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+}
+
+/// Task fields of a VTODO item
+pub struct Todo {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: String,
+    /// Unix time of the due date or 0
+    pub due: i64,
+    pub done: bool,
+    /// Task priority from 0 to 3
+    pub priority: u8,
+    pub parent_uid: Option<String>,
+    alarms: Vec<Component>,
+}
+
+impl Todo {
+    fn new(component: &Component) -> Self {
+        let text = |name: &str| {
+            component
+                .property(name)
+                .map(|property| unescape_text(&property.value))
+                .unwrap_or_default()
+        };
+        let parent_uid = component
+            .property("RELATED-TO")
+            .filter(|property| property.param("RELTYPE").unwrap_or("PARENT") == "PARENT")
+            .map(|property| property.value.clone());
+        Self {
+            uid: component
+                .property("UID")
+                .map(|property| property.value.clone()),
+            summary: text("SUMMARY"),
+            description: text("DESCRIPTION"),
+            due: component
+                .property("DUE")
+                .and_then(parse_datetime)
+                .map(|due| due.to_unix())
+                .unwrap_or(0),
+            done: component
+                .property("STATUS")
+                .map(|property| property.value == "COMPLETED")
+                .unwrap_or(false)
+                || component.property("COMPLETED").is_some(),
+            priority: component
+                .property("PRIORITY")
+                .and_then(|property| property.value.trim().parse::<u8>().ok())
+                .map(task_priority)
+                .unwrap_or(0),
+            parent_uid,
+            alarms: component
+                .children
+                .iter()
+                .filter(|child| child.name == "VALARM")
+                .map(Component::clone)
+                .collect(),
+        }
+    }
+
+    /// Create reminders from the VALARMs, relative ones need the due date on the task
+    pub fn create_reminders(&self, task: &Task) -> Result<Vec<Reminder>> {
+        let mut reminders = vec![];
+        for alarm in &self.alarms {
+            if let Some(reminder) = alarm_reminder(alarm, task) {
+                reminders.push(create_reminder(reminder)?);
+            }
+        }
+        Ok(reminders)
+    }
+}
+
+pub fn parse_todos(content: &str) -> Vec<Todo> {
+    parse_components(content)
+        .iter()
+        .flat_map(|calendar| &calendar.children)
+        .filter(|component| component.name == "VTODO")
+        .map(Todo::new)
+        .collect()
+}
+
+/// Import VTODO items as tasks in the section and their VALARMs as reminders.
+/// Items already imported or exported from this database are skipped.
+/// Everything is written in one transaction, nothing changes when it fails.
+pub fn import_ics(content: &str, project_id: i64, section_id: i64) -> Result<IcsImport> {
+    let todos = parse_todos(content);
+    let mut conn = get_connection();
+    let transaction = conn.transaction()?;
+    let result = write_todos(&transaction, todos, project_id, section_id)?;
+    transaction.commit()?;
+    Ok(result)
+}
+
+fn write_todos(
+    conn: &Connection,
+    todos: Vec<Todo>,
+    project_id: i64,
+    section_id: i64,
+) -> Result<IcsImport> {
+    let mut result = IcsImport::default();
+    let now = glib::DateTime::now_local().unwrap().to_unix();
+
+    // Parents can come after their subtasks, so they linked after creating all tasks
+    let mut uids: Vec<(String, i64)> = vec![];
+    let mut parents: Vec<(Task, String)> = vec![];
+    for todo in todos {
+        if todo.summary.is_empty() {
+            result
+                .skipped
+                .push((String::new(), gettext("Missing summary")));
+            continue;
+        }
+        if let Some(uid) = &todo.uid {
+            if find_task_in(conn, uid)?.is_some()
+                || uids.iter().any(|(imported, _)| imported == uid)
+            {
+                result
+                    .skipped
+                    .push((todo.summary, gettext("Already exists")));
+                continue;
+            }
+        }
+
+        let position: i32 = conn.query_row(
+            "SELECT coalesce(max(position), -1) + 1 FROM tasks WHERE section = ?",
+            [section_id],
+            |row| row.get(0),
+        )?;
+        let completed_at = if todo.done { now } else { 0 };
+        conn.execute(
+            "INSERT INTO tasks(name, done, project, section, position, description, date, priority,
+                created_at, updated_at, completed_at)
+            VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?9,?10)",
+            (
+                &todo.summary,
+                todo.done,
+                project_id,
+                section_id,
+                position,
+                &todo.description,
+                todo.due,
+                todo.priority,
+                now,
+                completed_at,
+            ),
+        )?;
+        let task = conn.query_row(
+            "SELECT * FROM tasks WHERE id = ?",
+            [conn.last_insert_rowid()],
+            |row| Task::try_from(row),
+        )?;
+        if let Some(uid) = &todo.uid {
+            conn.execute(
+                "INSERT OR REPLACE INTO task_uids(task, uid) VALUES (?1,?2)",
+                (task.id(), uid),
+            )?;
+            uids.push((uid.clone(), task.id()));
+        }
+        for alarm in &todo.alarms {
+            if let Some(reminder) = alarm_reminder(alarm, &task) {
+                conn.execute(
+                    "INSERT INTO reminders(datetime, task, priority, repeat, relative, date_offset)
+                    VALUES (?1,?2,?3,?4,?5,?6)",
+                    (
+                        reminder.datetime(),
+                        reminder.task(),
+                        reminder.priority(),
+                        reminder.repeat(),
+                        reminder.relative(),
+                        reminder.date_offset(),
+                    ),
+                )?;
+                reminder.set_id(conn.last_insert_rowid());
+                result.reminders.push(reminder);
+            }
+        }
+        if let Some(parent_uid) = todo.parent_uid {
+            parents.push((task.clone(), parent_uid));
+        }
+        result.tasks.push(task);
+    }
+
+    for (task, parent_uid) in parents {
+        let parent_id = if let Some((_, id)) = uids.iter().find(|(uid, _)| uid == &parent_uid) {
+            Some(*id)
+        } else {
+            find_task_in(conn, &parent_uid)?
+        };
+        let parent_id = if let Some(parent_id) = parent_id {
+            parent_id
+        } else {
+            continue;
+        };
+        // Parents out of the section would move the task out of the import target,
+        // and parents under the task would make a cycle
+        let linkable: bool = conn.query_row(
+            "WITH RECURSIVE ancestors(id) AS (
+                SELECT ?1
+                UNION
+                SELECT tasks.parent FROM tasks
+                    JOIN ancestors ON tasks.id = ancestors.id
+                    WHERE tasks.parent != 0
+            )
+            SELECT EXISTS(SELECT 1 FROM tasks WHERE id = ?1 AND section = ?3 AND deleted_at = 0)
+                AND NOT EXISTS(SELECT 1 FROM ancestors WHERE id = ?2)",
+            (parent_id, task.id(), section_id),
+            |row| row.get(0),
+        )?;
+        if !linkable {
+            continue;
+        }
+        let position: i32 = conn.query_row(
+            "SELECT coalesce(max(position), -1) + 1 FROM tasks WHERE parent = ?",
+            [parent_id],
+            |row| row.get(0),
+        )?;
+        // Close the gap the task leaves in the section
+        conn.execute(
+            "UPDATE tasks SET position = position - 1
+            WHERE section = ?1 AND position > (SELECT position FROM tasks WHERE id = ?2)",
+            (task.section(), task.id()),
+        )?;
+        conn.execute(
+            "UPDATE tasks SET parent = ?2, position = ?3 WHERE id = ?1",
+            (task.id(), parent_id, position),
+        )?;
+        task.set_parent(parent_id);
+        task.set_position(position);
+    }
+
+    Ok(result)
+}
+
+fn alarm_reminder(alarm: &Component, task: &Task) -> Option<Reminder> {
+    let trigger = alarm.property("TRIGGER")?;
+    let reminder = Reminder::default();
+    reminder.set_task(task.id());
+    if trigger.param("VALUE") == Some("DATE-TIME") {
+        reminder.set_datetime(parse_datetime(trigger)?.to_unix());
+    } else {
+        // Relative triggers use the due date for both start and end
+        let due = task.date();
+        if due == 0 {
+            return None;
+        }
+        let offset = parse_duration(&trigger.value)?;
+        if offset > 0 {
+            reminder.set_datetime(due + offset);
+        } else {
+            reminder.set_relative(true);
+            reminder.set_date_offset(-offset);
+            reminder.set_datetime(due + offset);
+        }
+    }
+    Some(reminder)
+}
+
+fn parse_components(content: &str) -> Vec<Component> {
+    // Unfold lines that continue with a space or tab
+    let content = content
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut roots = vec![];
+    let mut stack: Vec<Component> = vec![];
+    for line in content.lines() {
+        let property = if let Some(property) = parse_property(line) {
+            property
+        } else {
+            continue;
+        };
+        match property.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                name: property.value.to_uppercase(),
+                ..Default::default()
+            }),
+            "END" => {
+                if let Some(component) = stack.pop() {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(component);
+                    } else {
+                        roots.push(component);
+                    }
+                }
+            }
+            _ => {
+                if let Some(component) = stack.last_mut() {
+                    component.properties.push(property);
+                }
+            }
+        }
+    }
+    roots
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // Find the value separator outside of quoted parameter values
+    let mut quoted = false;
+    let mut separator = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => {
+                separator = Some(i);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (head, value) = line.split_at(separator?);
+    let mut head = head.split(';');
+    let name = head.next()?.to_uppercase();
+    let params = head
+        .filter_map(|param| param.split_once('='))
+        .map(|(name, value)| (name.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: value[1..].to_string(),
+    })
+}
+
+/// Parse DATE and DATE-TIME values, floating and TZID times are considered local
+fn parse_datetime(property: &Property) -> Option<glib::DateTime> {
+    let value = property.value.trim();
+    let number = |range: std::ops::Range<usize>| value.get(range)?.parse::<i32>().ok();
+    let (year, month, day) = (number(0..4)?, number(4..6)?, number(6..8)?);
+    if value.len() < 15 {
+        return glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok();
+    }
+    let (hour, minute, second) = (number(9..11)?, number(11..13)?, number(13..15)?);
+    if value.ends_with('Z') {
+        glib::DateTime::from_utc(year, month, day, hour, minute, second as f64)
+            .ok()?
+            .to_local()
+            .ok()
+    } else {
+        glib::DateTime::from_local(year, month, day, hour, minute, second as f64).ok()
+    }
+}
+
+/// Parse DURATION values to seconds e.g. -PT15M or P1DT2H
+fn parse_duration(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (sign, value) = if let Some(value) = value.strip_prefix('-') {
+        (-1, value)
+    } else {
+        (1, value.strip_prefix('+').unwrap_or(value))
+    };
+    let value = value.strip_prefix('P')?;
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'T' => continue,
+            'W' => 604800,
+            'D' => 86400,
+            'H' => 3600,
+            'M' => 60,
+            'S' => 1,
+            _ => return None,
+        };
+        seconds += number.parse::<i64>().ok()? * unit;
+        number.clear();
+    }
+    Some(sign * seconds)
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(value: &str) -> Option<glib::DateTime> {
+        parse_datetime(&parse_property(&format!("DUE:{value}")).unwrap())
+    }
+
+    #[test]
+    fn property_with_quoted_params() {
+        let property =
+            parse_property("attendee;CN=\"Doe: John\";role=CHAIR:mailto:john@example.com").unwrap();
+        assert_eq!(property.name, "ATTENDEE");
+        assert_eq!(property.param("CN"), Some("Doe: John"));
+        assert_eq!(property.param("ROLE"), Some("CHAIR"));
+        assert_eq!(property.value, "mailto:john@example.com");
+        assert!(parse_property("no value").is_none());
+    }
+
+    #[test]
+    fn todos_from_folded_calendar() {
+        let content = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Event\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VTODO\r\n\
+            UID:task-1\r\n\
+            SUMMARY:Buy milk\\, eggs\r\n  and bread\r\n\
+            DESCRIPTION:First line\\nSecond\\; line\n\tcontinued\r\n\
+            STATUS:COMPLETED\r\n\
+            PRIORITY:1\r\n\
+            RELATED-TO:task-0\r\n\
+            BEGIN:VALARM\r\n\
+            TRIGGER:-PT15M\r\n\
+            END:VALARM\r\n\
+            END:VTODO\r\n\
+            BEGIN:VTODO\r\n\
+            SUMMARY:Child\r\n\
+            RELATED-TO;RELTYPE=CHILD:task-1\r\n\
+            END:VTODO\r\n\
+            END:VCALENDAR\r\n";
+        let todos = parse_todos(content);
+        assert_eq!(todos.len(), 2);
+
+        let todo = &todos[0];
+        assert_eq!(todo.uid.as_deref(), Some("task-1"));
+        assert_eq!(todo.summary, "Buy milk, eggs and bread");
+        assert_eq!(todo.description, "First line\nSecond; linecontinued");
+        assert!(todo.done);
+        assert_eq!(todo.priority, 3);
+        assert_eq!(todo.parent_uid.as_deref(), Some("task-0"));
+        assert_eq!(todo.alarms.len(), 1);
+
+        let todo = &todos[1];
+        assert_eq!(todo.uid, None);
+        assert!(!todo.done);
+        assert_eq!(todo.parent_uid, None);
+        assert_eq!(todo.due, 0);
+    }
+
+    #[test]
+    fn datetime_values() {
+        let utc = datetime("20240131T120000Z").unwrap();
+        assert_eq!(
+            utc.to_unix(),
+            glib::DateTime::from_utc(2024, 1, 31, 12, 0, 0.0)
+                .unwrap()
+                .to_unix()
+        );
+        let floating = datetime("20240131T120000").unwrap();
+        assert_eq!((floating.ymd(), floating.hour()), ((2024, 1, 31), 12));
+        let date = datetime("20240229").unwrap();
+        assert_eq!((date.ymd(), date.hour()), ((2024, 2, 29), 0));
+        assert!(datetime("2024").is_none());
+        assert!(datetime("20240230").is_none());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("-PT15M"), Some(-900));
+        assert_eq!(parse_duration("P1DT2H"), Some(93600));
+        assert_eq!(parse_duration("+PT30S"), Some(30));
+        assert_eq!(parse_duration("P1W"), Some(604800));
+        assert_eq!(parse_duration("PT15X"), None);
+        assert_eq!(parse_duration("15M"), None);
+    }
+
+    #[test]
+    fn text_escaping() {
+        let text = "a;b,c\\d\nnew line";
+        assert_eq!(escape_text(text), "a\\;b\\,c\\\\d\\nnew line");
+        assert_eq!(unescape_text(&escape_text(text)), text);
+        assert_eq!(unescape_text("Line\\NBreak\\"), "Line\nBreak");
+    }
+
+    #[test]
+    fn line_folding() {
+        let folded = fold_line(&"a".repeat(100));
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(
+            lines,
+            [
+                "a".repeat(75),
+                format!(" {}", "a".repeat(25)),
+                String::new()
+            ]
+        );
+
+        // Multi-byte characters are not split
+        let folded = fold_line(&format!("{}é", "a".repeat(74)));
+        assert_eq!(folded, format!("{}\r\n é\r\n", "a".repeat(74)));
+
+        // Unfolding joins them back
+        let value = "é".repeat(50);
+        let content = format!("BEGIN:X\r\n{}END:X\r\n", fold_line(&format!("A:{value}")));
+        assert_eq!(parse_components(&content)[0].properties[0].value, value);
+    }
+
+    #[test]
+    fn repeat_rules() {
+        // Monday, January 8 2024 is the second Monday of the month
+        let date = glib::DateTime::from_local(2024, 1, 8, 9, 0, 0.0).unwrap();
+        let weekly = Repeat::new(RepeatFrequency::Weekly, 2, 0b1_0001);
+        assert_eq!(
+            rrule(&weekly, &date).as_deref(),
+            Some("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR")
+        );
+        let monthly = Repeat::new(RepeatFrequency::MonthlyWeekday, 1, 0);
+        assert_eq!(
+            rrule(&monthly, &date).as_deref(),
+            Some("FREQ=MONTHLY;INTERVAL=1;BYDAY=2MO")
+        );
+        let after_done = Repeat::new(RepeatFrequency::AfterDone, 1, 0);
+        assert_eq!(rrule(&after_done, &date), None);
+    }
+
+    #[test]
+    fn priorities() {
+        for priority in 0..=3 {
+            let ics = ics_priority(priority);
+            assert_eq!(ics.map(task_priority).unwrap_or(0), priority);
+        }
+        assert_eq!(task_priority(0), 0);
+        assert_eq!(task_priority(4), 3);
+        assert_eq!(task_priority(7), 1);
+    }
+}
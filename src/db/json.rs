@@ -0,0 +1,649 @@
+use gettextrs::gettext;
+use gtk::glib;
+use rusqlite::{params, Connection, Result, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::config::VERSION;
+use crate::db::get_connection;
+
+/// Increase only when the meaning of existing fields changes,
+/// new fields need a default value so older backups stay readable.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    format_version: u32,
+    #[serde(default)]
+    app_version: String,
+    #[serde(default)]
+    exported_at: i64,
+    #[serde(default)]
+    projects: Vec<ProjectData>,
+    #[serde(default)]
+    sections: Vec<SectionData>,
+    #[serde(default)]
+    tasks: Vec<TaskData>,
+    #[serde(default)]
+    records: Vec<RecordData>,
+    #[serde(default)]
+    reminders: Vec<ReminderData>,
+    #[serde(default)]
+    tags: Vec<TagData>,
+    #[serde(default)]
+    task_tags: Vec<TaskTagData>,
+    #[serde(default)]
+    task_dependencies: Vec<TaskDependencyData>,
+    #[serde(default)]
+    filters: Vec<FilterData>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ProjectData {
+    id: i64,
+    name: String,
+    archive: bool,
+    index: i32,
+    icon: String,
+    description: String,
+    /// Goal duration in seconds
+    goal: i64,
+    /// 0 for daily, 1 weekly and 2 monthly goals
+    goal_period: u8,
+    /// Unix time of moving to the trash or 0
+    deleted_at: i64,
+    /// Items trashed together share it, 0 in backups of older versions
+    trash_batch: i64,
+}
+
+impl ProjectData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            archive: row.get("archive")?,
+            index: row.get("i")?,
+            icon: row.get("icon")?,
+            description: row.get("description")?,
+            goal: row.get("goal")?,
+            goal_period: row.get("goal_period")?,
+            deleted_at: row.get("deleted_at")?,
+            trash_batch: row.get("trash_batch")?,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SectionData {
+    id: i64,
+    name: String,
+    project: i64,
+    index: i32,
+    priority_sort: bool,
+    deleted_at: i64,
+    trash_batch: i64,
+}
+
+impl SectionData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            project: row.get("project")?,
+            index: row.get("i")?,
+            priority_sort: row.get("priority_sort")?,
+            deleted_at: row.get("deleted_at")?,
+            trash_batch: row.get("trash_batch")?,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TaskData {
+    id: i64,
+    name: String,
+    done: bool,
+    project: i64,
+    section: i64,
+    position: i32,
+    suspended: bool,
+    /// Id of the parent task or 0 for top level tasks
+    parent: i64,
+    description: String,
+    date: i64,
+    repeat: String,
+    priority: u8,
+    /// Estimated duration in seconds
+    estimate: i64,
+    deleted_at: i64,
+    trash_batch: i64,
+    /// Unix times, 0 in backups of older versions
+    created_at: i64,
+    updated_at: i64,
+    completed_at: i64,
+    /// UID of a task imported from iCalendar or CalDAV
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+}
+
+impl TaskData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            done: row.get("done")?,
+            project: row.get("project")?,
+            section: row.get("section")?,
+            position: row.get("position")?,
+            suspended: row.get("suspended")?,
+            parent: row.get("parent")?,
+            description: row.get("description")?,
+            date: row.get("date")?,
+            repeat: row.get("repeat")?,
+            priority: row.get("priority")?,
+            estimate: row.get("estimate")?,
+            deleted_at: row.get("deleted_at")?,
+            trash_batch: row.get("trash_batch")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+            completed_at: row.get("completed_at")?,
+            uid: row.get("uid")?,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RecordData {
+    id: i64,
+    start: i64,
+    duration: i64,
+    task: i64,
+}
+
+impl RecordData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            start: row.get("start")?,
+            duration: row.get("duration")?,
+            task: row.get("task")?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ReminderData {
+    id: i64,
+    datetime: i64,
+    past: bool,
+    task: i64,
+    priority: u8,
+    repeat: String,
+    relative: bool,
+    date_offset: i64,
+}
+
+impl Default for ReminderData {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            datetime: 0,
+            past: false,
+            task: 0,
+            priority: 1,
+            repeat: String::new(),
+            relative: false,
+            date_offset: 0,
+        }
+    }
+}
+
+impl ReminderData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            datetime: row.get("datetime")?,
+            past: row.get("past")?,
+            task: row.get("task")?,
+            priority: row.get("priority")?,
+            repeat: row.get("repeat")?,
+            relative: row.get("relative")?,
+            date_offset: row.get("date_offset")?,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TagData {
+    id: i64,
+    name: String,
+}
+
+impl TagData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TaskTagData {
+    task: i64,
+    tag: i64,
+}
+
+impl TaskTagData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            task: row.get("task")?,
+            tag: row.get("tag")?,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FilterData {
+    id: i64,
+    name: String,
+    query: String,
+}
+
+impl FilterData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            query: row.get("query")?,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TaskDependencyData {
+    task: i64,
+    /// Id of the task that must be done first
+    blocker: i64,
+}
+
+impl TaskDependencyData {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            task: row.get("task")?,
+            blocker: row.get("blocker")?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Remove the current data and keep the ids of the backup
+    Replace,
+    /// Add the backup next to the current data with new ids
+    Merge,
+}
+
+/// Result of importing a JSON backup
+pub struct JsonImport {
+    pub projects: usize,
+    pub tasks: usize,
+    /// Ids of the reminders that should be scheduled
+    pub reminders: Vec<i64>,
+}
+
+/// Export the full data model as a versioned JSON document
+pub fn export_json() -> Result<String> {
+    let conn = get_connection();
+    let backup = Backup {
+        format_version: FORMAT_VERSION,
+        app_version: VERSION.to_string(),
+        exported_at: glib::DateTime::now_utc().unwrap().to_unix(),
+        projects: query(
+            &conn,
+            "SELECT * FROM projects ORDER BY i",
+            ProjectData::from_row,
+        )?,
+        sections: query(
+            &conn,
+            "SELECT * FROM sections ORDER BY project, i",
+            SectionData::from_row,
+        )?,
+        tasks: query(
+            &conn,
+            "SELECT tasks.*, task_uids.uid FROM tasks
+            LEFT JOIN task_uids ON task_uids.task = tasks.id ORDER BY tasks.id",
+            TaskData::from_row,
+        )?,
+        records: query(
+            &conn,
+            "SELECT * FROM records ORDER BY id",
+            RecordData::from_row,
+        )?,
+        reminders: query(
+            &conn,
+            "SELECT * FROM reminders ORDER BY id",
+            ReminderData::from_row,
+        )?,
+        tags: query(&conn, "SELECT * FROM tags ORDER BY id", TagData::from_row)?,
+        task_tags: query(
+            &conn,
+            "SELECT * FROM task_tags ORDER BY task, tag",
+            TaskTagData::from_row,
+        )?,
+        task_dependencies: query(
+            &conn,
+            "SELECT * FROM task_dependencies ORDER BY task, blocker",
+            TaskDependencyData::from_row,
+        )?,
+        filters: query(
+            &conn,
+            "SELECT * FROM filters ORDER BY id",
+            FilterData::from_row,
+        )?,
+    };
+    Ok(serde_json::to_string_pretty(&backup).unwrap())
+}
+
+fn query<T>(conn: &Connection, sql: &str, from_row: fn(&Row) -> Result<T>) -> Result<Vec<T>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([], from_row)?;
+    rows.collect()
+}
+
+/// Import a JSON backup in one transaction, nothing changes when it fails
+pub fn import_json(content: &str, mode: ImportMode) -> Result<JsonImport, String> {
+    let backup: Backup = serde_json::from_str(content)
+        .map_err(|err| gettext("Invalid backup file: {}").replace("{}", &err.to_string()))?;
+    if backup.format_version > FORMAT_VERSION {
+        return Err(gettext(
+            "The backup is made by a newer version of IPlan, please update application",
+        ));
+    }
+    if !has_valid_references(&backup) {
+        return Err(gettext(
+            "The backup has items without their project, section or task",
+        ));
+    }
+    if has_parent_cycle(&backup) {
+        return Err(gettext(
+            "The backup has tasks that are parents of themselves",
+        ));
+    }
+
+    let mut conn = get_connection();
+    let transaction = conn.transaction().map_err(|err| err.to_string())?;
+    let result = write_backup(&transaction, &backup, mode).map_err(|err| err.to_string())?;
+    transaction.commit().map_err(|err| err.to_string())?;
+    Ok(result)
+}
+
+fn has_valid_references(backup: &Backup) -> bool {
+    let projects: HashSet<i64> = backup.projects.iter().map(|project| project.id).collect();
+    let sections: HashSet<i64> = backup.sections.iter().map(|section| section.id).collect();
+    let tasks: HashSet<i64> = backup.tasks.iter().map(|task| task.id).collect();
+    let tags: HashSet<i64> = backup.tags.iter().map(|tag| tag.id).collect();
+    // Tasks from old versions may not have a section
+    backup
+        .sections
+        .iter()
+        .all(|section| projects.contains(&section.project))
+        && backup.tasks.iter().all(|task| {
+            projects.contains(&task.project)
+                && (task.section == 0 || sections.contains(&task.section))
+                && (task.parent == 0 || tasks.contains(&task.parent))
+        })
+        && backup
+            .records
+            .iter()
+            .all(|record| tasks.contains(&record.task))
+        && backup
+            .reminders
+            .iter()
+            .all(|reminder| tasks.contains(&reminder.task))
+        && backup
+            .task_tags
+            .iter()
+            .all(|task_tag| tasks.contains(&task_tag.task) && tags.contains(&task_tag.tag))
+        && backup.task_dependencies.iter().all(|dependency| {
+            tasks.contains(&dependency.task) && tasks.contains(&dependency.blocker)
+        })
+}
+
+/// A walk up the parents longer than the tasks count goes around a cycle
+fn has_parent_cycle(backup: &Backup) -> bool {
+    let parents: HashMap<i64, i64> = backup
+        .tasks
+        .iter()
+        .map(|task| (task.id, task.parent))
+        .collect();
+    backup.tasks.iter().any(|task| {
+        let mut parent = task.parent;
+        for _ in 0..parents.len() {
+            if parent == 0 {
+                return false;
+            }
+            parent = parents.get(&parent).copied().unwrap_or(0);
+        }
+        parent != 0
+    })
+}
+
+fn write_backup(conn: &Connection, backup: &Backup, mode: ImportMode) -> Result<JsonImport> {
+    // Merged items get new ids from the database and the maps keep them for references
+    let keep_ids = mode == ImportMode::Replace;
+    let new_id = |id: i64| if keep_ids { Some(id) } else { None };
+    let index_offset = if keep_ids {
+        conn.execute_batch(
+            "DELETE FROM projects;
+            DELETE FROM sections;
+            DELETE FROM tasks;
+            DELETE FROM records;
+            DELETE FROM reminders;
+            DELETE FROM task_uids;
+            DELETE FROM caldav_calendars;
+            DELETE FROM caldav_items;
+            DELETE FROM tags;
+            DELETE FROM task_tags;
+            DELETE FROM task_dependencies;
+            DELETE FROM filters;",
+        )?;
+        0
+    } else {
+        conn.query_row("SELECT coalesce(max(i), -1) + 1 FROM projects", [], |row| {
+            row.get::<_, i32>(0)
+        })?
+    };
+
+    // Merged trash batches come after the ones in the database,
+    // the deletion time was the batch in older versions
+    let batch_offset = if keep_ids {
+        0
+    } else {
+        conn.query_row(
+            "SELECT max(
+                (SELECT coalesce(max(trash_batch), 0) FROM projects),
+                (SELECT coalesce(max(trash_batch), 0) FROM sections),
+                (SELECT coalesce(max(trash_batch), 0) FROM tasks)
+            )",
+            [],
+            |row| row.get::<_, i64>(0),
+        )?
+    };
+    let trash_batch = |batch: i64, deleted_at: i64| match (batch, deleted_at) {
+        (_, 0) => 0,
+        (0, deleted_at) => deleted_at + batch_offset,
+        (batch, _) => batch + batch_offset,
+    };
+
+    let mut projects = HashMap::new();
+    for project in &backup.projects {
+        conn.execute(
+            "INSERT INTO projects(id, name, archive, i, icon, description, goal, goal_period, deleted_at, trash_batch)
+            VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10)",
+            (
+                new_id(project.id),
+                &project.name,
+                project.archive,
+                project.index + index_offset,
+                &project.icon,
+                &project.description,
+                project.goal.max(0),
+                project.goal_period.min(2),
+                project.deleted_at,
+                trash_batch(project.trash_batch, project.deleted_at),
+            ),
+        )?;
+        projects.insert(project.id, conn.last_insert_rowid());
+    }
+
+    let mut sections = HashMap::from([(0, 0)]);
+    for section in &backup.sections {
+        conn.execute(
+            "INSERT INTO sections(id, name, project, i, priority_sort, deleted_at, trash_batch)
+            VALUES (?1,?2,?3,?4,?5,?6,?7)",
+            (
+                new_id(section.id),
+                &section.name,
+                projects[&section.project],
+                section.index,
+                section.priority_sort,
+                section.deleted_at,
+                trash_batch(section.trash_batch, section.deleted_at),
+            ),
+        )?;
+        sections.insert(section.id, conn.last_insert_rowid());
+    }
+
+    // Parents are linked after inserting all tasks because a subtask can come before them
+    let mut tasks = HashMap::new();
+    let now = glib::DateTime::now_local().unwrap().to_unix();
+    for task in &backup.tasks {
+        let created_at = if task.created_at == 0 {
+            now
+        } else {
+            task.created_at
+        };
+        let updated_at = task.updated_at.max(created_at);
+        // Zero is for an unknown completion time
+        let completed_at = if task.done { task.completed_at } else { 0 };
+        conn.execute(
+            "INSERT INTO tasks(id, name, done, project, section, position, suspended, description, date, repeat, priority, estimate, deleted_at, trash_batch, created_at, updated_at, completed_at)
+            VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17)",
+            params![
+                new_id(task.id),
+                &task.name,
+                task.done,
+                projects[&task.project],
+                sections[&task.section],
+                task.position,
+                task.suspended,
+                &task.description,
+                task.date,
+                &task.repeat,
+                task.priority.min(3),
+                task.estimate.max(0),
+                task.deleted_at,
+                trash_batch(task.trash_batch, task.deleted_at),
+                created_at,
+                updated_at,
+                completed_at,
+            ],
+        )?;
+        let task_id = conn.last_insert_rowid();
+        tasks.insert(task.id, task_id);
+        if let Some(uid) = &task.uid {
+            // Merging the same backup twice can't reuse the UIDs
+            conn.execute(
+                "INSERT OR IGNORE INTO task_uids(task, uid) VALUES (?1,?2)",
+                (task_id, uid),
+            )?;
+        }
+    }
+    for task in backup.tasks.iter().filter(|task| task.parent != 0) {
+        conn.execute(
+            "UPDATE tasks SET parent = ?2 WHERE id = ?1",
+            (tasks[&task.id], tasks[&task.parent]),
+        )?;
+    }
+
+    // Open records are dropped, their timers are not running in this database
+    for record in backup.records.iter().filter(|record| record.duration != 0) {
+        conn.execute(
+            "INSERT INTO records(id, start, duration, task) VALUES (?1,?2,?3,?4)",
+            (
+                new_id(record.id),
+                record.start,
+                record.duration,
+                tasks[&record.task],
+            ),
+        )?;
+    }
+
+    let mut reminders = vec![];
+    for reminder in &backup.reminders {
+        conn.execute(
+            "INSERT INTO reminders(id, datetime, past, task, priority, repeat, relative, date_offset)
+            VALUES (?1,?2,?3,?4,?5,?6,?7,?8)",
+            (
+                new_id(reminder.id),
+                reminder.datetime,
+                reminder.past,
+                tasks[&reminder.task],
+                reminder.priority,
+                &reminder.repeat,
+                reminder.relative,
+                reminder.date_offset,
+            ),
+        )?;
+        if !reminder.past {
+            reminders.push(conn.last_insert_rowid());
+        }
+    }
+
+    // Merged tags join the current tag with the same name
+    let mut tags = HashMap::new();
+    for tag in &backup.tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO tags(id, name) VALUES (?1,?2)",
+            (new_id(tag.id), &tag.name),
+        )?;
+        let tag_id = conn.query_row("SELECT id FROM tags WHERE name = ?", [&tag.name], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        tags.insert(tag.id, tag_id);
+    }
+    for task_tag in &backup.task_tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO task_tags(task, tag) VALUES (?1,?2)",
+            (tasks[&task_tag.task], tags[&task_tag.tag]),
+        )?;
+    }
+    for dependency in &backup.task_dependencies {
+        conn.execute(
+            "INSERT OR IGNORE INTO task_dependencies(task, blocker) VALUES (?1,?2)",
+            (tasks[&dependency.task], tasks[&dependency.blocker]),
+        )?;
+    }
+    for filter in &backup.filters {
+        conn.execute(
+            "INSERT INTO filters(id, name, query) VALUES (?1,?2,?3)",
+            (new_id(filter.id), &filter.name, &filter.query),
+        )?;
+    }
+
+    Ok(JsonImport {
+        projects: backup.projects.len(),
+        tasks: backup.tasks.len(),
+        reminders,
+    })
+}
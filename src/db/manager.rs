@@ -1,13 +1,21 @@
 use gtk::glib;
-use rusqlite::{Connection, Result};
+use rusqlite::{functions::FunctionFlags, Connection, Result};
 use std::cmp::Ordering;
 
 use crate::db::migrate::MIGRATIONS;
+use crate::db::operations::history_writer;
 
-const DB_VERSION: u8 = 9;
+const DB_VERSION: u8 = 27;
 
 pub fn get_connection() -> Connection {
-    Connection::open(glib::user_data_dir().join("data.db")).expect("Failed connect to database")
+    let conn = Connection::open(glib::user_data_dir().join("data.db"))
+        .expect("Failed connect to database");
+    // The history rows keep their writer
+    conn.create_scalar_function("history_writer", 0, FunctionFlags::SQLITE_UTF8, |_| {
+        Ok(history_writer())
+    })
+    .expect("Failed to add history writer function");
+    conn
 }
 
 pub fn check_database() -> Result<()> {
@@ -26,6 +34,10 @@ pub fn check_database() -> Result<()> {
                 i           INTEGER NOT NULL,
                 icon        TEXT    NOT NULL DEFAULT '',
                 description TEXT    NOT NULL DEFAULT '',
+                goal        INTEGER NOT NULL DEFAULT 0,
+                goal_period INTEGER NOT NULL DEFAULT 0,
+                deleted_at  INTEGER NOT NULL DEFAULT 0,
+                trash_batch INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
@@ -37,6 +49,9 @@ pub fn check_database() -> Result<()> {
                 name      TEXT    NOT NULL,
                 project   INTEGER NOT NULL,
                 i         INTEGER NOT NULL,
+                priority_sort INTEGER NOT NULL DEFAULT 0,
+                deleted_at    INTEGER NOT NULL DEFAULT 0,
+                trash_batch   INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
@@ -54,6 +69,15 @@ pub fn check_database() -> Result<()> {
                 parent      INTEGER NOT NULL DEFAULT 0,
                 description TEXT    NOT NULL DEFAULT '',
                 date	    INTEGER NOT NULL DEFAULT 0,
+                repeat      TEXT    NOT NULL DEFAULT '',
+                priority    INTEGER NOT NULL DEFAULT 0,
+                estimate    INTEGER NOT NULL DEFAULT 0,
+                deleted_at  INTEGER NOT NULL DEFAULT 0,
+                created_at  INTEGER NOT NULL DEFAULT 0,
+                updated_at  INTEGER NOT NULL DEFAULT 0,
+                completed_at INTEGER NOT NULL DEFAULT 0,
+                next_occurrence INTEGER NOT NULL DEFAULT 0,
+                trash_batch INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
@@ -77,11 +101,100 @@ pub fn check_database() -> Result<()> {
                 past      INTEGER NOT NULL DEFAULT 0,
                 task      INTEGER NOT NULL,
                 priority  INTEGER NOT NULL DEFAULT 1,
+                repeat      TEXT    NOT NULL DEFAULT '',
+                relative    INTEGER NOT NULL DEFAULT 0,
+                date_offset INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
         )?;
 
+        conn.execute(
+            "CREATE TABLE task_uids (
+                task  INTEGER NOT NULL,
+                uid   TEXT    NOT NULL UNIQUE,
+                PRIMARY KEY(task)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE caldav_calendars (
+                project  INTEGER NOT NULL,
+                href     TEXT    NOT NULL,
+                PRIMARY KEY(project)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE caldav_items (
+                task     INTEGER NOT NULL,
+                project  INTEGER NOT NULL,
+                href     TEXT    NOT NULL UNIQUE,
+                etag     TEXT    NOT NULL DEFAULT '',
+                dirty    INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY(task)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE tags (
+                id    INTEGER NOT NULL,
+                name  TEXT    NOT NULL UNIQUE,
+                PRIMARY KEY(id AUTOINCREMENT)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE task_tags (
+                task  INTEGER NOT NULL,
+                tag   INTEGER NOT NULL,
+                PRIMARY KEY(task, tag)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE task_dependencies (
+                task     INTEGER NOT NULL,
+                blocker  INTEGER NOT NULL,
+                PRIMARY KEY(task, blocker)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE filters (
+                id     INTEGER NOT NULL,
+                name   TEXT    NOT NULL,
+                query  TEXT    NOT NULL,
+                PRIMARY KEY(id AUTOINCREMENT)
+            );",
+            (),
+        )?;
+
+        conn.execute_batch(
+            "CREATE TABLE history (
+                id          INTEGER NOT NULL,
+                step        INTEGER,
+                redo        INTEGER NOT NULL DEFAULT 0,
+                table_name  TEXT    NOT NULL,
+                item        INTEGER NOT NULL,
+                statement   TEXT    NOT NULL,
+                writer      INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY(id AUTOINCREMENT)
+            );
+            CREATE TRIGGER history_writer AFTER INSERT ON history BEGIN
+                UPDATE history SET writer = history_writer() WHERE id = new.id;
+            END;",
+        )?;
+
+        create_search_index(&conn)?;
+        create_history_triggers(&conn)?;
+
         conn.execute(&format!("PRAGMA user_version={}", DB_VERSION), ())?;
     } else {
         let conn = get_connection();
@@ -106,3 +219,142 @@ pub fn check_database() -> Result<()> {
     }
     Ok(())
 }
+
+/// Full-text index of tasks and projects, triggers keep it in sync with the tables
+pub fn create_search_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE tasks_fts USING fts5(
+            name, description, content='tasks', content_rowid='id',
+            tokenize='unicode61 remove_diacritics 2'
+        );
+        CREATE TRIGGER tasks_fts_insert AFTER INSERT ON tasks BEGIN
+            INSERT INTO tasks_fts(rowid, name, description)
+            VALUES (new.id, new.name, new.description);
+        END;
+        CREATE TRIGGER tasks_fts_delete AFTER DELETE ON tasks BEGIN
+            INSERT INTO tasks_fts(tasks_fts, rowid, name, description)
+            VALUES ('delete', old.id, old.name, old.description);
+        END;
+        CREATE TRIGGER tasks_fts_update AFTER UPDATE OF name, description ON tasks BEGIN
+            INSERT INTO tasks_fts(tasks_fts, rowid, name, description)
+            VALUES ('delete', old.id, old.name, old.description);
+            INSERT INTO tasks_fts(rowid, name, description)
+            VALUES (new.id, new.name, new.description);
+        END;
+        INSERT INTO tasks_fts(tasks_fts) VALUES ('rebuild');
+
+        CREATE VIRTUAL TABLE projects_fts USING fts5(
+            name, description, content='projects', content_rowid='id',
+            tokenize='unicode61 remove_diacritics 2'
+        );
+        CREATE TRIGGER projects_fts_insert AFTER INSERT ON projects BEGIN
+            INSERT INTO projects_fts(rowid, name, description)
+            VALUES (new.id, new.name, new.description);
+        END;
+        CREATE TRIGGER projects_fts_delete AFTER DELETE ON projects BEGIN
+            INSERT INTO projects_fts(projects_fts, rowid, name, description)
+            VALUES ('delete', old.id, old.name, old.description);
+        END;
+        CREATE TRIGGER projects_fts_update AFTER UPDATE OF name, description ON projects BEGIN
+            INSERT INTO projects_fts(projects_fts, rowid, name, description)
+            VALUES ('delete', old.id, old.name, old.description);
+            INSERT INTO projects_fts(rowid, name, description)
+            VALUES (new.id, new.name, new.description);
+        END;
+        INSERT INTO projects_fts(projects_fts) VALUES ('rebuild');",
+    )
+}
+
+/// Tables with undo history and the column of the task or the item itself
+const HISTORY_TABLES: [(&str, &str); 9] = [
+    ("projects", "id"),
+    ("sections", "id"),
+    ("tasks", "id"),
+    ("records", "task"),
+    ("reminders", "task"),
+    ("tags", "id"),
+    ("task_tags", "task"),
+    ("task_dependencies", "task"),
+    ("filters", "id"),
+];
+
+/// Triggers that save the statement reverting each change in the history table,
+/// a migration that changes the columns of the tables writes their new SQL out
+pub fn create_history_triggers(conn: &Connection) -> Result<()> {
+    for (table, item) in HISTORY_TABLES {
+        let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<String>>>()?;
+
+        // Only saved records, the timer handles the open ones
+        let (insert_when, update_when, delete_when) = match table {
+            "records" => (
+                "new.duration > 0",
+                "old.duration > 0 AND new.duration > 0",
+                "old.duration > 0",
+            ),
+            // Deleting tasks is suspending them first and the delete toast undoes it,
+            // moving them to the trash ends the suspension
+            "tasks" => (
+                "1",
+                "(old.suspended = new.suspended OR old.deleted_at != new.deleted_at)",
+                "1",
+            ),
+            _ => ("1", "1", "1"),
+        };
+        let changed = columns
+            .iter()
+            .map(|column| format!("old.\"{column}\" IS NOT new.\"{column}\""))
+            .collect::<Vec<String>>()
+            .join(" OR ");
+        // Restored tasks are never left suspended
+        let quoted_value = |column: &String| {
+            if table == "tasks" && column == "suspended" {
+                "'0'".to_string()
+            } else {
+                format!("quote(old.\"{column}\")")
+            }
+        };
+        let old_values = columns
+            .iter()
+            .map(|column| format!("'\"{column}\" = ' || {}", quoted_value(column)))
+            .collect::<Vec<String>>()
+            .join(" || ', ' || ");
+        let names = columns
+            .iter()
+            .map(|column| format!("\"{column}\""))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let quoted_values = columns
+            .iter()
+            .map(quoted_value)
+            .collect::<Vec<String>>()
+            .join(" || ', ' || ");
+
+        conn.execute_batch(&format!(
+            "DROP TRIGGER IF EXISTS {table}_history_insert;
+            DROP TRIGGER IF EXISTS {table}_history_update;
+            DROP TRIGGER IF EXISTS {table}_history_delete;
+            CREATE TRIGGER {table}_history_insert AFTER INSERT ON {table}
+            WHEN {insert_when} BEGIN
+                INSERT INTO history(table_name, item, statement)
+                VALUES ('{table}', new.{item}, 'DELETE FROM {table} WHERE rowid = ' || new.rowid);
+            END;
+            CREATE TRIGGER {table}_history_update AFTER UPDATE ON {table}
+            WHEN ({changed}) AND {update_when} BEGIN
+                INSERT INTO history(table_name, item, statement)
+                VALUES ('{table}', old.{item},
+                    'UPDATE {table} SET ' || {old_values} || ' WHERE rowid = ' || old.rowid);
+            END;
+            CREATE TRIGGER {table}_history_delete AFTER DELETE ON {table}
+            WHEN {delete_when} BEGIN
+                INSERT INTO history(table_name, item, statement)
+                VALUES ('{table}', old.{item},
+                    'INSERT OR REPLACE INTO {table}(rowid, {names}) VALUES ('
+                    || old.rowid || ', ' || {quoted_values} || ')');
+            END;"
+        ))?;
+    }
+    Ok(())
+}
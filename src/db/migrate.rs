@@ -1,8 +1,13 @@
+use gtk::glib;
 use rusqlite::{Error, Result};
 
 use crate::db::get_connection;
+use crate::db::manager::create_search_index;
 
-pub static MIGRATIONS: [fn() -> Result<()>; 9] = [to1, to2, to3, to4, to5, to6, to7, to8, to9];
+pub static MIGRATIONS: [fn() -> Result<()>; 27] = [
+    to1, to2, to3, to4, to5, to6, to7, to8, to9, to10, to11, to12, to13, to14, to15, to16, to17,
+    to18, to19, to20, to21, to22, to23, to24, to25, to26, to27,
+];
 
 fn to1() -> Result<()> {
     // Create records from duration column in tasks table and drop it.
@@ -173,3 +178,583 @@ fn to9() -> Result<()> {
     }
     Ok(())
 }
+
+fn to10() -> Result<()> {
+    // Add repeat column to tasks table
+    let conn = get_connection();
+    conn.execute("ALTER TABLE tasks ADD repeat TEXT NOT NULL DEFAULT '';", ())?;
+    Ok(())
+}
+
+fn to11() -> Result<()> {
+    // Add repeat, relative and date_offset columns to reminders table
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE reminders ADD repeat TEXT NOT NULL DEFAULT '';",
+        (),
+    )?;
+    conn.execute(
+        "ALTER TABLE reminders ADD relative INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    conn.execute(
+        "ALTER TABLE reminders ADD date_offset INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to12() -> Result<()> {
+    // Create task_uids table for keeping the UID of imported iCalendar tasks
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE task_uids (
+            task  INTEGER NOT NULL,
+            uid   TEXT    NOT NULL UNIQUE,
+            PRIMARY KEY(task)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to13() -> Result<()> {
+    // Create caldav_calendars and caldav_items tables for CalDAV sync
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE caldav_calendars (
+            project  INTEGER NOT NULL,
+            href     TEXT    NOT NULL,
+            PRIMARY KEY(project)
+        );",
+        (),
+    )?;
+    conn.execute(
+        "CREATE TABLE caldav_items (
+            task     INTEGER NOT NULL,
+            project  INTEGER NOT NULL,
+            href     TEXT    NOT NULL UNIQUE,
+            etag     TEXT    NOT NULL DEFAULT '',
+            dirty    INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY(task)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to14() -> Result<()> {
+    // Create tags and task_tags tables for labeling tasks across projects
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE tags (
+            id    INTEGER NOT NULL,
+            name  TEXT    NOT NULL UNIQUE,
+            PRIMARY KEY(id AUTOINCREMENT)
+        );",
+        (),
+    )?;
+    conn.execute(
+        "CREATE TABLE task_tags (
+            task  INTEGER NOT NULL,
+            tag   INTEGER NOT NULL,
+            PRIMARY KEY(task, tag)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to15() -> Result<()> {
+    // Add priority column to tasks table and priority_sort column to sections table
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE tasks ADD priority INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    conn.execute(
+        "ALTER TABLE sections ADD priority_sort INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to16() -> Result<()> {
+    // Create task_dependencies table for tasks blocked by other tasks
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE task_dependencies (
+            task     INTEGER NOT NULL,
+            blocker  INTEGER NOT NULL,
+            PRIMARY KEY(task, blocker)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to17() -> Result<()> {
+    // Add estimate column to tasks table
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE tasks ADD estimate INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to18() -> Result<()> {
+    // Add goal columns to projects table
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE projects ADD goal INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    conn.execute(
+        "ALTER TABLE projects ADD goal_period INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to19() -> Result<()> {
+    // Create full-text search tables for tasks and projects
+    let conn = get_connection();
+    create_search_index(&conn)
+}
+
+fn to20() -> Result<()> {
+    // Create filters table for the saved task queries
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE filters (
+            id     INTEGER NOT NULL,
+            name   TEXT    NOT NULL,
+            query  TEXT    NOT NULL,
+            PRIMARY KEY(id AUTOINCREMENT)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to21() -> Result<()> {
+    // Create history table for undo and redo, to26 adds the triggers that fill it
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE history (
+            id          INTEGER NOT NULL,
+            step        INTEGER,
+            redo        INTEGER NOT NULL DEFAULT 0,
+            table_name  TEXT    NOT NULL,
+            item        INTEGER NOT NULL,
+            statement   TEXT    NOT NULL,
+            PRIMARY KEY(id AUTOINCREMENT)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to22() -> Result<()> {
+    // Add deleted_at columns for the trash
+    let conn = get_connection();
+    conn.execute_batch(
+        "ALTER TABLE projects ADD deleted_at INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE sections ADD deleted_at INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE tasks ADD deleted_at INTEGER NOT NULL DEFAULT 0;",
+    )?;
+    Ok(())
+}
+
+fn to23() -> Result<()> {
+    // Add creation, modification and completion times of tasks,
+    // the time records are the best guess for the existing tasks,
+    // done tasks without records keep zero for an unknown completion time,
+    // the backfill is not an undo step
+    let conn = get_connection();
+    let now = glib::DateTime::now_local().unwrap().to_unix();
+    conn.execute_batch(&format!(
+        "ALTER TABLE tasks ADD created_at INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE tasks ADD updated_at INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE tasks ADD completed_at INTEGER NOT NULL DEFAULT 0;
+        UPDATE tasks SET created_at = coalesce(
+            (SELECT min(start) FROM records WHERE records.task = tasks.id), {now}
+        );
+        UPDATE tasks SET updated_at = coalesce(
+            (SELECT max(start + duration) FROM records WHERE records.task = tasks.id), created_at
+        );
+        UPDATE tasks SET completed_at = coalesce(
+            (SELECT max(start + duration) FROM records WHERE records.task = tasks.id), 0
+        ) WHERE done = true;
+        DELETE FROM history WHERE step IS NULL;"
+    ))?;
+    Ok(())
+}
+
+fn to24() -> Result<()> {
+    // Link done repeating tasks to their next occurrence, so completing can be taken back
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE tasks ADD next_occurrence INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to25() -> Result<()> {
+    // Add trash batches to restore the items trashed together,
+    // the deletion time was the batch of the items trashed before
+    let conn = get_connection();
+    conn.execute_batch(
+        "ALTER TABLE projects ADD trash_batch INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE sections ADD trash_batch INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE tasks ADD trash_batch INTEGER NOT NULL DEFAULT 0;
+        UPDATE projects SET trash_batch = deleted_at;
+        UPDATE sections SET trash_batch = deleted_at;
+        UPDATE tasks SET trash_batch = deleted_at;",
+    )
+}
+
+fn to26() -> Result<()> {
+    // Triggers that save the statement reverting each change in the history table,
+    // written for the columns of this version, restored tasks are never left suspended
+    let conn = get_connection();
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS projects_history_insert;
+        DROP TRIGGER IF EXISTS projects_history_update;
+        DROP TRIGGER IF EXISTS projects_history_delete;
+        DROP TRIGGER IF EXISTS sections_history_insert;
+        DROP TRIGGER IF EXISTS sections_history_update;
+        DROP TRIGGER IF EXISTS sections_history_delete;
+        DROP TRIGGER IF EXISTS tasks_history_insert;
+        DROP TRIGGER IF EXISTS tasks_history_update;
+        DROP TRIGGER IF EXISTS tasks_history_delete;
+        DROP TRIGGER IF EXISTS records_history_insert;
+        DROP TRIGGER IF EXISTS records_history_update;
+        DROP TRIGGER IF EXISTS records_history_delete;
+        DROP TRIGGER IF EXISTS reminders_history_insert;
+        DROP TRIGGER IF EXISTS reminders_history_update;
+        DROP TRIGGER IF EXISTS reminders_history_delete;
+        DROP TRIGGER IF EXISTS tags_history_insert;
+        DROP TRIGGER IF EXISTS tags_history_update;
+        DROP TRIGGER IF EXISTS tags_history_delete;
+        DROP TRIGGER IF EXISTS task_tags_history_insert;
+        DROP TRIGGER IF EXISTS task_tags_history_update;
+        DROP TRIGGER IF EXISTS task_tags_history_delete;
+        DROP TRIGGER IF EXISTS task_dependencies_history_insert;
+        DROP TRIGGER IF EXISTS task_dependencies_history_update;
+        DROP TRIGGER IF EXISTS task_dependencies_history_delete;
+        DROP TRIGGER IF EXISTS filters_history_insert;
+        DROP TRIGGER IF EXISTS filters_history_update;
+        DROP TRIGGER IF EXISTS filters_history_delete;
+        CREATE TRIGGER projects_history_insert AFTER INSERT ON projects BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('projects', new.id, 'DELETE FROM projects WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER projects_history_update AFTER UPDATE ON projects
+        WHEN (old.id IS NOT new.id OR old.name IS NOT new.name OR old.archive IS NOT new.archive OR
+            old.i IS NOT new.i OR old.icon IS NOT new.icon OR
+            old.description IS NOT new.description OR old.goal IS NOT new.goal OR
+            old.goal_period IS NOT new.goal_period OR old.deleted_at IS NOT new.deleted_at OR
+            old.trash_batch IS NOT new.trash_batch) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('projects', old.id, 'UPDATE projects SET '
+                || 'id = ' || quote(old.id)
+                || ', name = ' || quote(old.name)
+                || ', archive = ' || quote(old.archive)
+                || ', i = ' || quote(old.i)
+                || ', icon = ' || quote(old.icon)
+                || ', description = ' || quote(old.description)
+                || ', goal = ' || quote(old.goal)
+                || ', goal_period = ' || quote(old.goal_period)
+                || ', deleted_at = ' || quote(old.deleted_at)
+                || ', trash_batch = ' || quote(old.trash_batch)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER projects_history_delete AFTER DELETE ON projects BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('projects', old.id,
+                'INSERT OR REPLACE INTO projects(rowid, id, name, archive, i, icon, description, goal, '
+                || 'goal_period, deleted_at, trash_batch) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.id)
+                || ', ' || quote(old.name)
+                || ', ' || quote(old.archive)
+                || ', ' || quote(old.i)
+                || ', ' || quote(old.icon)
+                || ', ' || quote(old.description)
+                || ', ' || quote(old.goal)
+                || ', ' || quote(old.goal_period)
+                || ', ' || quote(old.deleted_at)
+                || ', ' || quote(old.trash_batch)
+                || ')');
+        END;
+        CREATE TRIGGER sections_history_insert AFTER INSERT ON sections BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('sections', new.id, 'DELETE FROM sections WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER sections_history_update AFTER UPDATE ON sections
+        WHEN (old.id IS NOT new.id OR old.name IS NOT new.name OR old.project IS NOT new.project OR
+            old.i IS NOT new.i OR old.priority_sort IS NOT new.priority_sort OR
+            old.deleted_at IS NOT new.deleted_at OR old.trash_batch IS NOT new.trash_batch) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('sections', old.id, 'UPDATE sections SET '
+                || 'id = ' || quote(old.id)
+                || ', name = ' || quote(old.name)
+                || ', project = ' || quote(old.project)
+                || ', i = ' || quote(old.i)
+                || ', priority_sort = ' || quote(old.priority_sort)
+                || ', deleted_at = ' || quote(old.deleted_at)
+                || ', trash_batch = ' || quote(old.trash_batch)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER sections_history_delete AFTER DELETE ON sections BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('sections', old.id,
+                'INSERT OR REPLACE INTO sections(rowid, id, name, project, i, priority_sort, deleted_at, '
+                || 'trash_batch) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.id)
+                || ', ' || quote(old.name)
+                || ', ' || quote(old.project)
+                || ', ' || quote(old.i)
+                || ', ' || quote(old.priority_sort)
+                || ', ' || quote(old.deleted_at)
+                || ', ' || quote(old.trash_batch)
+                || ')');
+        END;
+        CREATE TRIGGER tasks_history_insert AFTER INSERT ON tasks BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('tasks', new.id, 'DELETE FROM tasks WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER tasks_history_update AFTER UPDATE ON tasks
+        WHEN (old.id IS NOT new.id OR old.name IS NOT new.name OR old.done IS NOT new.done OR
+            old.project IS NOT new.project OR old.section IS NOT new.section OR
+            old.position IS NOT new.position OR old.suspended IS NOT new.suspended OR
+            old.parent IS NOT new.parent OR old.description IS NOT new.description OR
+            old.date IS NOT new.date OR old.repeat IS NOT new.repeat OR
+            old.priority IS NOT new.priority OR old.estimate IS NOT new.estimate OR
+            old.deleted_at IS NOT new.deleted_at OR old.created_at IS NOT new.created_at OR
+            old.updated_at IS NOT new.updated_at OR old.completed_at IS NOT new.completed_at OR
+            old.next_occurrence IS NOT new.next_occurrence OR
+            old.trash_batch IS NOT new.trash_batch)
+            AND (old.suspended = new.suspended OR old.deleted_at != new.deleted_at) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('tasks', old.id, 'UPDATE tasks SET '
+                || 'id = ' || quote(old.id)
+                || ', name = ' || quote(old.name)
+                || ', done = ' || quote(old.done)
+                || ', project = ' || quote(old.project)
+                || ', section = ' || quote(old.section)
+                || ', position = ' || quote(old.position)
+                || ', suspended = 0'
+                || ', parent = ' || quote(old.parent)
+                || ', description = ' || quote(old.description)
+                || ', date = ' || quote(old.date)
+                || ', repeat = ' || quote(old.repeat)
+                || ', priority = ' || quote(old.priority)
+                || ', estimate = ' || quote(old.estimate)
+                || ', deleted_at = ' || quote(old.deleted_at)
+                || ', created_at = ' || quote(old.created_at)
+                || ', updated_at = ' || quote(old.updated_at)
+                || ', completed_at = ' || quote(old.completed_at)
+                || ', next_occurrence = ' || quote(old.next_occurrence)
+                || ', trash_batch = ' || quote(old.trash_batch)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER tasks_history_delete AFTER DELETE ON tasks BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('tasks', old.id,
+                'INSERT OR REPLACE INTO tasks(rowid, id, name, done, project, section, position, suspended, '
+                || 'parent, description, date, repeat, priority, estimate, '
+                || 'deleted_at, created_at, updated_at, completed_at, '
+                || 'next_occurrence, trash_batch) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.id)
+                || ', ' || quote(old.name)
+                || ', ' || quote(old.done)
+                || ', ' || quote(old.project)
+                || ', ' || quote(old.section)
+                || ', ' || quote(old.position)
+                || ', 0'
+                || ', ' || quote(old.parent)
+                || ', ' || quote(old.description)
+                || ', ' || quote(old.date)
+                || ', ' || quote(old.repeat)
+                || ', ' || quote(old.priority)
+                || ', ' || quote(old.estimate)
+                || ', ' || quote(old.deleted_at)
+                || ', ' || quote(old.created_at)
+                || ', ' || quote(old.updated_at)
+                || ', ' || quote(old.completed_at)
+                || ', ' || quote(old.next_occurrence)
+                || ', ' || quote(old.trash_batch)
+                || ')');
+        END;
+        CREATE TRIGGER records_history_insert AFTER INSERT ON records
+        WHEN new.duration > 0 BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('records', new.task, 'DELETE FROM records WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER records_history_update AFTER UPDATE ON records
+        WHEN (old.id IS NOT new.id OR old.start IS NOT new.start OR
+            old.duration IS NOT new.duration OR old.task IS NOT new.task)
+            AND old.duration > 0 AND new.duration > 0 BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('records', old.task, 'UPDATE records SET '
+                || 'id = ' || quote(old.id)
+                || ', start = ' || quote(old.start)
+                || ', duration = ' || quote(old.duration)
+                || ', task = ' || quote(old.task)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER records_history_delete AFTER DELETE ON records
+        WHEN old.duration > 0 BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('records', old.task,
+                'INSERT OR REPLACE INTO records(rowid, id, start, duration, task) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.id)
+                || ', ' || quote(old.start)
+                || ', ' || quote(old.duration)
+                || ', ' || quote(old.task)
+                || ')');
+        END;
+        CREATE TRIGGER reminders_history_insert AFTER INSERT ON reminders BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('reminders', new.task, 'DELETE FROM reminders WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER reminders_history_update AFTER UPDATE ON reminders
+        WHEN (old.id IS NOT new.id OR old.datetime IS NOT new.datetime OR old.past IS NOT new.past OR
+            old.task IS NOT new.task OR old.priority IS NOT new.priority OR
+            old.repeat IS NOT new.repeat OR old.relative IS NOT new.relative OR
+            old.date_offset IS NOT new.date_offset) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('reminders', old.task, 'UPDATE reminders SET '
+                || 'id = ' || quote(old.id)
+                || ', datetime = ' || quote(old.datetime)
+                || ', past = ' || quote(old.past)
+                || ', task = ' || quote(old.task)
+                || ', priority = ' || quote(old.priority)
+                || ', repeat = ' || quote(old.repeat)
+                || ', relative = ' || quote(old.relative)
+                || ', date_offset = ' || quote(old.date_offset)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER reminders_history_delete AFTER DELETE ON reminders BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('reminders', old.task,
+                'INSERT OR REPLACE INTO reminders(rowid, id, datetime, past, task, priority, repeat, relative, '
+                || 'date_offset) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.id)
+                || ', ' || quote(old.datetime)
+                || ', ' || quote(old.past)
+                || ', ' || quote(old.task)
+                || ', ' || quote(old.priority)
+                || ', ' || quote(old.repeat)
+                || ', ' || quote(old.relative)
+                || ', ' || quote(old.date_offset)
+                || ')');
+        END;
+        CREATE TRIGGER tags_history_insert AFTER INSERT ON tags BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('tags', new.id, 'DELETE FROM tags WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER tags_history_update AFTER UPDATE ON tags
+        WHEN (old.id IS NOT new.id OR old.name IS NOT new.name) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('tags', old.id, 'UPDATE tags SET '
+                || 'id = ' || quote(old.id)
+                || ', name = ' || quote(old.name)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER tags_history_delete AFTER DELETE ON tags BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('tags', old.id,
+                'INSERT OR REPLACE INTO tags(rowid, id, name) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.id)
+                || ', ' || quote(old.name)
+                || ')');
+        END;
+        CREATE TRIGGER task_tags_history_insert AFTER INSERT ON task_tags BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('task_tags', new.task, 'DELETE FROM task_tags WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER task_tags_history_update AFTER UPDATE ON task_tags
+        WHEN (old.task IS NOT new.task OR old.tag IS NOT new.tag) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('task_tags', old.task, 'UPDATE task_tags SET '
+                || 'task = ' || quote(old.task)
+                || ', tag = ' || quote(old.tag)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER task_tags_history_delete AFTER DELETE ON task_tags BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('task_tags', old.task,
+                'INSERT OR REPLACE INTO task_tags(rowid, task, tag) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.task)
+                || ', ' || quote(old.tag)
+                || ')');
+        END;
+        CREATE TRIGGER task_dependencies_history_insert AFTER INSERT ON task_dependencies BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('task_dependencies', new.task, 'DELETE FROM task_dependencies WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER task_dependencies_history_update AFTER UPDATE ON task_dependencies
+        WHEN (old.task IS NOT new.task OR old.blocker IS NOT new.blocker) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('task_dependencies', old.task, 'UPDATE task_dependencies SET '
+                || 'task = ' || quote(old.task)
+                || ', blocker = ' || quote(old.blocker)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER task_dependencies_history_delete AFTER DELETE ON task_dependencies BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('task_dependencies', old.task,
+                'INSERT OR REPLACE INTO task_dependencies(rowid, task, blocker) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.task)
+                || ', ' || quote(old.blocker)
+                || ')');
+        END;
+        CREATE TRIGGER filters_history_insert AFTER INSERT ON filters BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('filters', new.id, 'DELETE FROM filters WHERE rowid = ' || new.rowid);
+        END;
+        CREATE TRIGGER filters_history_update AFTER UPDATE ON filters
+        WHEN (old.id IS NOT new.id OR old.name IS NOT new.name OR old.query IS NOT new.query) BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('filters', old.id, 'UPDATE filters SET '
+                || 'id = ' || quote(old.id)
+                || ', name = ' || quote(old.name)
+                || ', query = ' || quote(old.query)
+                || ' WHERE rowid = ' || old.rowid);
+        END;
+        CREATE TRIGGER filters_history_delete AFTER DELETE ON filters BEGIN
+            INSERT INTO history(table_name, item, statement)
+            VALUES ('filters', old.id,
+                'INSERT OR REPLACE INTO filters(rowid, id, name, query) VALUES ('
+                || old.rowid
+                || ', ' || quote(old.id)
+                || ', ' || quote(old.name)
+                || ', ' || quote(old.query)
+                || ')');
+        END;",
+    )
+}
+
+fn to27() -> Result<()> {
+    // Save the writer of the history rows, so each one only seals or drops its own changes
+    let conn = get_connection();
+    conn.execute_batch(
+        "ALTER TABLE history ADD writer INTEGER NOT NULL DEFAULT 0;
+        DELETE FROM history WHERE step IS NULL;
+        CREATE TRIGGER history_writer AFTER INSERT ON history BEGIN
+            UPDATE history SET writer = history_writer() WHERE id = new.id;
+        END;",
+    )
+}
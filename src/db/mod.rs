@@ -2,6 +2,9 @@ mod manager;
 pub use manager::check_database;
 pub use manager::get_connection;
 
+pub mod ics;
+pub mod json;
 pub mod migrate;
 pub mod models;
 pub mod operations;
+pub mod timesheet;
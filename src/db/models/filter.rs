@@ -0,0 +1,61 @@
+use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
+use rusqlite::{Error, Result, Row};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, Properties)]
+    #[properties(wrapper_type=super::Filter)]
+    pub struct Filter {
+        #[property(get, set)]
+        pub id: Cell<i64>,
+        #[property(get, set)]
+        pub name: RefCell<String>,
+        /// Search query with filters like due:today or project:Work
+        #[property(get, set)]
+        pub query: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Filter {
+        const NAME: &'static str = "Filter";
+        type Type = super::Filter;
+    }
+
+    impl ObjectImpl for Filter {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct Filter(ObjectSubclass<imp::Filter>);
+}
+
+impl Filter {
+    pub fn new(id: i64, name: String, query: String) -> Self {
+        glib::Object::builder()
+            .property("id", id)
+            .property("name", name)
+            .property("query", query)
+            .build()
+    }
+}
+
+impl TryFrom<&Row<'_>> for Filter {
+    type Error = Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(Filter::new(row.get(0)?, row.get(1)?, row.get(2)?))
+    }
+}
@@ -11,4 +11,13 @@ mod record;
 pub use record::Record;
 
 mod reminder;
-pub use reminder::Reminder;
+pub use reminder::{Reminder, OFFSET_UNITS};
+
+mod repeat;
+pub use repeat::{Repeat, RepeatFrequency};
+
+mod tag;
+pub use tag::Tag;
+
+mod filter;
+pub use filter::Filter;
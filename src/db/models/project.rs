@@ -1,8 +1,9 @@
+use gettextrs::gettext;
 use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
 use rusqlite::{Error, Result, Row};
 use std::cell::{Cell, RefCell};
 
-use crate::db::operations::project_duration;
+use crate::db::operations::{project_duration, project_duration_since};
 
 mod imp {
     use super::*;
@@ -22,6 +23,12 @@ mod imp {
         pub icon: RefCell<String>,
         #[property(get, set)]
         pub description: RefCell<String>,
+        /// Goal duration in seconds, 0 for none
+        #[property(get, set)]
+        pub goal: Cell<i64>,
+        /// 0 for daily, 1 weekly and 2 monthly goals
+        #[property(get, set, maximum = 2)]
+        pub goal_period: Cell<u8>,
     }
 
     #[glib::object_subclass]
@@ -50,30 +57,44 @@ glib::wrapper! {
 }
 
 impl Project {
-    pub fn new(
-        id: i64,
-        name: String,
-        archive: bool,
-        index: i32,
-        icon: String,
-        description: String,
-    ) -> Self {
-        glib::Object::builder()
-            .property("id", id)
-            .property("name", name)
-            .property("archive", archive)
-            .property("index", index)
-            .property("icon", icon)
-            .property("description", description)
-            .build()
+    pub fn new(properties: &[(&str, &dyn ToValue)]) -> Self {
+        let obj = glib::Object::new::<Self>();
+        obj.set_properties(properties);
+        obj
     }
 
     pub fn duration(&self) -> i64 {
         project_duration(self.id()).unwrap()
     }
 
+    /// Start of the current goal period, weeks start on Monday
+    pub fn goal_period_start(&self) -> glib::DateTime {
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap();
+        match self.goal_period() {
+            1 => today.add_days(1 - today.day_of_week()).unwrap(),
+            2 => glib::DateTime::from_local(now.year(), now.month(), 1, 0, 0, 0.0).unwrap(),
+            _ => today,
+        }
+    }
+
+    pub fn goal_period_display(&self) -> String {
+        match self.goal_period() {
+            1 => gettext("This Week"),
+            2 => gettext("This Month"),
+            _ => gettext("Today"),
+        }
+    }
+
+    /// Time spent in the current goal period
+    pub fn goal_progress(&self) -> i64 {
+        project_duration_since(self.id(), self.goal_period_start().to_unix()).unwrap()
+    }
+
     pub fn static_variant_type_string() -> String {
-        "(xsbiss)".to_string()
+        "(xsbissxy)".to_string()
     }
 }
 
@@ -81,14 +102,16 @@ impl TryFrom<&Row<'_>> for Project {
     type Error = Error;
 
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
-        Ok(Project::new(
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-            row.get(3)?,
-            row.get(4)?,
-            row.get(5)?,
-        ))
+        Ok(Project::new(&[
+            ("id", &row.get::<usize, i64>(0)?),
+            ("name", &row.get::<usize, String>(1)?),
+            ("archive", &row.get::<usize, bool>(2)?),
+            ("index", &row.get::<usize, i32>(3)?),
+            ("icon", &row.get::<usize, String>(4)?),
+            ("description", &row.get::<usize, String>(5)?),
+            ("goal", &row.get::<usize, i64>(6)?),
+            ("goal_period", &row.get::<usize, u8>(7)?),
+        ]))
     }
 }
 
@@ -96,21 +119,32 @@ impl TryFrom<&glib::Variant> for Project {
     type Error = ();
 
     fn try_from(value: &glib::Variant) -> Result<Self, Self::Error> {
-        let (id, name, archive, index, icon, description): (
+        let (id, name, archive, index, icon, description, goal, goal_period): (
             i64,
             String,
             bool,
             i32,
             String,
             String,
+            i64,
+            u8,
         ) = value.get().ok_or(())?;
-        Ok(Project::new(id, name, archive, index, icon, description))
+        Ok(Project::new(&[
+            ("id", &id),
+            ("name", &name),
+            ("archive", &archive),
+            ("index", &index),
+            ("icon", &icon),
+            ("description", &description),
+            ("goal", &goal),
+            ("goal_period", &goal_period),
+        ]))
     }
 }
 
 impl Default for Project {
     fn default() -> Self {
-        Project::new(0, String::new(), false, 0, String::new(), String::new())
+        Project::new(&[])
     }
 }
 
@@ -123,6 +157,8 @@ impl ToVariant for Project {
             self.index(),
             self.icon(),
             self.description(),
+            self.goal(),
+            self.goal_period(),
         ))
     }
 }
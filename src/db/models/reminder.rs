@@ -1,8 +1,13 @@
+use gettextrs::gettext;
 use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
 use rusqlite::{Error, Result, Row};
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::time::Duration;
 
+use crate::db::models::Repeat;
+
+pub const OFFSET_UNITS: [i64; 4] = [60, 3600, 86400, 604800];
+
 mod imp {
     use super::*;
     #[derive(Default, Debug, Properties)]
@@ -18,6 +23,12 @@ mod imp {
         pub task: Cell<i64>,
         #[property(get, set)]
         pub priority: Cell<u8>,
+        #[property(get, set)]
+        pub repeat: RefCell<String>,
+        #[property(get, set)]
+        pub relative: Cell<bool>,
+        #[property(get, set, name = "date-offset")]
+        pub date_offset: Cell<i64>,
     }
 
     #[glib::object_subclass]
@@ -63,19 +74,55 @@ impl Reminder {
     pub fn datetime_duration(&self) -> Duration {
         Duration::from_secs(self.datetime() as u64)
     }
+
+    pub fn repeat_rule(&self) -> Option<Repeat> {
+        Repeat::parse(&self.repeat())
+    }
+
+    /// Split the date offset into a value and an index of OFFSET_UNITS
+    pub fn date_offset_parts(&self) -> (i64, usize) {
+        let offset = self.date_offset();
+        if offset == 0 {
+            return (0, 0);
+        }
+        for (i, unit) in OFFSET_UNITS.iter().enumerate().rev() {
+            if offset % unit == 0 {
+                return (offset / unit, i);
+            }
+        }
+        (offset / OFFSET_UNITS[0], 0)
+    }
+
+    pub fn date_offset_display(&self) -> String {
+        let (value, unit) = self.date_offset_parts();
+        if value == 0 {
+            return gettext("At the due date");
+        }
+        let text = match unit {
+            0 => gettext("{} minutes before"),
+            1 => gettext("{} hours before"),
+            2 => gettext("{} days before"),
+            _ => gettext("{} weeks before"),
+        };
+        text.replace("{}", &value.to_string())
+    }
 }
 
 impl TryFrom<&Row<'_>> for Reminder {
     type Error = Error;
 
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
-        Ok(Reminder::new(
+        let reminder = Reminder::new(
             row.get(0)?,
             row.get(1)?,
             row.get(2)?,
             row.get(3)?,
             row.get(4)?,
-        ))
+        );
+        reminder.set_repeat(row.get::<usize, String>(5)?);
+        reminder.set_relative(row.get::<usize, bool>(6)?);
+        reminder.set_date_offset(row.get::<usize, i64>(7)?);
+        Ok(reminder)
     }
 }
 
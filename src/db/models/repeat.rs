@@ -0,0 +1,369 @@
+use gettextrs::gettext;
+use gtk::glib;
+use std::fmt::Display;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatFrequency {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+    MonthlyWeekday,
+    Yearly,
+    AfterDone,
+}
+
+impl RepeatFrequency {
+    pub const ALL: [RepeatFrequency; 6] = [
+        RepeatFrequency::Daily,
+        RepeatFrequency::Weekly,
+        RepeatFrequency::Monthly,
+        RepeatFrequency::MonthlyWeekday,
+        RepeatFrequency::Yearly,
+        RepeatFrequency::AfterDone,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            RepeatFrequency::Daily => "daily",
+            RepeatFrequency::Weekly => "weekly",
+            RepeatFrequency::Monthly => "monthly",
+            RepeatFrequency::MonthlyWeekday => "monthly-weekday",
+            RepeatFrequency::Yearly => "yearly",
+            RepeatFrequency::AfterDone => "after-done",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|frequency| frequency.name() == name)
+    }
+}
+
+/// Repeat rule of a task, stored as text in the repeat column of tasks table.
+/// Format: "frequency;interval;weekdays" e.g. "weekly;1;5" for every Monday and Wednesday.
+/// Weekdays is a bit mask that starts from Monday and only used by weekly frequency.
+/// Monthly and yearly frequencies use the task date as the anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repeat {
+    pub frequency: RepeatFrequency,
+    pub interval: u32,
+    pub weekdays: u8,
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Self {
+            frequency: RepeatFrequency::default(),
+            interval: 1,
+            weekdays: 0,
+        }
+    }
+}
+
+impl Repeat {
+    pub fn new(frequency: RepeatFrequency, interval: u32, weekdays: u8) -> Self {
+        Self {
+            frequency,
+            interval: interval.max(1),
+            weekdays: weekdays & 0b111_1111,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split(';');
+        let frequency = RepeatFrequency::from_name(parts.next()?)?;
+        let interval = parts.next()?.parse::<u32>().ok()?;
+        let weekdays = parts.next().unwrap_or("0").parse::<u8>().ok()?;
+        Some(Self::new(frequency, interval, weekdays))
+    }
+
+    pub fn has_weekday(&self, day_of_week: i32) -> bool {
+        self.weekdays & (1 << (day_of_week - 1)) != 0
+    }
+
+    /// Calculate the date of the next occurrence.
+    /// `date` is the current occurrence date and `done_date` is used by AfterDone frequency.
+    pub fn next_date(&self, date: &glib::DateTime, done_date: &glib::DateTime) -> glib::DateTime {
+        let interval = self.interval as i32;
+        match self.frequency {
+            RepeatFrequency::Daily => date.add_days(interval).unwrap(),
+            RepeatFrequency::Weekly => {
+                let weekdays = if self.weekdays == 0 {
+                    1 << (date.day_of_week() - 1)
+                } else {
+                    self.weekdays
+                };
+                let week_start = date.add_days(1 - date.day_of_week()).unwrap();
+                let mut next = date.add_days(1).unwrap();
+                loop {
+                    let week = next.difference(&week_start).as_days() / 7;
+                    if week % interval as i64 == 0
+                        && weekdays & (1 << (next.day_of_week() - 1)) != 0
+                    {
+                        break next;
+                    }
+                    next = next.add_days(1).unwrap();
+                }
+            }
+            RepeatFrequency::Monthly => date.add_months(interval).unwrap(),
+            RepeatFrequency::MonthlyWeekday => {
+                let nth = (date.day_of_month() - 1) / 7;
+                let month = date.add_months(interval).unwrap();
+                let first_day = month.add_days(1 - month.day_of_month()).unwrap();
+                let days_in_month = first_day
+                    .add_months(1)
+                    .unwrap()
+                    .add_days(-1)
+                    .unwrap()
+                    .day_of_month();
+                let mut day = 1 + (date.day_of_week() - first_day.day_of_week() + 7) % 7 + nth * 7;
+                // Use the last same weekday for the fifth weekday if the month doesn't have it
+                if day > days_in_month {
+                    day -= 7;
+                }
+                first_day.add_days(day - 1).unwrap()
+            }
+            RepeatFrequency::Yearly => date.add_years(interval).unwrap(),
+            RepeatFrequency::AfterDone => {
+                let today = glib::DateTime::new(
+                    &glib::TimeZone::local(),
+                    done_date.year(),
+                    done_date.month(),
+                    done_date.day_of_month(),
+                    date.hour(),
+                    date.minute(),
+                    0.0,
+                )
+                .unwrap();
+                today.add_days(interval).unwrap()
+            }
+        }
+    }
+
+    /// Check if the rule started from `date` has an occurrence on the `day`.
+    /// The `date` itself not counted and AfterDone frequency never has a predictable occurrence.
+    pub fn occurs_on(&self, date: &glib::DateTime, day: &glib::DateTime) -> bool {
+        if self.frequency == RepeatFrequency::AfterDone {
+            return false;
+        }
+        let day_ymd = day.ymd();
+        let interval = self.interval as i32;
+        let mut next = date.clone();
+        let mut count = 0;
+        loop {
+            count += 1;
+            // Calculate months from the date to prevent moving the day after a short month
+            next = match self.frequency {
+                RepeatFrequency::Monthly => date.add_months(interval * count).unwrap(),
+                RepeatFrequency::Yearly => date.add_years(interval * count).unwrap(),
+                _ => self.next_date(&next, &next),
+            };
+            let next_ymd = next.ymd();
+            if next_ymd == day_ymd {
+                return true;
+            } else if next_ymd > day_ymd {
+                return false;
+            }
+        }
+    }
+
+    pub fn weekday_name(day_of_week: i32) -> String {
+        // January 1, 2024 is Monday
+        glib::DateTime::from_local(2024, 1, day_of_week, 0, 0, 0.0)
+            .unwrap()
+            .format("%a")
+            .unwrap()
+            .to_string()
+    }
+
+    pub fn display(&self) -> String {
+        let interval = self.interval.to_string();
+        match self.frequency {
+            RepeatFrequency::Daily => {
+                if self.interval == 1 {
+                    gettext("Every day")
+                } else {
+                    gettext("Every {} days").replace("{}", &interval)
+                }
+            }
+            RepeatFrequency::Weekly => {
+                let text = if self.interval == 1 {
+                    gettext("Every week")
+                } else {
+                    gettext("Every {} weeks").replace("{}", &interval)
+                };
+                if self.weekdays == 0 {
+                    text
+                } else {
+                    let weekdays = (1..=7)
+                        .filter(|day| self.has_weekday(*day))
+                        .map(Self::weekday_name)
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    format!("{text}, {weekdays}")
+                }
+            }
+            RepeatFrequency::Monthly => {
+                if self.interval == 1 {
+                    gettext("Every month")
+                } else {
+                    gettext("Every {} months").replace("{}", &interval)
+                }
+            }
+            RepeatFrequency::MonthlyWeekday => {
+                if self.interval == 1 {
+                    gettext("Every month on the same weekday")
+                } else {
+                    gettext("Every {} months on the same weekday").replace("{}", &interval)
+                }
+            }
+            RepeatFrequency::Yearly => {
+                if self.interval == 1 {
+                    gettext("Every year")
+                } else {
+                    gettext("Every {} years").replace("{}", &interval)
+                }
+            }
+            RepeatFrequency::AfterDone => {
+                if self.interval == 1 {
+                    gettext("1 day after completion")
+                } else {
+                    gettext("{} days after completion").replace("{}", &interval)
+                }
+            }
+        }
+    }
+}
+
+impl Display for Repeat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{};{};{}",
+            self.frequency.name(),
+            self.interval,
+            self.weekdays
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: i32, day: i32) -> glib::DateTime {
+        glib::DateTime::from_local(year, month, day, 12, 0, 0.0).unwrap()
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let repeat = Repeat::parse("weekly;2;5").unwrap();
+        assert_eq!(repeat, Repeat::new(RepeatFrequency::Weekly, 2, 5));
+        assert_eq!(repeat.to_string(), "weekly;2;5");
+        assert_eq!(
+            Repeat::parse("daily;3"),
+            Some(Repeat::new(RepeatFrequency::Daily, 3, 0))
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(Repeat::parse(""), None);
+        assert_eq!(Repeat::parse("hourly;1;0"), None);
+        assert_eq!(Repeat::parse("daily"), None);
+        assert_eq!(Repeat::parse("daily;x;0"), None);
+        assert_eq!(Repeat::parse("weekly;1;300"), None);
+    }
+
+    #[test]
+    fn parse_clamps_interval_and_weekdays() {
+        let repeat = Repeat::parse("weekly;0;255").unwrap();
+        assert_eq!(repeat.interval, 1);
+        assert_eq!(repeat.weekdays, 0b111_1111);
+    }
+
+    #[test]
+    fn next_date_daily() {
+        let repeat = Repeat::new(RepeatFrequency::Daily, 3, 0);
+        let date = day(2024, 2, 27);
+        assert_eq!(repeat.next_date(&date, &date).ymd(), (2024, 3, 1));
+    }
+
+    #[test]
+    fn next_date_weekly_weekdays() {
+        // Monday and Wednesday
+        let repeat = Repeat::new(RepeatFrequency::Weekly, 1, 0b101);
+        let monday = day(2024, 1, 1);
+        let wednesday = repeat.next_date(&monday, &monday);
+        assert_eq!(wednesday.ymd(), (2024, 1, 3));
+        assert_eq!(repeat.next_date(&wednesday, &wednesday).ymd(), (2024, 1, 8));
+
+        let repeat = Repeat::new(RepeatFrequency::Weekly, 2, 0b101);
+        assert_eq!(
+            repeat.next_date(&wednesday, &wednesday).ymd(),
+            (2024, 1, 15)
+        );
+    }
+
+    #[test]
+    fn next_date_weekly_without_weekdays() {
+        let repeat = Repeat::new(RepeatFrequency::Weekly, 1, 0);
+        let date = day(2024, 1, 3);
+        assert_eq!(repeat.next_date(&date, &date).ymd(), (2024, 1, 10));
+    }
+
+    #[test]
+    fn next_date_month_end() {
+        let repeat = Repeat::new(RepeatFrequency::Monthly, 1, 0);
+        let date = day(2024, 1, 31);
+        assert_eq!(repeat.next_date(&date, &date).ymd(), (2024, 2, 29));
+
+        let repeat = Repeat::new(RepeatFrequency::Yearly, 1, 0);
+        let date = day(2024, 2, 29);
+        assert_eq!(repeat.next_date(&date, &date).ymd(), (2025, 2, 28));
+    }
+
+    #[test]
+    fn next_date_monthly_weekday() {
+        let repeat = Repeat::new(RepeatFrequency::MonthlyWeekday, 1, 0);
+        // Second Monday
+        let date = day(2024, 1, 8);
+        assert_eq!(repeat.next_date(&date, &date).ymd(), (2024, 2, 12));
+        // Fifth Monday becomes the last one of February
+        let date = day(2024, 1, 29);
+        assert_eq!(repeat.next_date(&date, &date).ymd(), (2024, 2, 26));
+    }
+
+    #[test]
+    fn next_date_after_done() {
+        let repeat = Repeat::new(RepeatFrequency::AfterDone, 2, 0);
+        let date = glib::DateTime::from_local(2024, 1, 1, 9, 30, 0.0).unwrap();
+        let next = repeat.next_date(&date, &day(2024, 1, 10));
+        assert_eq!(next.ymd(), (2024, 1, 12));
+        assert_eq!((next.hour(), next.minute()), (9, 30));
+    }
+
+    #[test]
+    fn occurs_on_month_end() {
+        let repeat = Repeat::new(RepeatFrequency::Monthly, 1, 0);
+        let date = day(2024, 1, 31);
+        assert!(!repeat.occurs_on(&date, &date));
+        assert!(repeat.occurs_on(&date, &day(2024, 2, 29)));
+        // Counted from the anchor date, not from the short month
+        assert!(repeat.occurs_on(&date, &day(2024, 3, 31)));
+        assert!(!repeat.occurs_on(&date, &day(2024, 3, 29)));
+    }
+
+    #[test]
+    fn occurs_on_weekly_and_after_done() {
+        let repeat = Repeat::new(RepeatFrequency::Weekly, 2, 0b1);
+        let date = day(2024, 1, 1);
+        assert!(!repeat.occurs_on(&date, &day(2024, 1, 8)));
+        assert!(repeat.occurs_on(&date, &day(2024, 1, 15)));
+
+        let repeat = Repeat::new(RepeatFrequency::AfterDone, 1, 0);
+        assert!(!repeat.occurs_on(&date, &day(2024, 1, 2)));
+    }
+}
@@ -16,6 +16,9 @@ mod imp {
         pub project: Cell<i64>,
         #[property(get, set)]
         pub index: Cell<i32>,
+        /// Tasks sorted by priority instead of position
+        #[property(get, set, name = "priority-sort")]
+        pub priority_sort: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -58,12 +61,9 @@ impl TryFrom<&Row<'_>> for Section {
     type Error = Error;
 
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
-        Ok(Section::new(
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-            row.get(3)?,
-        ))
+        let section = Section::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?);
+        section.set_priority_sort(row.get::<usize, bool>(4)?);
+        Ok(section)
     }
 }
 
@@ -0,0 +1,57 @@
+use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
+use rusqlite::{Error, Result, Row};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, Properties)]
+    #[properties(wrapper_type=super::Tag)]
+    pub struct Tag {
+        #[property(get, set)]
+        pub id: Cell<i64>,
+        #[property(get, set)]
+        pub name: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Tag {
+        const NAME: &'static str = "Tag";
+        type Type = super::Tag;
+    }
+
+    impl ObjectImpl for Tag {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct Tag(ObjectSubclass<imp::Tag>);
+}
+
+impl Tag {
+    pub fn new(id: i64, name: String) -> Self {
+        glib::Object::builder()
+            .property("id", id)
+            .property("name", name)
+            .build()
+    }
+}
+
+impl TryFrom<&Row<'_>> for Tag {
+    type Error = Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(Tag::new(row.get(0)?, row.get(1)?))
+    }
+}
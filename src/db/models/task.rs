@@ -5,8 +5,8 @@ use rusqlite;
 use std::cell::{Cell, RefCell};
 use std::fmt::Display;
 
-use crate::db::models::Record;
-use crate::db::operations::{read_records, task_duration};
+use crate::db::models::{Record, Repeat};
+use crate::db::operations::{read_records, task_duration, task_estimate};
 
 mod imp {
     use super::*;
@@ -34,6 +34,14 @@ mod imp {
         pub description: RefCell<String>,
         #[property(get, set)]
         pub date: Cell<i64>,
+        #[property(get, set)]
+        pub repeat: RefCell<String>,
+        /// 0 for none, 1 low, 2 medium and 3 high
+        #[property(get, set, maximum = 3)]
+        pub priority: Cell<u8>,
+        /// Estimated duration in seconds, 0 for none
+        #[property(get, set)]
+        pub estimate: Cell<i64>,
     }
 
     #[glib::object_subclass]
@@ -76,19 +84,19 @@ impl Task {
         Record::duration_display(self.duration())
     }
 
+    /// Sum of the estimates in the task tree
+    pub fn total_estimate(&self) -> i64 {
+        task_estimate(self.id()).unwrap()
+    }
+
+    /// The newest open record, the repair at startup closes the others
     pub fn incomplete_record(&self) -> Option<Record> {
-        let incomplete_records =
-            read_records(Some(self.id()), true, None, None).expect("Failed to read records");
-        match incomplete_records.len() {
-            0 => None,
-            1 => {
-                let record = incomplete_records.get(0).unwrap().to_owned();
-                record
-                    .set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
-                Some(record)
-            }
-            _ => panic!("The Task cannot have multiple incomplete records"),
-        }
+        let record = read_records(Some(self.id()), true, None, None)
+            .expect("Failed to read records")
+            .into_iter()
+            .next()?;
+        record.set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
+        Some(record)
     }
 
     pub fn date_datetime(&self) -> Option<glib::DateTime> {
@@ -100,6 +108,10 @@ impl Task {
         }
     }
 
+    pub fn repeat_rule(&self) -> Option<Repeat> {
+        Repeat::parse(&self.repeat())
+    }
+
     pub fn date_display(datetime: &glib::DateTime) -> String {
         let now = glib::DateTime::now_local().unwrap();
         let local_timezone = glib::TimeZone::local();
@@ -135,6 +147,15 @@ impl Task {
         }
     }
 
+    pub fn priority_display(&self) -> Option<String> {
+        match self.priority() {
+            1 => Some(gettext("Low Priority")),
+            2 => Some(gettext("Medium Priority")),
+            3 => Some(gettext("High Priority")),
+            _ => None,
+        }
+    }
+
     pub fn different_properties<'a>(&self, other: &Self) -> Vec<&'a str> {
         let mut properties = vec![];
         if self.id() != other.id() {
@@ -167,6 +188,15 @@ impl Task {
         if self.date() != other.date() {
             properties.push("date");
         }
+        if self.repeat() != other.repeat() {
+            properties.push("repeat");
+        }
+        if self.priority() != other.priority() {
+            properties.push("priority");
+        }
+        if self.estimate() != other.estimate() {
+            properties.push("estimate");
+        }
         properties
     }
 
@@ -182,6 +212,9 @@ impl Task {
             ("parent", &self.parent()),
             ("description", &self.description()),
             ("date", &self.date()),
+            ("repeat", &self.repeat()),
+            ("priority", &self.priority()),
+            ("estimate", &self.estimate()),
         ])
     }
 }
@@ -201,6 +234,9 @@ impl TryFrom<&rusqlite::Row<'_>> for Task {
             ("parent", &row.get::<usize, i64>(7)?),
             ("description", &row.get::<usize, String>(8)?),
             ("date", &row.get::<usize, i64>(9)?),
+            ("repeat", &row.get::<usize, String>(10)?),
+            ("priority", &row.get::<usize, u8>(11)?),
+            ("estimate", &row.get::<usize, i64>(12)?),
         ]))
     }
 }
@@ -220,7 +256,7 @@ impl Display for Task {
         };
         write!(
             f,
-            "Task {{ id: {} name: {} done: {} project: {} section: {} position: {} suspended: {} parent: {} description: {} date: {} }}",
+            "Task {{ id: {} name: {} done: {} project: {} section: {} position: {} suspended: {} parent: {} description: {} date: {} repeat: {} priority: {} estimate: {} }}",
             self.id(),
             self.name(),
             self.done(),
@@ -230,7 +266,10 @@ impl Display for Task {
             self.suspended(),
             self.parent(),
             self.description(),
-            date_text
+            date_text,
+            self.repeat(),
+            self.priority(),
+            self.estimate()
         )
     }
 }
@@ -248,41 +287,35 @@ impl ToVariant for Task {
             self.parent(),
             self.description(),
             self.date(),
+            self.repeat(),
+            self.priority(),
+            self.estimate(),
         ))
     }
 }
 
 impl StaticVariantType for Task {
     fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
-        std::borrow::Cow::from(glib::VariantTy::new("(xsbxxibxsx)").unwrap())
+        std::borrow::Cow::from(glib::VariantTy::new("(xsbxxibxsxsyx)").unwrap())
     }
 }
 
 impl FromVariant for Task {
     fn from_variant(variant: &glib::Variant) -> Option<Self> {
-        let (id, name, done, project, section, position, suspended, parent, description, date): (
-            i64,
-            String,
-            bool,
-            i64,
-            i64,
-            i32,
-            bool,
-            i64,
-            String,
-            i64,
-        ) = variant.get()?;
         Some(Task::new(&[
-            ("id", &id),
-            ("name", &name),
-            ("done", &done),
-            ("project", &project),
-            ("section", &section),
-            ("position", &position),
-            ("suspended", &suspended),
-            ("parent", &parent),
-            ("description", &description),
-            ("date", &date),
+            ("id", &variant.try_child_value(0)?.get::<i64>()?),
+            ("name", &variant.try_child_value(1)?.get::<String>()?),
+            ("done", &variant.try_child_value(2)?.get::<bool>()?),
+            ("project", &variant.try_child_value(3)?.get::<i64>()?),
+            ("section", &variant.try_child_value(4)?.get::<i64>()?),
+            ("position", &variant.try_child_value(5)?.get::<i32>()?),
+            ("suspended", &variant.try_child_value(6)?.get::<bool>()?),
+            ("parent", &variant.try_child_value(7)?.get::<i64>()?),
+            ("description", &variant.try_child_value(8)?.get::<String>()?),
+            ("date", &variant.try_child_value(9)?.get::<i64>()?),
+            ("repeat", &variant.try_child_value(10)?.get::<String>()?),
+            ("priority", &variant.try_child_value(11)?.get::<u8>()?),
+            ("estimate", &variant.try_child_value(12)?.get::<i64>()?),
         ]))
     }
 }
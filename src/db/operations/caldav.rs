@@ -0,0 +1,79 @@
+use rusqlite::{Result, Row};
+
+use crate::db::get_connection;
+
+/// Task stored on the CalDAV server as a calendar object
+#[derive(Clone, Default)]
+pub struct CalDavItem {
+    pub task: i64,
+    pub project: i64,
+    pub href: String,
+    pub etag: String,
+    /// Changed locally after the last sync
+    pub dirty: bool,
+}
+
+impl TryFrom<&Row<'_>> for CalDavItem {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(CalDavItem {
+            task: row.get(0)?,
+            project: row.get(1)?,
+            href: row.get(2)?,
+            etag: row.get(3)?,
+            dirty: row.get(4)?,
+        })
+    }
+}
+
+pub fn read_caldav_calendar(project_id: i64) -> Result<Option<String>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT href FROM caldav_calendars WHERE project = ?")?;
+    let mut rows = stmt.query([project_id])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn set_caldav_calendar(project_id: i64, href: &str) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR REPLACE INTO caldav_calendars(project, href) VALUES (?1,?2)",
+        (project_id, href),
+    )?;
+    Ok(())
+}
+
+pub fn read_caldav_items(project_id: Option<i64>) -> Result<Vec<CalDavItem>> {
+    let conn = get_connection();
+    let filter = if let Some(project_id) = project_id {
+        format!("WHERE project = {project_id}")
+    } else {
+        String::new()
+    };
+    let mut stmt = conn.prepare(&format!("SELECT * FROM caldav_items {filter}"))?;
+    let mut rows = stmt.query([])?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        items.push(CalDavItem::try_from(row)?)
+    }
+    Ok(items)
+}
+
+pub fn set_caldav_item(item: &CalDavItem) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR REPLACE INTO caldav_items(task, project, href, etag, dirty) VALUES (?1,?2,?3,?4,?5)",
+        (item.task, item.project, &item.href, &item.etag, item.dirty),
+    )?;
+    Ok(())
+}
+
+pub fn delete_caldav_item(task_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute("DELETE FROM caldav_items WHERE task = ?", (task_id,))?;
+    Ok(())
+}
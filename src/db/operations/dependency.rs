@@ -0,0 +1,93 @@
+use rusqlite::Result;
+
+use crate::db::get_connection;
+use crate::db::models::Task;
+
+/// Tasks that must be done before the task
+pub fn read_blockers(task_id: i64) -> Result<Vec<Task>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT tasks.* FROM tasks JOIN task_dependencies ON task_dependencies.blocker = tasks.id
+        WHERE task_dependencies.task = ? AND tasks.deleted_at = 0
+        ORDER BY tasks.name COLLATE NOCASE",
+    )?;
+    let mut rows = stmt.query([task_id])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push(Task::try_from(row)?)
+    }
+    Ok(tasks)
+}
+
+/// Tasks that are waiting for the task
+pub fn read_dependents(task_id: i64) -> Result<Vec<Task>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT tasks.* FROM tasks JOIN task_dependencies ON task_dependencies.task = tasks.id
+        WHERE task_dependencies.blocker = ? AND tasks.deleted_at = 0",
+    )?;
+    let mut rows = stmt.query([task_id])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push(Task::try_from(row)?)
+    }
+    Ok(tasks)
+}
+
+pub fn is_blocked(task_id: i64) -> Result<bool> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT EXISTS(SELECT 1 FROM task_dependencies
+        JOIN tasks ON tasks.id = task_dependencies.blocker
+        WHERE task_dependencies.task = ? AND tasks.done = false AND tasks.deleted_at = 0)",
+    )?;
+    stmt.query_row([task_id], |row| row.get(0))
+}
+
+/// Check the blocker and the tasks blocking it, recursively, to find the task
+pub fn dependency_has_cycle(task_id: i64, blocker_id: i64) -> Result<bool> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE blocker_tree(id) AS (
+	        SELECT ?2
+	        UNION
+	        SELECT task_dependencies.blocker
+		        FROM task_dependencies
+		        JOIN blocker_tree ON task_dependencies.task=blocker_tree.id
+        )
+        SELECT EXISTS(SELECT 1 FROM blocker_tree WHERE id=?1)",
+    )?;
+    stmt.query_row((task_id, blocker_id), |row| row.get(0))
+}
+
+/// Returns false without adding it when the dependency makes a cycle
+pub fn add_task_blocker(task_id: i64, blocker_id: i64) -> Result<bool> {
+    if dependency_has_cycle(task_id, blocker_id)? {
+        return Ok(false);
+    }
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR IGNORE INTO task_dependencies(task, blocker) VALUES (?1,?2)",
+        (task_id, blocker_id),
+    )?;
+    Ok(true)
+}
+
+pub fn remove_task_blocker(task_id: i64, blocker_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "DELETE FROM task_dependencies WHERE task = ?1 AND blocker = ?2",
+        (task_id, blocker_id),
+    )?;
+    Ok(())
+}
+
+pub fn copy_task_blockers(from_task: i64, to_task: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR IGNORE INTO task_dependencies(task, blocker)
+        SELECT ?2, blocker FROM task_dependencies WHERE task = ?1",
+        (from_task, to_task),
+    )?;
+    Ok(())
+}
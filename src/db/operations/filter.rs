@@ -0,0 +1,49 @@
+use rusqlite::Result;
+
+use crate::db::get_connection;
+use crate::db::models::Filter;
+
+pub fn create_filter(name: &str, query: &str) -> Result<Filter> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT INTO filters(name, query) VALUES (?1, ?2)",
+        (name, query),
+    )?;
+    Ok(Filter::new(
+        conn.last_insert_rowid(),
+        name.to_string(),
+        query.to_string(),
+    ))
+}
+
+pub fn read_filters() -> Result<Vec<Filter>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM filters ORDER BY id")?;
+    let mut rows = stmt.query([])?;
+    let mut filters = Vec::new();
+    while let Some(row) = rows.next()? {
+        filters.push(Filter::try_from(row)?)
+    }
+    Ok(filters)
+}
+
+pub fn read_filter(filter_id: i64) -> Result<Filter> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM filters WHERE id = ?")?;
+    stmt.query_row([filter_id], |row| Filter::try_from(row))
+}
+
+pub fn update_filter(filter: &Filter) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "UPDATE filters SET name = ?2, query = ?3 WHERE id = ?1",
+        (filter.id(), filter.name(), filter.query()),
+    )?;
+    Ok(())
+}
+
+pub fn delete_filter(filter_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute("DELETE FROM filters WHERE id = ?", (filter_id,))?;
+    Ok(())
+}
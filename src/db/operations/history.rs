@@ -0,0 +1,147 @@
+use rusqlite::Result;
+use std::cell::Cell;
+
+use crate::db::get_connection;
+use crate::db::models::Task;
+use crate::db::operations::read_task;
+
+// Steps kept for undo
+const HISTORY_LIMIT: i64 = 100;
+
+thread_local! {
+    // Set while running a write that is not a user action
+    static BACKGROUND: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Items changed by an undo or redo
+#[derive(Default)]
+pub struct HistoryStep {
+    /// Changed tasks with their state before the step, None for the restored ones
+    pub tasks: Vec<(i64, Option<Task>)>,
+    /// Tasks with changed time records
+    pub records: Vec<i64>,
+    /// Projects, sections, tags or filters changed
+    pub structure: bool,
+}
+
+/// The writer of the history rows, each process writes the user actions as one writer
+/// and the other writes as another, so it only seals or drops its own rows
+pub fn history_writer() -> i64 {
+    user_writer() + i64::from(BACKGROUND.with(Cell::get))
+}
+
+fn user_writer() -> i64 {
+    i64::from(std::process::id()) * 2
+}
+
+/// Close the changes of the last user action as one undo step
+pub fn seal_history() -> Result<()> {
+    let conn = get_connection();
+    let sealed = conn.execute(
+        "UPDATE history SET step = (SELECT coalesce(max(step), 0) + 1 FROM history)
+        WHERE step IS NULL AND writer = ?",
+        (user_writer(),),
+    )?;
+    if sealed > 0 {
+        // A new change replaces the undone steps
+        conn.execute("DELETE FROM history WHERE redo = true", ())?;
+        conn.execute(
+            "DELETE FROM history WHERE step <= (SELECT max(step) FROM history) - ?",
+            (HISTORY_LIMIT,),
+        )?;
+    }
+    Ok(())
+}
+
+/// Keep a write that is not a user action, like reminders, sync, commands or D-Bus calls,
+/// out of the undo history, the pending changes of the user are left for their step
+pub fn without_history<T, E: From<rusqlite::Error>>(
+    write: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    let background = BACKGROUND.with(|cell| cell.replace(true));
+    let result = write();
+    let discarded = get_connection().execute(
+        "DELETE FROM history WHERE step IS NULL AND writer = ?",
+        (history_writer(),),
+    );
+    BACKGROUND.with(|cell| cell.set(background));
+    discarded?;
+    result
+}
+
+pub fn clear_history() -> Result<()> {
+    let conn = get_connection();
+    conn.execute("DELETE FROM history", ())?;
+    Ok(())
+}
+
+pub fn undo_history() -> Result<Option<HistoryStep>> {
+    apply_history(false)
+}
+
+pub fn redo_history() -> Result<Option<HistoryStep>> {
+    apply_history(true)
+}
+
+/// Run the statements of the last step in reverse, the triggers save their opposite for the other side
+fn apply_history(redo: bool) -> Result<Option<HistoryStep>> {
+    seal_history()?;
+    let mut conn = get_connection();
+    let step = {
+        let mut stmt = conn.prepare(
+            "SELECT step FROM history WHERE redo = ? AND step IS NOT NULL ORDER BY id DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query([redo])?;
+        if let Some(row) = rows.next()? {
+            row.get::<_, i64>(0)?
+        } else {
+            return Ok(None);
+        }
+    };
+    let changes = {
+        let mut stmt = conn.prepare(
+            "SELECT table_name, item, statement FROM history
+            WHERE step = ?1 AND redo = ?2 ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map((step, redo), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        rows.collect::<Result<Vec<(String, i64, String)>>>()?
+    };
+
+    let mut history_step = HistoryStep::default();
+    for (table_name, item, _) in &changes {
+        match table_name.as_str() {
+            "tasks" | "reminders" | "task_tags" | "task_dependencies" => {
+                if !history_step.tasks.iter().any(|(id, _)| id == item) {
+                    history_step.tasks.push((*item, read_task(*item).ok()));
+                }
+            }
+            "records" => {
+                if !history_step.records.contains(item) {
+                    history_step.records.push(*item);
+                }
+            }
+            _ => history_step.structure = true,
+        }
+    }
+
+    let transaction = conn.transaction()?;
+    for (_, _, statement) in &changes {
+        transaction.execute(statement, ())?;
+    }
+    transaction.execute(
+        "DELETE FROM history WHERE step = ?1 AND redo = ?2",
+        (step, redo),
+    )?;
+    transaction.execute(
+        "UPDATE history SET step = ?1, redo = ?2 WHERE step IS NULL AND writer = ?3",
+        (step, !redo, history_writer()),
+    )?;
+    transaction.commit()?;
+    Ok(Some(history_step))
+}
@@ -12,3 +12,30 @@ pub use record::*;
 
 mod reminder;
 pub use reminder::*;
+
+mod caldav;
+pub use caldav::*;
+
+mod report;
+pub use report::*;
+
+mod tag;
+pub use tag::*;
+
+mod dependency;
+pub use dependency::*;
+
+mod search;
+pub use search::*;
+
+mod query;
+pub use query::*;
+
+mod filter;
+pub use filter::*;
+
+mod history;
+pub use history::*;
+
+mod trash;
+pub use trash::*;
@@ -12,24 +12,21 @@ pub fn create_project(name: &str, icon: &str, description: &str) -> Result<Proje
         "INSERT INTO projects(name, i, icon, description) VALUES (?1,?2,?3,?4)",
         (name, index, icon, description),
     )?;
-    Ok(Project::new(
-        conn.last_insert_rowid(),
-        name.to_string(),
-        false,
-        index,
-        icon.to_string(),
-        description.to_string(),
-    ))
+    Ok(Project::new(&[
+        ("id", &conn.last_insert_rowid()),
+        ("name", &name),
+        ("index", &index),
+        ("icon", &icon),
+        ("description", &description),
+    ]))
 }
 
 pub fn read_projects(archive: bool) -> Result<Vec<Project>> {
-    let filters = if !archive {
-        "WHERE archive = false"
-    } else {
-        ""
-    };
+    let filters = if !archive { "AND archive = false" } else { "" };
     let conn = get_connection();
-    let mut stmt = conn.prepare(&format!("SELECT * FROM projects {filters} ORDER BY i ASC"))?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM projects WHERE deleted_at = 0 {filters} ORDER BY i ASC"
+    ))?;
     let mut rows = stmt.query([])?;
     let mut projects = Vec::new();
     while let Some(row) = rows.next()? {
@@ -40,7 +37,7 @@ pub fn read_projects(archive: bool) -> Result<Vec<Project>> {
 
 pub fn read_project(project_id: i64) -> Result<Project> {
     let conn = get_connection();
-    let mut stmt = conn.prepare("SELECT * FROM projects WHERE id = ?")?;
+    let mut stmt = conn.prepare("SELECT * FROM projects WHERE id = ? AND deleted_at = 0")?;
     stmt.query_row([project_id], |row| Project::try_from(row))
 }
 
@@ -73,7 +70,8 @@ pub fn update_project(project: &Project) -> Result<()> {
     conn.execute(
         &format!(
             "UPDATE projects SET
-            name = ?2, archive = ?3, icon = ?4, description = ?5 {index_stmt} WHERE id = ?1"
+            name = ?2, archive = ?3, icon = ?4, description = ?5,
+            goal = ?6, goal_period = ?7 {index_stmt} WHERE id = ?1"
         ),
         (
             project.id(),
@@ -81,44 +79,38 @@ pub fn update_project(project: &Project) -> Result<()> {
             project.archive(),
             project.icon(),
             project.description(),
+            project.goal(),
+            project.goal_period(),
         ),
     )?;
     Ok(())
 }
 
-pub fn delete_project(project_id: i64, index: i32) -> Result<()> {
+pub fn project_duration(project_id: i64) -> Result<i64> {
     let conn = get_connection();
-    // Notify: Not return error when id not exists
-    conn.execute("DELETE FROM projects WHERE id = ?", (project_id,))?;
-    conn.execute("DELETE FROM sections WHERE project = ?", (project_id,))?;
-    conn.execute("DELETE FROM tasks WHERE project = ?", (project_id,))?;
-    // Decrease upper projects index
-    conn.execute("UPDATE projects SET i = i - 1 WHERE i > ?1", (index,))?;
-    Ok(())
+    let mut stmt = conn.prepare(
+        "SELECT coalesce(sum(records.duration), 0) FROM tasks
+        JOIN records ON records.task = tasks.id AND tasks.project = ?1
+        WHERE tasks.deleted_at = 0",
+    )?;
+    stmt.query_row([project_id], |row| row.get::<_, i64>(0))
 }
 
-pub fn find_projects(text: &str, archive: bool) -> Result<Vec<Project>> {
-    let filters = if archive { "" } else { "AND archive = false" };
-    // Replace % and _ with \% and \_ because they have meaning
-    // FIXME: do this without copy string
-    let text = text.replace('%', r"\%").replace('_', r"\_");
+/// Time spent on the project from the start until now
+pub fn project_duration_since(project_id: i64, start: i64) -> Result<i64> {
     let conn = get_connection();
-    let mut stmt = conn.prepare(&format!(
-        "SELECT * FROM projects WHERE name LIKE ? ESCAPE '\\' {filters}"
-    ))?;
-    let mut rows = stmt.query([format!("%{text}%")])?;
-    let mut projects = Vec::new();
-    while let Some(row) = rows.next()? {
-        projects.push(Project::try_from(row)?)
-    }
-    Ok(projects)
+    let mut stmt = conn.prepare(
+        "SELECT coalesce(sum(records.duration), 0) FROM tasks
+        JOIN records ON records.task = tasks.id AND tasks.project = ?1
+        WHERE records.start >= ?2 AND tasks.deleted_at = 0",
+    )?;
+    stmt.query_row([project_id, start], |row| row.get::<_, i64>(0))
 }
 
-pub fn project_duration(project_id: i64) -> Result<i64> {
+pub fn project_estimate(project_id: i64) -> Result<i64> {
     let conn = get_connection();
     let mut stmt = conn.prepare(
-        "SELECT coalesce(sum(records.duration), 0) FROM tasks
-        JOIN records ON records.task = tasks.id AND tasks.project = ?1",
+        "SELECT coalesce(sum(estimate), 0) FROM tasks WHERE project = ?1 AND deleted_at = 0",
     )?;
     stmt.query_row([project_id], |row| row.get::<_, i64>(0))
 }
@@ -0,0 +1,668 @@
+use gettextrs::gettext;
+use gtk::glib;
+use rusqlite::{params_from_iter, types::Value, Result};
+use std::fmt::Display;
+
+use crate::db::get_connection;
+use crate::db::models::Task;
+use crate::db::operations::search::{description_markup, match_markup, match_query};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Split the comparison prefix from the value, equal without a prefix
+    fn parse(value: &str) -> (Self, &str) {
+        for (prefix, comparison) in [
+            ("<=", Self::LessEqual),
+            (">=", Self::GreaterEqual),
+            ("<", Self::Less),
+            (">", Self::Greater),
+            ("=", Self::Equal),
+        ] {
+            if let Some(value) = value.strip_prefix(prefix) {
+                return (comparison, value);
+            }
+        }
+        (Self::Equal, value)
+    }
+
+    fn operator(&self) -> &'static str {
+        match self {
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Equal => "=",
+            Self::GreaterEqual => ">=",
+            Self::Greater => ">",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TaskAttribute {
+    Date,
+    Description,
+    Estimate,
+    Reminder,
+    Repeat,
+    Tag,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TaskFilter {
+    /// Project name without case
+    Project(String),
+    /// Section name without case
+    Section(String),
+    /// Tag name without case
+    Tag(String),
+    /// Unix time of the day start and the next day start
+    Due(Comparison, i64, i64),
+    NoDate,
+    Overdue,
+    Has(TaskAttribute),
+    Done(bool),
+    /// Seconds recorded for the task
+    Tracked(Comparison, i64),
+    /// Tasks with an open record
+    Running,
+}
+
+impl TaskFilter {
+    /// SQL condition with its parameters pushed in order
+    fn sql(&self, params: &mut Vec<Value>) -> String {
+        match self {
+            Self::Project(name) => {
+                params.push(Value::Text(name.clone()));
+                "tasks.project IN (SELECT id FROM projects
+                WHERE name = ? COLLATE NOCASE AND deleted_at = 0)"
+                    .to_string()
+            }
+            Self::Section(name) => {
+                params.push(Value::Text(name.clone()));
+                "tasks.section IN (SELECT id FROM sections
+                WHERE name = ? COLLATE NOCASE AND deleted_at = 0)"
+                    .to_string()
+            }
+            Self::Tag(name) => {
+                params.push(Value::Text(name.clone()));
+                "tasks.id IN (SELECT task_tags.task FROM task_tags
+                JOIN tags ON tags.id = task_tags.tag WHERE tags.name = ? COLLATE NOCASE)"
+                    .to_string()
+            }
+            Self::Due(comparison, start, end) => {
+                let (condition, values) = match comparison {
+                    Comparison::Equal => ("tasks.date >= ? AND tasks.date < ?", vec![*start, *end]),
+                    Comparison::Less => ("tasks.date < ?", vec![*start]),
+                    Comparison::LessEqual => ("tasks.date < ?", vec![*end]),
+                    Comparison::GreaterEqual => ("tasks.date >= ?", vec![*start]),
+                    Comparison::Greater => ("tasks.date >= ?", vec![*end]),
+                };
+                params.extend(values.into_iter().map(Value::Integer));
+                format!("tasks.date != 0 AND {condition}")
+            }
+            Self::NoDate => "tasks.date = 0".to_string(),
+            Self::Overdue => {
+                params.push(Value::Integer(today().to_unix()));
+                "tasks.date != 0 AND tasks.date < ? AND tasks.done = false".to_string()
+            }
+            Self::Has(attribute) => match attribute {
+                TaskAttribute::Date => "tasks.date != 0",
+                TaskAttribute::Description => "tasks.description != ''",
+                TaskAttribute::Estimate => "tasks.estimate != 0",
+                TaskAttribute::Reminder => {
+                    "EXISTS (SELECT 1 FROM reminders WHERE reminders.task = tasks.id)"
+                }
+                TaskAttribute::Repeat => "tasks.repeat != ''",
+                TaskAttribute::Tag => {
+                    "EXISTS (SELECT 1 FROM task_tags WHERE task_tags.task = tasks.id)"
+                }
+            }
+            .to_string(),
+            Self::Done(done) => {
+                params.push(Value::Integer(*done as i64));
+                "tasks.done = ?".to_string()
+            }
+            Self::Tracked(comparison, seconds) => {
+                params.push(Value::Integer(*seconds));
+                format!(
+                    "(SELECT coalesce(sum(duration), 0) FROM records WHERE records.task = tasks.id) {} ?",
+                    comparison.operator()
+                )
+            }
+            Self::Running => {
+                "EXISTS (SELECT 1 FROM records WHERE records.task = tasks.id AND records.duration = 0)"
+                    .to_string()
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum QueryError {
+    UnknownFilter(String),
+    InvalidValue(String),
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownFilter(token) => {
+                write!(f, "{}", gettext("Unknown filter “{}”").replace("{}", token))
+            }
+            Self::InvalidValue(token) => {
+                write!(
+                    f,
+                    "{}",
+                    gettext("Invalid value in “{}”").replace("{}", token)
+                )
+            }
+        }
+    }
+}
+
+/// Free text with filters like project:Work, tag:urgent, due:<2026-11-01, overdue or tracked:>2h
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct TaskQuery {
+    pub text: String,
+    pub filters: Vec<TaskFilter>,
+}
+
+impl TaskQuery {
+    pub fn from_text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut words = Vec::new();
+        let mut filters = Vec::new();
+        for token in tokens(input) {
+            match token.as_str() {
+                "overdue" => {
+                    filters.push(TaskFilter::Overdue);
+                    continue;
+                }
+                "running" => {
+                    filters.push(TaskFilter::Running);
+                    continue;
+                }
+                _ => (),
+            }
+            let Some((key, value)) = token.split_once(':') else {
+                words.push(token);
+                continue;
+            };
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+                words.push(token);
+                continue;
+            }
+            let invalid = || QueryError::InvalidValue(token.clone());
+            if value.is_empty() {
+                return Err(invalid());
+            }
+            let filter = match key.to_lowercase().as_str() {
+                "project" => TaskFilter::Project(value.to_string()),
+                "section" => TaskFilter::Section(value.to_string()),
+                "tag" => TaskFilter::Tag(value.to_string()),
+                "due" => {
+                    let (comparison, value) = Comparison::parse(value);
+                    if value == "none" && comparison == Comparison::Equal {
+                        TaskFilter::NoDate
+                    } else {
+                        let start = parse_date(value).ok_or_else(invalid)?;
+                        let end = start.add_days(1).unwrap();
+                        TaskFilter::Due(comparison, start.to_unix(), end.to_unix())
+                    }
+                }
+                "has" => TaskFilter::Has(match value.to_lowercase().as_str() {
+                    "date" => TaskAttribute::Date,
+                    "description" => TaskAttribute::Description,
+                    "estimate" => TaskAttribute::Estimate,
+                    "reminder" => TaskAttribute::Reminder,
+                    "repeat" => TaskAttribute::Repeat,
+                    "tag" => TaskAttribute::Tag,
+                    _ => return Err(invalid()),
+                }),
+                "done" => TaskFilter::Done(match value.to_lowercase().as_str() {
+                    "yes" | "true" => true,
+                    "no" | "false" => false,
+                    _ => return Err(invalid()),
+                }),
+                "tracked" => {
+                    let (comparison, value) = Comparison::parse(value);
+                    TaskFilter::Tracked(comparison, parse_duration(value).ok_or_else(invalid)?)
+                }
+                _ => return Err(QueryError::UnknownFilter(token.clone())),
+            };
+            filters.push(filter);
+        }
+        Ok(Self {
+            text: words.join(" "),
+            filters,
+        })
+    }
+
+    /// The input with its tag filters of the old name renamed, None when it has none
+    pub fn rename_tag(input: &str, old_name: &str, new_name: &str) -> Option<String> {
+        let mut renamed = false;
+        let tokens = tokens(input)
+            .into_iter()
+            .map(|token| match token.split_once(':') {
+                Some((key, value))
+                    if key.eq_ignore_ascii_case("tag")
+                        && value.to_lowercase() == old_name.to_lowercase() =>
+                {
+                    renamed = true;
+                    quote(&format!("tag:{new_name}"))
+                }
+                _ => quote(&token),
+            })
+            .collect::<Vec<String>>();
+        renamed.then(|| tokens.join(" "))
+    }
+
+    fn has_done_filter(&self) -> bool {
+        self.filters
+            .iter()
+            .any(|filter| matches!(filter, TaskFilter::Done(_)))
+    }
+}
+
+/// Built-in task lists of the sidebar
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SmartView {
+    Today,
+    Overdue,
+    Upcoming,
+    NoDate,
+    Running,
+}
+
+impl SmartView {
+    pub const ALL: [Self; 5] = [
+        Self::Today,
+        Self::Overdue,
+        Self::Upcoming,
+        Self::NoDate,
+        Self::Running,
+    ];
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    pub fn index(&self) -> u8 {
+        Self::ALL.iter().position(|view| view == self).unwrap() as u8
+    }
+
+    pub fn display(&self) -> String {
+        match self {
+            Self::Today => gettext("Today"),
+            Self::Overdue => gettext("Overdue"),
+            Self::Upcoming => gettext("Next 7 Days"),
+            Self::NoDate => gettext("No Date"),
+            Self::Running => gettext("Running Timers"),
+        }
+    }
+
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            Self::Today => "weather-clear-symbolic",
+            Self::Overdue => "dialog-warning-symbolic",
+            Self::Upcoming => "x-office-calendar-symbolic",
+            Self::NoDate => "action-unavailable-symbolic",
+            Self::Running => "media-playback-start-symbolic",
+        }
+    }
+
+    /// Date of the tasks added in the view
+    pub fn new_task_date(&self) -> i64 {
+        if *self == Self::Today {
+            today().to_unix()
+        } else {
+            0
+        }
+    }
+
+    pub fn query(&self) -> TaskQuery {
+        let today = today();
+        let filters = match self {
+            Self::Today => {
+                let tomorrow = today.add_days(1).unwrap();
+                vec![TaskFilter::Due(
+                    Comparison::Equal,
+                    today.to_unix(),
+                    tomorrow.to_unix(),
+                )]
+            }
+            Self::Overdue => vec![TaskFilter::Overdue],
+            Self::Upcoming => {
+                let week_end = today.add_days(7).unwrap();
+                vec![
+                    TaskFilter::Due(Comparison::GreaterEqual, today.to_unix(), 0),
+                    TaskFilter::Due(Comparison::Less, week_end.to_unix(), 0),
+                ]
+            }
+            Self::NoDate => vec![TaskFilter::NoDate],
+            Self::Running => vec![TaskFilter::Running],
+        };
+        TaskQuery {
+            text: String::new(),
+            filters,
+        }
+    }
+}
+
+/// FROM and WHERE clauses of the query with their parameters, the selected columns and the order
+fn query_parts(
+    query: &TaskQuery,
+    done: bool,
+) -> Option<(String, Vec<Value>, &'static str, &'static str)> {
+    let text_query = match_query(&query.text);
+    if text_query.is_none() && query.filters.is_empty() {
+        return None;
+    }
+
+    let mut conditions = vec![
+        "tasks.suspended = false".to_string(),
+        "tasks.deleted_at = 0".to_string(),
+    ];
+    let mut params = Vec::new();
+    let (source, columns, order) = if let Some(text_query) = text_query {
+        conditions.push("tasks_fts MATCH ?".to_string());
+        params.push(Value::Text(text_query));
+        (
+            "tasks_fts JOIN tasks ON tasks.id = tasks_fts.rowid",
+            "highlight(tasks_fts, 0, char(2), char(3)) AS name_match,
+            snippet(tasks_fts, 1, char(2), char(3), '…', 10) AS description_match",
+            "bm25(tasks_fts, 10.0, 1.0)",
+        )
+    } else {
+        (
+            "tasks",
+            "tasks.name AS name_match, '' AS description_match",
+            "tasks.date = 0, tasks.date, tasks.id",
+        )
+    };
+    // The done filter in the query comes before the option
+    if !done && !query.has_done_filter() {
+        conditions.push("tasks.done = false".to_string());
+    }
+    for filter in &query.filters {
+        conditions.push(filter.sql(&mut params));
+    }
+    let clauses = format!("FROM {source} WHERE {}", conditions.join(" AND "));
+    Some((clauses, params, columns, order))
+}
+
+/// Tasks matching the query, ranked by the free text or sorted by date without it,
+/// with the name and a description snippet as Pango markup
+pub fn query_tasks(query: &TaskQuery, done: bool) -> Result<Vec<(Task, String, String)>> {
+    let Some((clauses, params, columns, order)) = query_parts(query, done) else {
+        return Ok(Vec::new());
+    };
+    let conn = get_connection();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tasks.*, {columns} {clauses} ORDER BY {order}"
+    ))?;
+    let mut rows = stmt.query(params_from_iter(params))?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push((
+            Task::try_from(row)?,
+            match_markup(&row.get::<_, String>("name_match")?),
+            description_markup(&row.get::<_, String>("description_match")?),
+        ))
+    }
+    Ok(tasks)
+}
+
+/// Check the task against the query without reading the other tasks
+pub fn query_has_task(query: &TaskQuery, done: bool, task_id: i64) -> Result<bool> {
+    let Some((clauses, mut params, _, _)) = query_parts(query, done) else {
+        return Ok(false);
+    };
+    params.push(Value::Integer(task_id));
+    let conn = get_connection();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT EXISTS (SELECT 1 {clauses} AND tasks.id = ?)"
+    ))?;
+    stmt.query_row(params_from_iter(params), |row| row.get(0))
+}
+
+/// Words of the input, double quotes keep the spaces in a word
+fn tokens(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Token written back to the input, double quotes keep its spaces
+fn quote(token: &str) -> String {
+    if !token.contains(char::is_whitespace) {
+        return token.to_string();
+    }
+    match token.split_once(':') {
+        Some((key, value)) if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic()) => {
+            format!("{key}:\"{value}\"")
+        }
+        _ => format!("\"{token}\""),
+    }
+}
+
+fn today() -> glib::DateTime {
+    let now = glib::DateTime::now_local().unwrap();
+    glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0).unwrap()
+}
+
+/// Start of the day from today, tomorrow, yesterday or YYYY-MM-DD
+fn parse_date(value: &str) -> Option<glib::DateTime> {
+    match value.to_lowercase().as_str() {
+        "today" => Some(today()),
+        "tomorrow" => today().add_days(1).ok(),
+        "yesterday" => today().add_days(-1).ok(),
+        value => {
+            let mut parts = value.split('-').map(|part| part.parse::<i32>().ok());
+            let (Some(Some(year)), Some(Some(month)), Some(Some(day)), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return None;
+            };
+            glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()
+        }
+    }
+}
+
+/// Seconds from a duration like 2h, 45m, 1h30m or 90s
+fn parse_duration(value: &str) -> Option<i64> {
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for c in value.to_lowercase().chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'h' | 'm' | 's' => {
+                let amount = number.parse::<f64>().ok()?;
+                number.clear();
+                seconds += amount
+                    * match c {
+                        'h' => 3600.0,
+                        'm' => 60.0,
+                        _ => 1.0,
+                    };
+            }
+            _ => return None,
+        }
+    }
+    // A number without a unit
+    if !number.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some(seconds as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(input: &str) -> Vec<TaskFilter> {
+        TaskQuery::parse(input).unwrap().filters
+    }
+
+    fn day_start(year: i32, month: i32, day: i32) -> i64 {
+        glib::DateTime::from_local(year, month, day, 0, 0, 0.0)
+            .unwrap()
+            .to_unix()
+    }
+
+    #[test]
+    fn parse_text_and_filters() {
+        let query = TaskQuery::parse("write report project:Work overdue").unwrap();
+        assert_eq!(query.text, "write report");
+        assert_eq!(
+            query.filters,
+            vec![TaskFilter::Project("Work".to_string()), TaskFilter::Overdue]
+        );
+        assert_eq!(TaskQuery::parse("").unwrap(), TaskQuery::default());
+    }
+
+    #[test]
+    fn parse_quotes() {
+        let query =
+            TaskQuery::parse("project:\"Home Office\" tag:\"to do\" \"two words\"").unwrap();
+        assert_eq!(query.text, "two words");
+        assert_eq!(
+            query.filters,
+            vec![
+                TaskFilter::Project("Home Office".to_string()),
+                TaskFilter::Tag("to do".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn rename_tag() {
+        assert_eq!(
+            TaskQuery::rename_tag("tag:Urgent due:today", "urgent", "to do"),
+            Some("tag:\"to do\" due:today".to_string())
+        );
+        assert_eq!(
+            TaskQuery::rename_tag(
+                "project:\"Home Office\" TAG:urgent \"two words\"",
+                "urgent",
+                "now"
+            ),
+            Some("project:\"Home Office\" tag:now \"two words\"".to_string())
+        );
+        assert_eq!(TaskQuery::rename_tag("tag:later", "urgent", "now"), None);
+    }
+
+    #[test]
+    fn parse_words_with_colons() {
+        // Only alphabetic keys are filters
+        let query = TaskQuery::parse("meet at 10:30").unwrap();
+        assert_eq!(query.text, "meet at 10:30");
+        assert!(query.filters.is_empty());
+    }
+
+    #[test]
+    fn parse_unknown_and_invalid() {
+        assert_eq!(
+            TaskQuery::parse("color:red"),
+            Err(QueryError::UnknownFilter("color:red".to_string()))
+        );
+        for input in [
+            "project:",
+            "due:someday",
+            "due:2026-13-01",
+            "has:color",
+            "done:maybe",
+            "tracked:2x",
+            "tracked:2",
+        ] {
+            assert_eq!(
+                TaskQuery::parse(input),
+                Err(QueryError::InvalidValue(input.to_string())),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_due() {
+        let start = day_start(2026, 11, 1);
+        let end = day_start(2026, 11, 2);
+        assert_eq!(
+            filters("due:2026-11-01"),
+            vec![TaskFilter::Due(Comparison::Equal, start, end)]
+        );
+        assert_eq!(
+            filters("due:<2026-11-01 due:>=2026-11-01"),
+            vec![
+                TaskFilter::Due(Comparison::Less, start, end),
+                TaskFilter::Due(Comparison::GreaterEqual, start, end)
+            ]
+        );
+        assert_eq!(filters("due:none"), vec![TaskFilter::NoDate]);
+        let today = today().to_unix();
+        assert!(matches!(
+            filters("DUE:Today")[..],
+            [TaskFilter::Due(Comparison::Equal, start, _)] if start == today
+        ));
+    }
+
+    #[test]
+    fn parse_has_done_and_tracked() {
+        assert_eq!(
+            filters("has:Reminder done:yes done:false"),
+            vec![
+                TaskFilter::Has(TaskAttribute::Reminder),
+                TaskFilter::Done(true),
+                TaskFilter::Done(false)
+            ]
+        );
+        assert_eq!(
+            filters("tracked:>1h30m tracked:<=45m tracked:90s running"),
+            vec![
+                TaskFilter::Tracked(Comparison::Greater, 5400),
+                TaskFilter::Tracked(Comparison::LessEqual, 2700),
+                TaskFilter::Tracked(Comparison::Equal, 90),
+                TaskFilter::Running
+            ]
+        );
+    }
+
+    #[test]
+    fn done_filter_replaces_option() {
+        let (clauses, params, _, _) =
+            query_parts(&TaskQuery::parse("done:yes").unwrap(), false).unwrap();
+        assert!(!clauses.contains("tasks.done = false"));
+        assert_eq!(params, vec![Value::Integer(1)]);
+        let (clauses, _, _, _) = query_parts(&TaskQuery::parse("overdue").unwrap(), false).unwrap();
+        assert!(clauses.contains("tasks.done = false"));
+        assert!(query_parts(&TaskQuery::default(), false).is_none());
+    }
+}
@@ -3,6 +3,23 @@ use rusqlite::Result;
 use crate::db::get_connection;
 use crate::db::models::Record;
 
+/// How many timers can run at the same time
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum TimerPolicy {
+    #[default]
+    Single,
+    Parallel,
+}
+
+impl TimerPolicy {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "parallel" => Self::Parallel,
+            _ => Self::Single,
+        }
+    }
+}
+
 pub fn create_record(start: i64, task_id: i64, duration: i64) -> Result<Record> {
     let conn = get_connection();
     conn.execute(
@@ -50,7 +67,7 @@ pub fn read_records(
     Ok(records)
 }
 
-pub fn _read_record(record_id: i64) -> Result<Record> {
+pub fn read_record(record_id: i64) -> Result<Record> {
     let conn = get_connection();
     let mut stmt = conn.prepare("SELECT * FROM records WHERE id = ?")?;
     stmt.query_row([record_id], |row| Record::try_from(row))
@@ -76,3 +93,55 @@ pub fn delete_record(record_id: i64) -> Result<()> {
     conn.execute("DELETE FROM records WHERE id = ?", (record_id,))?;
     Ok(())
 }
+
+/// Open a record for the task or return its open one.
+/// Returns the records closed at the start by the single timer policy too.
+pub fn start_record(
+    task_id: i64,
+    start: i64,
+    policy: TimerPolicy,
+) -> Result<(Record, Vec<Record>)> {
+    let open_records = read_records(None, true, None, None)?;
+    if let Some(record) = open_records.iter().find(|record| record.task() == task_id) {
+        return Ok((record.to_owned(), vec![]));
+    }
+
+    let mut closed_records = vec![];
+    if policy == TimerPolicy::Single {
+        for record in open_records {
+            // Zero is for open records
+            record.set_duration((start - record.start()).max(1));
+            update_record(&record)?;
+            closed_records.push(record);
+        }
+    }
+    Ok((create_record(start, task_id, 0)?, closed_records))
+}
+
+/// Remove the open records of deleted tasks and close the extra ones
+/// at the start of the next record. Returns the closed records.
+pub fn repair_incomplete_records(policy: TimerPolicy) -> Result<Vec<Record>> {
+    let conn = get_connection();
+    conn.execute(
+        "DELETE FROM records WHERE duration = 0 AND task NOT IN (SELECT id FROM tasks)",
+        (),
+    )?;
+
+    // Newest first
+    let records = read_records(None, true, None, None)?;
+    let mut closed_records = vec![];
+    for (i, record) in records.iter().enumerate() {
+        let next_record = match policy {
+            TimerPolicy::Single => records[..i].last(),
+            TimerPolicy::Parallel => records[..i]
+                .iter()
+                .rfind(|next_record| next_record.task() == record.task()),
+        };
+        if let Some(next_record) = next_record {
+            record.set_duration((next_record.start() - record.start()).max(1));
+            update_record(record)?;
+            closed_records.push(record.to_owned());
+        }
+    }
+    Ok(closed_records)
+}
@@ -3,19 +3,22 @@ use rusqlite::Result;
 use crate::db::get_connection;
 use crate::db::models::Reminder;
 
-pub fn create_reminder(datetime: i64, task_id: i64, priority: u8) -> Result<Reminder> {
+pub fn create_reminder(reminder: Reminder) -> Result<Reminder> {
     let conn = get_connection();
     conn.execute(
-        "INSERT INTO reminders(datetime, task, priority) VALUES (?1,?2,?3)",
-        (datetime, task_id, priority),
+        "INSERT INTO reminders(datetime, task, priority, repeat, relative, date_offset) VALUES (?1,?2,?3,?4,?5,?6)",
+        (
+            reminder.datetime(),
+            reminder.task(),
+            reminder.priority(),
+            reminder.repeat(),
+            reminder.relative(),
+            reminder.date_offset(),
+        ),
     )?;
-    Ok(Reminder::new(
-        conn.last_insert_rowid(),
-        datetime,
-        false,
-        task_id,
-        priority,
-    ))
+    reminder.set_id(conn.last_insert_rowid());
+    reminder.set_past(false);
+    Ok(reminder)
 }
 
 pub fn read_reminders(task_id: Option<i64>) -> Result<Vec<Reminder>> {
@@ -45,18 +48,56 @@ pub fn read_reminder(reminder_id: i64) -> Result<Reminder> {
 pub fn update_reminder(reminder: &Reminder) -> Result<()> {
     let conn = get_connection();
     conn.execute(
-        "UPDATE reminders SET datetime = ?2, past = ?3, task = ?4, priority = ?5 WHERE id = ?1",
+        "UPDATE reminders SET datetime = ?2, past = ?3, task = ?4, priority = ?5,
+        repeat = ?6, relative = ?7, date_offset = ?8 WHERE id = ?1",
         (
             reminder.id(),
             reminder.datetime(),
             reminder.past(),
             reminder.task(),
             reminder.priority(),
+            reminder.repeat(),
+            reminder.relative(),
+            reminder.date_offset(),
         ),
     )?;
     Ok(())
 }
 
+pub fn update_relative_reminders(task_id: i64, task_date: i64) -> Result<Vec<Reminder>> {
+    let conn = get_connection();
+    if task_date == 0 {
+        // Relative reminders can't fire without the task date
+        conn.execute(
+            "UPDATE reminders SET past = 1 WHERE task = ?1 AND relative = 1",
+            (task_id,),
+        )?;
+        return Ok(vec![]);
+    }
+    conn.execute(
+        "UPDATE reminders SET datetime = ?2 - date_offset, past = 0 WHERE task = ?1 AND relative = 1",
+        (task_id, task_date),
+    )?;
+    let mut stmt = conn.prepare("SELECT * FROM reminders WHERE task = ? AND relative = 1")?;
+    let mut rows = stmt.query([task_id])?;
+    let mut reminders = Vec::new();
+    while let Some(row) = rows.next()? {
+        reminders.push(Reminder::try_from(row)?)
+    }
+    Ok(reminders)
+}
+
+pub fn copy_relative_reminders(from_task: i64, to_task: i64, task_date: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT INTO reminders(datetime, task, priority, relative, date_offset)
+        SELECT ?3 - date_offset, ?2, priority, relative, date_offset
+        FROM reminders WHERE task = ?1 AND relative = 1",
+        (from_task, to_task, task_date),
+    )?;
+    Ok(())
+}
+
 pub fn delete_reminder(reminder_id: i64) -> Result<()> {
     let conn = get_connection();
     // Notify: Not return error when id not exists
@@ -0,0 +1,84 @@
+use rusqlite::Result;
+use std::collections::HashMap;
+
+use crate::db::get_connection;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReportGroup {
+    Project,
+    Section,
+    Task,
+    /// Name of groups is the weekday number from 0 for Sunday
+    Weekday,
+}
+
+/// Total duration of the finished records that started in the time range for each group
+pub fn read_report(
+    start: i64,
+    end: i64,
+    project_id: Option<i64>,
+    group: ReportGroup,
+) -> Result<Vec<(String, i64)>> {
+    let (name, group_by) = match group {
+        ReportGroup::Project => ("projects.name", "tasks.project"),
+        ReportGroup::Section => (
+            "projects.name || ' / ' || coalesce(sections.name, '')",
+            "tasks.project, tasks.section",
+        ),
+        ReportGroup::Task => ("tasks.name", "tasks.id"),
+        ReportGroup::Weekday => (
+            "strftime('%w', records.start, 'unixepoch', 'localtime')",
+            "1",
+        ),
+    };
+    let conn = get_connection();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {name}, sum(records.duration) FROM records
+        JOIN tasks ON tasks.id = records.task
+        JOIN projects ON projects.id = tasks.project
+        LEFT JOIN sections ON sections.id = tasks.section
+        WHERE {} GROUP BY {group_by} ORDER BY 2 DESC",
+        records_filter(project_id)
+    ))?;
+    let rows = stmt.query_map((start, end), |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+/// Total duration of each day by date in "%F" format, days without records are not included
+pub fn read_daily_durations(
+    start: i64,
+    end: i64,
+    project_id: Option<i64>,
+) -> Result<HashMap<String, i64>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT date(records.start, 'unixepoch', 'localtime'), sum(records.duration) FROM records
+        JOIN tasks ON tasks.id = records.task
+        WHERE {} GROUP BY 1",
+        records_filter(project_id)
+    ))?;
+    let rows = stmt.query_map((start, end), |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+pub fn read_total_duration(start: i64, end: i64, project_id: Option<i64>) -> Result<i64> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT coalesce(sum(records.duration), 0) FROM records
+        JOIN tasks ON tasks.id = records.task
+        WHERE {}",
+        records_filter(project_id)
+    ))?;
+    stmt.query_row((start, end), |row| row.get(0))
+}
+
+fn records_filter(project_id: Option<i64>) -> String {
+    let mut filter = String::from(
+        "records.duration > 0 AND records.start >= ?1 AND records.start < ?2
+        AND tasks.deleted_at = 0",
+    );
+    if let Some(project_id) = project_id {
+        filter.push_str(&format!(" AND tasks.project = {project_id}"));
+    }
+    filter
+}
@@ -0,0 +1,77 @@
+use gtk::glib;
+use rusqlite::Result;
+
+use crate::db::get_connection;
+use crate::db::models::{Project, Task};
+use crate::db::operations::{query_tasks, TaskQuery};
+
+// Marks around the matched words, char(2) and char(3) in SQL,
+// replaced by markup after escaping the text
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// Ranked full-text search in task names and descriptions,
+/// with the name and a description snippet as Pango markup
+pub fn search_tasks(text: &str, done: bool) -> Result<Vec<(Task, String, String)>> {
+    query_tasks(&TaskQuery::from_text(text), done)
+}
+
+/// Ranked full-text search in project names and descriptions,
+/// with the name and a description snippet as Pango markup
+pub fn search_projects(text: &str, archive: bool) -> Result<Vec<(Project, String, String)>> {
+    let Some(query) = match_query(text) else {
+        return Ok(Vec::new());
+    };
+    let filters = if archive {
+        ""
+    } else {
+        "AND projects.archive = false"
+    };
+    let conn = get_connection();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT projects.*,
+            highlight(projects_fts, 0, char(2), char(3)) AS name_match,
+            snippet(projects_fts, 1, char(2), char(3), '…', 10) AS description_match
+        FROM projects_fts JOIN projects ON projects.id = projects_fts.rowid
+        WHERE projects_fts MATCH ?1 AND projects.deleted_at = 0 {filters}
+        ORDER BY bm25(projects_fts, 10.0, 1.0)"
+    ))?;
+    let mut rows = stmt.query([query])?;
+    let mut projects = Vec::new();
+    while let Some(row) = rows.next()? {
+        projects.push((
+            Project::try_from(row)?,
+            match_markup(&row.get::<_, String>("name_match")?),
+            description_markup(&row.get::<_, String>("description_match")?),
+        ))
+    }
+    Ok(projects)
+}
+
+/// Every word as a quoted prefix, so the FTS5 syntax in the text has no meaning
+pub(super) fn match_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+pub(super) fn match_markup(text: &str) -> String {
+    glib::markup_escape_text(text)
+        .replace(MATCH_START, "<b>")
+        .replace(MATCH_END, "</b>")
+}
+
+// Snippets without a match are only the start of the description
+pub(super) fn description_markup(snippet: &str) -> String {
+    if snippet.contains(MATCH_START) {
+        match_markup(&snippet.replace('\n', " "))
+    } else {
+        String::new()
+    }
+}
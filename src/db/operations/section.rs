@@ -22,7 +22,8 @@ pub fn create_section(name: &str, project_id: i64) -> Result<Section> {
 
 pub fn read_sections(project_id: i64) -> Result<Vec<Section>> {
     let conn = get_connection();
-    let mut stmt = conn.prepare("SELECT * FROM sections WHERE project = ? ORDER BY i ASC")?;
+    let mut stmt =
+        conn.prepare("SELECT * FROM sections WHERE project = ? AND deleted_at = 0 ORDER BY i ASC")?;
     let mut rows = stmt.query([project_id])?;
     let mut sections = Vec::new();
     while let Some(row) = rows.next()? {
@@ -33,7 +34,7 @@ pub fn read_sections(project_id: i64) -> Result<Vec<Section>> {
 
 pub fn read_section(section_id: i64) -> Result<Section> {
     let conn = get_connection();
-    let mut stmt = conn.prepare("SELECT * FROM sections WHERE id = ?")?;
+    let mut stmt = conn.prepare("SELECT * FROM sections WHERE id = ? AND deleted_at = 0")?;
     stmt.query_row([section_id], |row| Section::try_from(row))
 }
 
@@ -66,26 +67,19 @@ pub fn update_section(section: &Section) -> Result<()> {
     conn.execute(
         &format!(
             "UPDATE sections SET
-            name = ?2, project = ?3, i = ?4 {index_stmt} WHERE id = ?1"
+            name = ?2, project = ?3, i = ?4, priority_sort = ?5 {index_stmt} WHERE id = ?1"
         ),
         (
             section.id(),
             section.name(),
             section.project(),
             section.index(),
+            section.priority_sort(),
         ),
     )?;
     Ok(())
 }
 
-pub fn delete_section(section_id: i64) -> Result<()> {
-    let conn = get_connection();
-    // Notify: Not return error when id not exists
-    conn.execute("DELETE FROM sections WHERE id = ?", (section_id,))?;
-    conn.execute("DELETE FROM tasks WHERE section = ?", (section_id,))?;
-    Ok(())
-}
-
 fn new_index(project_id: i64) -> i32 {
     let conn = get_connection();
     let mut stmt = conn
@@ -0,0 +1,124 @@
+use rusqlite::Result;
+
+use crate::db::get_connection;
+use crate::db::models::{Tag, Task};
+use crate::db::operations::{read_filters, update_filter, TaskQuery};
+
+pub fn create_tag(name: &str) -> Result<Tag> {
+    let conn = get_connection();
+    conn.execute("INSERT INTO tags(name) VALUES (?1)", (name,))?;
+    Ok(Tag::new(conn.last_insert_rowid(), name.to_string()))
+}
+
+pub fn read_tags() -> Result<Vec<Tag>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM tags ORDER BY name COLLATE NOCASE")?;
+    let mut rows = stmt.query([])?;
+    let mut tags = Vec::new();
+    while let Some(row) = rows.next()? {
+        tags.push(Tag::try_from(row)?)
+    }
+    Ok(tags)
+}
+
+pub fn read_tag(tag_id: i64) -> Result<Tag> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM tags WHERE id = ?")?;
+    stmt.query_row([tag_id], |row| Tag::try_from(row))
+}
+
+pub fn find_tag(name: &str) -> Result<Option<Tag>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM tags WHERE name = ?")?;
+    let mut rows = stmt.query([name])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(Tag::try_from(row)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Rename the tag in the saved filters too, they find tags by name
+pub fn update_tag(tag: &Tag) -> Result<()> {
+    let conn = get_connection();
+    let old_name: String =
+        conn.query_row("SELECT name FROM tags WHERE id = ?", [tag.id()], |row| {
+            row.get(0)
+        })?;
+    conn.execute(
+        "UPDATE tags SET name = ?2 WHERE id = ?1",
+        (tag.id(), tag.name()),
+    )?;
+    for filter in read_filters()? {
+        if let Some(query) = TaskQuery::rename_tag(&filter.query(), &old_name, &tag.name()) {
+            filter.set_query(query);
+            update_filter(&filter)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn delete_tag(tag_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute("DELETE FROM task_tags WHERE tag = ?", (tag_id,))?;
+    conn.execute("DELETE FROM tags WHERE id = ?", (tag_id,))?;
+    Ok(())
+}
+
+pub fn read_task_tags(task_id: i64) -> Result<Vec<Tag>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT tags.* FROM tags JOIN task_tags ON task_tags.tag = tags.id
+        WHERE task_tags.task = ? ORDER BY tags.name COLLATE NOCASE",
+    )?;
+    let mut rows = stmt.query([task_id])?;
+    let mut tags = Vec::new();
+    while let Some(row) = rows.next()? {
+        tags.push(Tag::try_from(row)?)
+    }
+    Ok(tags)
+}
+
+pub fn add_task_tag(task_id: i64, tag_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR IGNORE INTO task_tags(task, tag) VALUES (?1,?2)",
+        (task_id, tag_id),
+    )?;
+    Ok(())
+}
+
+pub fn remove_task_tag(task_id: i64, tag_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "DELETE FROM task_tags WHERE task = ?1 AND tag = ?2",
+        (task_id, tag_id),
+    )?;
+    Ok(())
+}
+
+pub fn copy_task_tags(from_task: i64, to_task: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR IGNORE INTO task_tags(task, tag) SELECT ?2, tag FROM task_tags WHERE task = ?1",
+        (from_task, to_task),
+    )?;
+    Ok(())
+}
+
+/// Undone tasks with the tag from all projects
+pub fn read_tag_tasks(tag_id: i64) -> Result<Vec<Task>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT tasks.* FROM tasks JOIN task_tags ON task_tags.task = tasks.id
+        WHERE task_tags.tag = ? AND tasks.done = false AND tasks.suspended = false
+        AND tasks.deleted_at = 0
+        ORDER BY tasks.project, tasks.position",
+    )?;
+    let mut rows = stmt.query([tag_id])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push(Task::try_from(row)?)
+    }
+    Ok(tasks)
+}
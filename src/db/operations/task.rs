@@ -1,13 +1,18 @@
+use gtk::glib;
 use rusqlite::Result;
 
 use crate::db::get_connection;
 use crate::db::models::Task;
+use crate::db::operations::{
+    copy_relative_reminders, copy_task_blockers, copy_task_tags, search_tasks,
+};
 
 pub fn create_task(task: Task) -> Result<Task> {
     let conn = get_connection();
+    let now = glib::DateTime::now_local().unwrap().to_unix();
     conn.execute(
-        "INSERT INTO tasks(name, project, section, position, parent, description, date) VALUES (?1,?2,?3,?4,?5,?6,?7)",
-        (task.name(), task.project(), task.section(), task.position(), task.parent(), task.description(), task.date()),
+        "INSERT INTO tasks(name, project, section, position, parent, description, date, repeat, priority, estimate, created_at, updated_at) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?11)",
+        (task.name(), task.project(), task.section(), task.position(), task.parent(), task.description(), task.date(), task.repeat(), task.priority(), task.estimate(), now),
     )?;
     task.set_id(conn.last_insert_rowid());
     Ok(task)
@@ -21,7 +26,7 @@ pub fn read_tasks(
     time_range: Option<(i64, i64)>,
     suspended: bool,
 ) -> Result<Vec<Task>> {
-    let filters = &mut vec![];
+    let filters = &mut vec!["deleted_at = 0".to_string()];
     if let Some(project_id) = project_id {
         filters.push(format!("project = {project_id}"));
     }
@@ -61,10 +66,136 @@ pub fn read_tasks(
     Ok(tasks)
 }
 
+pub fn read_repeating_tasks() -> Result<Vec<Task>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT * FROM tasks
+        WHERE repeat != '' AND date != 0 AND done = false AND suspended = false AND deleted_at = 0",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push(Task::try_from(row)?)
+    }
+    Ok(tasks)
+}
+
+pub fn create_next_occurrence(task: &Task) -> Result<Option<Task>> {
+    // The repeat rule moves to the next occurrence and the done task keeps as history
+    let repeat = if let Some(repeat) = task.repeat_rule() {
+        repeat
+    } else {
+        return Ok(None);
+    };
+    let now = glib::DateTime::now_local().unwrap();
+    let date = task.date_datetime().unwrap_or_else(|| {
+        glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0).unwrap()
+    });
+    let parent = task.parent();
+    let position = if parent == 0 {
+        new_task_position(task.section())
+    } else {
+        new_subtask_position(parent)
+    };
+    let next_task = create_task(Task::new(&[
+        ("name", &task.name()),
+        ("project", &task.project()),
+        ("section", &task.section()),
+        ("position", &position),
+        ("parent", &parent),
+        ("description", &task.description()),
+        ("date", &repeat.next_date(&date, &now).to_unix()),
+        ("repeat", &task.repeat()),
+        ("priority", &task.priority()),
+        ("estimate", &task.estimate()),
+    ]))?;
+    copy_relative_reminders(task.id(), next_task.id(), next_task.date())?;
+    copy_task_tags(task.id(), next_task.id())?;
+    copy_task_blockers(task.id(), next_task.id())?;
+    task.set_repeat(String::new());
+    update_task(task)?;
+    get_connection().execute(
+        "UPDATE tasks SET next_occurrence = ?2 WHERE id = ?1",
+        (task.id(), next_task.id()),
+    )?;
+    Ok(Some(next_task))
+}
+
+/// Take back the occurrence created when the task was done and let the task repeat again,
+/// returns the removed occurrence. It stays when it is done, timed or has subtasks.
+pub fn revert_next_occurrence(task: &Task) -> Result<Option<Task>> {
+    let conn = get_connection();
+    let next_id: i64 = conn.query_row(
+        "SELECT next_occurrence FROM tasks WHERE id = ?",
+        [task.id()],
+        |row| row.get(0),
+    )?;
+    if next_id == 0 {
+        return Ok(None);
+    }
+    conn.execute(
+        "UPDATE tasks SET next_occurrence = 0 WHERE id = ?",
+        [task.id()],
+    )?;
+    let next_task = match read_task(next_id) {
+        Ok(next_task) => next_task,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let used: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM records WHERE task = ?1)
+            OR EXISTS(SELECT 1 FROM tasks WHERE parent = ?1)",
+        [next_id],
+        |row| row.get(0),
+    )?;
+    if next_task.done() || used {
+        return Ok(None);
+    }
+    delete_task(next_id)?;
+    task.set_repeat(next_task.repeat());
+    update_task(task)?;
+    Ok(Some(next_task))
+}
+
+/// Done top level tasks of the section with their completion time, latest first
+pub fn read_done_tasks(section_id: i64) -> Result<Vec<(Task, i64)>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT * FROM tasks
+        WHERE section = ?1 AND parent = 0 AND done = true AND suspended = false AND deleted_at = 0
+        ORDER BY completed_at DESC, position DESC",
+    )?;
+    let mut rows = stmt.query([section_id])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push((Task::try_from(row)?, row.get("completed_at")?));
+    }
+    Ok(tasks)
+}
+
+/// Tasks and subtasks completed in the time range with their completion time, latest first,
+/// the ones with an unknown completion time are not listed
+pub fn read_completed_tasks(start: i64, end: i64) -> Result<Vec<(Task, i64)>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT * FROM tasks
+        WHERE done = true AND completed_at != 0 AND completed_at >= ?1 AND completed_at < ?2
+        AND suspended = false AND deleted_at = 0
+        ORDER BY completed_at DESC",
+    )?;
+    let mut rows = stmt.query([start, end])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push((Task::try_from(row)?, row.get("completed_at")?));
+    }
+    Ok(tasks)
+}
+
 pub fn read_subtasks_summary(task_id: i64) -> Result<Vec<(String, bool)>> {
     let conn = get_connection();
-    let mut stmt =
-        conn.prepare("SELECT name, done FROM tasks WHERE parent = ?1 ORDER BY position DESC")?;
+    let mut stmt = conn.prepare(
+        "SELECT name, done FROM tasks WHERE parent = ?1 AND deleted_at = 0 ORDER BY position DESC",
+    )?;
     let mut rows = stmt.query([task_id])?;
     let mut subtasks = Vec::new();
     while let Some(row) = rows.next()? {
@@ -83,6 +214,7 @@ pub fn task_tree(task_id: i64, has_date: bool) -> Result<Vec<i64>> {
 	        SELECT tasks.id, tasks.parent, tasks.date
 		        FROM tasks
 		        JOIN task_tree ON tasks.parent=task_tree.id
+		        WHERE tasks.deleted_at = 0
         )
         SELECT id FROM task_tree {filter}",
     ))?;
@@ -97,12 +229,50 @@ pub fn task_tree(task_id: i64, has_date: bool) -> Result<Vec<i64>> {
 
 pub fn read_task(task_id: i64) -> Result<Task> {
     let conn = get_connection();
-    let mut stmt = conn.prepare("SELECT * FROM tasks WHERE id = ?")?;
+    let mut stmt = conn.prepare("SELECT * FROM tasks WHERE id = ? AND deleted_at = 0")?;
     stmt.query_row([task_id], |row| Task::try_from(row))
 }
 
+pub fn read_task_uid(task_id: i64) -> Result<Option<String>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT uid FROM task_uids WHERE task = ?")?;
+    let mut rows = stmt.query([task_id])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn set_task_uid(task_id: i64, uid: &str) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR REPLACE INTO task_uids(task, uid) VALUES (?1,?2)",
+        (task_id, uid),
+    )?;
+    Ok(())
+}
+
 pub fn update_task(task: &Task) -> Result<()> {
     let conn = get_connection();
+    if write_task(&conn, task)? {
+        // Push the change in the next CalDAV sync
+        conn.execute(
+            "UPDATE caldav_items SET dirty = true WHERE task = ?1",
+            (task.id(),),
+        )?;
+    }
+    Ok(())
+}
+
+/// Update the task with a change pulled from the CalDAV server, it is not pushed back
+pub fn update_pulled_task(task: &Task) -> Result<()> {
+    write_task(&get_connection(), task)?;
+    Ok(())
+}
+
+/// Returns true when a field of the CalDAV calendar object is changed
+fn write_task(conn: &rusqlite::Connection, task: &Task) -> Result<bool> {
     let old_task = read_task(task.id())?;
     let position_stmt = &mut String::new();
 
@@ -170,14 +340,40 @@ pub fn update_task(task: &Task) -> Result<()> {
 
     let task_suspended = task.suspended();
     if task_suspended != old_task.suspended() {
-        update_task_tree_suspended(&conn, task.id(), task_suspended)?;
+        update_task_tree_suspended(conn, task.id(), task_suspended)?;
     }
 
+    // Fields in the CalDAV calendar object of the task
+    let synced = task.name() != old_task.name()
+        || task.done() != old_task.done()
+        || task.project() != old_task.project()
+        || task.parent() != old_task.parent()
+        || task.description() != old_task.description()
+        || task.date() != old_task.date()
+        || task.repeat() != old_task.repeat()
+        || task.priority() != old_task.priority();
+    // Moving and suspending the task are not counted as modifications
+    let now = glib::DateTime::now_local().unwrap().to_unix();
+    let modified =
+        synced || task.section() != old_task.section() || task.estimate() != old_task.estimate();
+    let updated_stmt = if modified {
+        format!("updated_at = {now},")
+    } else {
+        String::new()
+    };
+    let completed_at = match (task.done(), old_task.done()) {
+        (true, false) => now.to_string(),
+        (true, true) => "completed_at".to_string(),
+        (false, _) => "0".to_string(),
+    };
+
     conn.execute(
         &format!(
             "UPDATE tasks SET
             name = ?2, done = ?3, project = ?4, section = ?5,
-            {position_stmt} parent = ?6, description = ?7, date = ?8 WHERE id = ?1"
+            {position_stmt} parent = ?6, description = ?7, date = ?8, repeat = ?9,
+            priority = ?10, estimate = ?11, {updated_stmt} completed_at = {completed_at}
+            WHERE id = ?1"
         ),
         (
             task.id(),
@@ -188,9 +384,12 @@ pub fn update_task(task: &Task) -> Result<()> {
             task.parent(),
             task.description(),
             task.date(),
+            task.repeat(),
+            task.priority(),
+            task.estimate(),
         ),
     )?;
-    Ok(())
+    Ok(synced)
 }
 
 fn update_task_tree_suspended(
@@ -212,24 +411,29 @@ fn update_task_tree_suspended(
     Ok(())
 }
 
+/// Remove the task tree for good with its records and reminders
 pub fn delete_task(task_id: i64) -> Result<()> {
     let conn = get_connection();
     // No return error when id not exists
     conn.execute_batch(&format!(
         "BEGIN TRANSACTION;
-        CREATE TEMPORARY TABLE temp_task_tree (id INT, project INT, section INT, position INT, parent INT);
+        CREATE TEMPORARY TABLE temp_task_tree (id INT, project INT, section INT, position INT, parent INT, deleted_at INT);
 
-        WITH RECURSIVE cte_task_tree(id, project, section, position, parent) AS (
-            SELECT id, project, section, position, parent FROM tasks WHERE id={task_id}
+        WITH RECURSIVE cte_task_tree(id, project, section, position, parent, deleted_at) AS (
+            SELECT id, project, section, position, parent, deleted_at FROM tasks WHERE id={task_id}
             UNION ALL
-            SELECT tasks.id, tasks.project, tasks.section, tasks.position, tasks.parent
+            SELECT tasks.id, tasks.project, tasks.section, tasks.position, tasks.parent, tasks.deleted_at
                 FROM tasks
                 JOIN cte_task_tree ON tasks.parent=cte_task_tree.id
         )
-        INSERT INTO temp_task_tree (id, project, section, position, parent) SELECT * FROM cte_task_tree;
+        INSERT INTO temp_task_tree (id, project, section, position, parent, deleted_at) SELECT * FROM cte_task_tree;
 
         DELETE FROM records WHERE task IN (SELECT id from temp_task_tree);
         DELETE FROM reminders WHERE task IN (SELECT id from temp_task_tree);
+        DELETE FROM task_uids WHERE task IN (SELECT id from temp_task_tree);
+        DELETE FROM task_tags WHERE task IN (SELECT id from temp_task_tree);
+        DELETE FROM task_dependencies
+            WHERE task IN (SELECT id from temp_task_tree) OR blocker IN (SELECT id from temp_task_tree);
         DELETE FROM tasks WHERE  id in (SELECT id from temp_task_tree);
 
         UPDATE tasks
@@ -237,7 +441,8 @@ pub fn delete_task(task_id: i64) -> Result<()> {
             FROM temp_task_tree
             WHERE tasks.position > temp_task_tree.position
             AND tasks.section = temp_task_tree.section
-            AND tasks.parent = temp_task_tree.parent;
+            AND tasks.parent = temp_task_tree.parent
+            AND temp_task_tree.deleted_at = 0;
 
         DROP TABLE temp_task_tree;
         COMMIT;"
@@ -247,23 +452,10 @@ pub fn delete_task(task_id: i64) -> Result<()> {
 }
 
 pub fn find_tasks(text: &str, done: bool) -> Result<Vec<Task>> {
-    let mut filters = "AND suspended = false".to_string();
-    if !done {
-        filters.push_str(" AND done = false");
-    };
-    // Replace % and _ with \% and \_ because they have meaning
-    // FIXME: do this without copy string
-    let text = text.replace('%', r"\%").replace('_', r"\_");
-    let conn = get_connection();
-    let mut stmt = conn.prepare(&format!(
-        "SELECT * FROM tasks WHERE name LIKE ? ESCAPE '\\' {filters}"
-    ))?;
-    let mut rows = stmt.query([format!("%{text}%")])?;
-    let mut tasks = Vec::new();
-    while let Some(row) = rows.next()? {
-        tasks.push(Task::try_from(row)?)
-    }
-    Ok(tasks)
+    Ok(search_tasks(text, done)?
+        .into_iter()
+        .map(|(task, _, _)| task)
+        .collect())
 }
 
 pub fn new_task_position(section_id: i64) -> i32 {
@@ -300,8 +492,35 @@ pub fn task_duration(task_id: i64) -> Result<i64> {
 	        SELECT tasks.id, tasks.parent
 		        FROM tasks
 		        JOIN task_tree ON tasks.parent=task_tree.id
+		        WHERE tasks.deleted_at = 0
         )
         SELECT coalesce(sum(duration), 0) FROM records JOIN task_tree ON records.task=task_tree.id;",
     )?;
     stmt.query_row([task_id], |row| row.get::<_, i64>(0))
 }
+
+pub fn task_estimate(task_id: i64) -> Result<i64> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE task_tree(id, parent, estimate) AS (
+	        SELECT id, parent, estimate FROM tasks WHERE id=?1
+	        UNION ALL
+	        SELECT tasks.id, tasks.parent, tasks.estimate
+		        FROM tasks
+		        JOIN task_tree ON tasks.parent=task_tree.id
+		        WHERE tasks.deleted_at = 0
+        )
+        SELECT coalesce(sum(estimate), 0) FROM task_tree;",
+    )?;
+    stmt.query_row([task_id], |row| row.get::<_, i64>(0))
+}
+
+/// Sum of the estimates for the tasks dated in the time range
+pub fn date_estimate(start: i64, end: i64) -> Result<i64> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT coalesce(sum(estimate), 0) FROM tasks
+        WHERE date >= ?1 AND date < ?2 AND suspended = false AND deleted_at = 0",
+    )?;
+    stmt.query_row([start, end], |row| row.get::<_, i64>(0))
+}
@@ -0,0 +1,375 @@
+use gtk::glib;
+use rusqlite::{Connection, Result};
+
+use crate::db::get_connection;
+use crate::db::models::{Project, Section, Task};
+use crate::db::operations::{new_subtask_position, new_task_position};
+
+/// Deleted item with the things trashed along with it
+pub enum TrashItem {
+    Project(Project),
+    Section(Section),
+    Task(Task),
+}
+
+impl TrashItem {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Project(project) => project.name(),
+            Self::Section(section) => section.name(),
+            Self::Task(task) => task.name(),
+        }
+    }
+}
+
+/// Move the task and its subtasks to the trash, saving their running timers
+pub fn trash_task(task_id: i64) -> Result<()> {
+    let mut conn = get_connection();
+    let transaction = conn.transaction()?;
+    let (_, section, parent, deleted_at) = task_place(&transaction, task_id)?;
+    if deleted_at != 0 {
+        return Ok(());
+    }
+    let position: i32 = transaction.query_row(
+        "SELECT position FROM tasks WHERE id = ?",
+        [task_id],
+        |row| row.get(0),
+    )?;
+    let batch = new_trash_batch(&transaction)?;
+    let deleted_at = now();
+    let task_ids = trash_tasks(&transaction, &format!("id = {task_id}"), batch, deleted_at)?;
+    close_records(&transaction, &task_ids, deleted_at)?;
+    transaction.execute(
+        "UPDATE tasks SET position = position - 1
+        WHERE section = ?1 AND parent = ?2 AND position > ?3 AND deleted_at = 0",
+        (section, parent, position),
+    )?;
+    transaction.commit()
+}
+
+/// Move the section and its tasks to the trash, saving their running timers
+pub fn trash_section(section_id: i64) -> Result<()> {
+    let mut conn = get_connection();
+    let transaction = conn.transaction()?;
+    let batch = new_trash_batch(&transaction)?;
+    let deleted_at = now();
+    transaction.execute(
+        "UPDATE sections SET deleted_at = ?2, trash_batch = ?3 WHERE id = ?1 AND deleted_at = 0",
+        (section_id, deleted_at, batch),
+    )?;
+    let condition = format!("section = {section_id}");
+    let task_ids = trash_tasks(&transaction, &condition, batch, deleted_at)?;
+    close_records(&transaction, &task_ids, deleted_at)?;
+    transaction.commit()
+}
+
+/// Move the project with its sections and tasks to the trash, saving their running timers
+pub fn trash_project(project_id: i64, index: i32) -> Result<()> {
+    let mut conn = get_connection();
+    let transaction = conn.transaction()?;
+    let batch = new_trash_batch(&transaction)?;
+    let deleted_at = now();
+    transaction.execute(
+        "UPDATE projects SET deleted_at = ?2, trash_batch = ?3 WHERE id = ?1 AND deleted_at = 0",
+        (project_id, deleted_at, batch),
+    )?;
+    transaction.execute(
+        "UPDATE sections SET deleted_at = ?2, trash_batch = ?3
+        WHERE project = ?1 AND deleted_at = 0",
+        (project_id, deleted_at, batch),
+    )?;
+    let condition = format!("project = {project_id}");
+    let task_ids = trash_tasks(&transaction, &condition, batch, deleted_at)?;
+    close_records(&transaction, &task_ids, deleted_at)?;
+    // Decrease upper projects index
+    transaction.execute(
+        "UPDATE projects SET i = i - 1 WHERE i > ?1 AND deleted_at = 0",
+        (index,),
+    )?;
+    transaction.commit()
+}
+
+/// Task in the trash, read_task only reads the others
+pub fn read_trashed_task(task_id: i64) -> Result<Task> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM tasks WHERE id = ? AND deleted_at != 0")?;
+    stmt.query_row([task_id], |row| Task::try_from(row))
+}
+
+/// Trashed items with their deletion time, newest first,
+/// the contents of a trashed project or section are restored with it and not listed
+pub fn read_trash() -> Result<Vec<(TrashItem, i64)>> {
+    let conn = get_connection();
+    let mut items: Vec<(TrashItem, i64)> = Vec::new();
+
+    let mut stmt = conn.prepare("SELECT * FROM projects WHERE deleted_at != 0")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        items.push((
+            TrashItem::Project(Project::try_from(row)?),
+            row.get("deleted_at")?,
+        ));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT * FROM sections WHERE deleted_at != 0
+        AND NOT EXISTS (SELECT 1 FROM projects
+            WHERE projects.id = sections.project AND projects.deleted_at != 0
+            AND projects.trash_batch = sections.trash_batch)",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        items.push((
+            TrashItem::Section(Section::try_from(row)?),
+            row.get("deleted_at")?,
+        ));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT * FROM tasks WHERE deleted_at != 0
+        AND NOT EXISTS (SELECT 1 FROM tasks AS parents
+            WHERE parents.id = tasks.parent AND parents.deleted_at != 0
+            AND parents.trash_batch = tasks.trash_batch)
+        AND NOT EXISTS (SELECT 1 FROM sections
+            WHERE sections.id = tasks.section AND sections.deleted_at != 0
+            AND sections.trash_batch = tasks.trash_batch)
+        AND NOT EXISTS (SELECT 1 FROM projects
+            WHERE projects.id = tasks.project AND projects.deleted_at != 0
+            AND projects.trash_batch = tasks.trash_batch)",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        items.push((
+            TrashItem::Task(Task::try_from(row)?),
+            row.get("deleted_at")?,
+        ));
+    }
+
+    items.sort_by(|(_, a), (_, b)| b.cmp(a));
+    Ok(items)
+}
+
+/// Bring back the item with everything trashed along with it and return the restored tasks,
+/// its trashed project, section or parent task come back without their other contents
+pub fn restore_trash_item(item: &TrashItem) -> Result<Vec<i64>> {
+    let conn = get_connection();
+    match item {
+        TrashItem::Project(project) => {
+            let batch = trash_batch(&conn, "projects", project.id())?;
+            restore_project(&conn, project.id())?;
+            conn.execute(
+                "UPDATE sections SET deleted_at = 0
+                WHERE project = ?1 AND deleted_at != 0 AND trash_batch = ?2",
+                (project.id(), batch),
+            )?;
+            restore_tasks(&conn, &format!("project = {}", project.id()), batch)
+        }
+        TrashItem::Section(section) => {
+            let batch = trash_batch(&conn, "sections", section.id())?;
+            restore_project(&conn, section.project())?;
+            restore_section(&conn, section.id())?;
+            restore_tasks(&conn, &format!("section = {}", section.id()), batch)
+        }
+        TrashItem::Task(task) => {
+            let (project, section, parent, _) = task_place(&conn, task.id())?;
+            let batch = trash_batch(&conn, "tasks", task.id())?;
+            restore_place(&conn, project, section, parent)?;
+            let position = if parent == 0 {
+                new_task_position(section)
+            } else {
+                new_subtask_position(parent)
+            };
+            let task_ids = restore_tasks(&conn, &format!("id = {}", task.id()), batch)?;
+            conn.execute(
+                "UPDATE tasks SET position = ?2 WHERE id = ?1",
+                (task.id(), position),
+            )?;
+            Ok(task_ids)
+        }
+    }
+}
+
+/// Remove the item for good with everything in the trash that belongs to it
+pub fn delete_trash_item(item: &TrashItem) -> Result<()> {
+    match item {
+        TrashItem::Project(project) => {
+            let condition = format!("project = {}", project.id());
+            purge(&condition, &condition, &format!("id = {}", project.id()))
+        }
+        TrashItem::Section(section) => purge(
+            &format!("section = {}", section.id()),
+            &format!("id = {}", section.id()),
+            "false",
+        ),
+        TrashItem::Task(task) => purge(&format!("id = {}", task.id()), "false", "false"),
+    }
+}
+
+pub fn empty_trash() -> Result<()> {
+    purge("true", "true", "true")
+}
+
+/// Remove the items trashed before the time
+pub fn purge_trash(before: i64) -> Result<()> {
+    let condition = format!("deleted_at < {before}");
+    purge(&condition, &condition, &condition)
+}
+
+fn now() -> i64 {
+    glib::DateTime::now_local().unwrap().to_unix()
+}
+
+fn task_place(conn: &Connection, task_id: i64) -> Result<(i64, i64, i64, i64)> {
+    conn.query_row(
+        "SELECT project, section, parent, deleted_at FROM tasks WHERE id = ?",
+        [task_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )
+}
+
+/// Items trashed together share a batch, it is kept after restoring them
+fn new_trash_batch(conn: &Connection) -> Result<i64> {
+    conn.query_row(
+        "SELECT max(
+            (SELECT coalesce(max(trash_batch), 0) FROM projects),
+            (SELECT coalesce(max(trash_batch), 0) FROM sections),
+            (SELECT coalesce(max(trash_batch), 0) FROM tasks)
+        ) + 1",
+        [],
+        |row| row.get(0),
+    )
+}
+
+fn trash_batch(conn: &Connection, table: &str, id: i64) -> Result<i64> {
+    conn.query_row(
+        &format!("SELECT trash_batch FROM {table} WHERE id = ?"),
+        [id],
+        |row| row.get(0),
+    )
+}
+
+/// Move the selected tasks and their subtasks to the trash in the batch,
+/// returns the trashed tasks
+fn trash_tasks(
+    conn: &Connection,
+    condition: &str,
+    batch: i64,
+    deleted_at: i64,
+) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(&format!(
+        "WITH RECURSIVE task_tree(id) AS (
+                SELECT id FROM tasks WHERE {condition} AND deleted_at = 0
+                UNION
+                SELECT tasks.id FROM tasks
+                    JOIN task_tree ON tasks.parent = task_tree.id
+                    WHERE tasks.deleted_at = 0
+            )
+            UPDATE tasks SET deleted_at = ?2, trash_batch = ?1, suspended = false
+            WHERE id IN (SELECT id FROM task_tree) RETURNING id"
+    ))?;
+    let rows = stmt.query_map((batch, deleted_at), |row| row.get(0))?;
+    rows.collect()
+}
+
+/// Bring back the selected tasks of the batch and their subtasks trashed with them,
+/// returns the restored tasks
+fn restore_tasks(conn: &Connection, condition: &str, batch: i64) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(&format!(
+        "WITH RECURSIVE task_tree(id) AS (
+                SELECT id FROM tasks WHERE {condition} AND deleted_at != 0 AND trash_batch = ?1
+                UNION
+                SELECT tasks.id FROM tasks
+                    JOIN task_tree ON tasks.parent = task_tree.id
+                    WHERE tasks.deleted_at != 0 AND tasks.trash_batch = ?1
+            )
+            UPDATE tasks SET deleted_at = 0, suspended = false
+            WHERE id IN (SELECT id FROM task_tree) RETURNING id"
+    ))?;
+    let rows = stmt.query_map([batch], |row| row.get(0))?;
+    rows.collect()
+}
+
+/// Save the open records of the tasks until the time, the timer forgets them after
+fn close_records(conn: &Connection, task_ids: &[i64], end: i64) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "UPDATE records SET duration = max(?2 - start, 1) WHERE task = ?1 AND duration = 0",
+    )?;
+    for task_id in task_ids {
+        stmt.execute((task_id, end))?;
+    }
+    Ok(())
+}
+
+/// Restore the trashed places of a restored task, from the parent up to the project
+fn restore_place(conn: &Connection, project: i64, section: i64, parent: i64) -> Result<()> {
+    if parent == 0 {
+        restore_project(conn, project)?;
+        return restore_section(conn, section);
+    }
+    let (parent_project, parent_section, grandparent, deleted_at) = task_place(conn, parent)?;
+    if deleted_at != 0 {
+        restore_place(conn, parent_project, parent_section, grandparent)?;
+        let position = if grandparent == 0 {
+            new_task_position(parent_section)
+        } else {
+            new_subtask_position(grandparent)
+        };
+        conn.execute(
+            "UPDATE tasks SET deleted_at = 0, suspended = false, position = ?2 WHERE id = ?1",
+            (parent, position),
+        )?;
+    }
+    Ok(())
+}
+
+fn restore_project(conn: &Connection, project_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE projects SET deleted_at = 0,
+            i = (SELECT coalesce(max(i), -1) + 1 FROM projects WHERE deleted_at = 0)
+        WHERE id = ?1 AND deleted_at != 0",
+        (project_id,),
+    )?;
+    Ok(())
+}
+
+fn restore_section(conn: &Connection, section_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE sections SET deleted_at = 0,
+            i = (SELECT coalesce(max(i), -1) + 1 FROM sections AS others
+                WHERE others.project = sections.project AND others.deleted_at = 0)
+        WHERE id = ?1 AND deleted_at != 0",
+        (section_id,),
+    )?;
+    Ok(())
+}
+
+/// Delete the trashed rows matching the conditions, the tasks with their trashed subtasks,
+/// records, reminders, tags and dependencies
+fn purge(tasks: &str, sections: &str, projects: &str) -> Result<()> {
+    let conn = get_connection();
+    conn.execute_batch(&format!(
+        "BEGIN TRANSACTION;
+        CREATE TEMPORARY TABLE temp_trash AS
+            WITH RECURSIVE task_tree(id) AS (
+                SELECT id FROM tasks WHERE deleted_at != 0 AND ({tasks})
+                UNION
+                SELECT tasks.id FROM tasks
+                    JOIN task_tree ON tasks.parent = task_tree.id
+                    WHERE tasks.deleted_at != 0
+            )
+            SELECT id FROM task_tree;
+
+        DELETE FROM records WHERE task IN (SELECT id FROM temp_trash);
+        DELETE FROM reminders WHERE task IN (SELECT id FROM temp_trash);
+        DELETE FROM task_uids WHERE task IN (SELECT id FROM temp_trash);
+        DELETE FROM task_tags WHERE task IN (SELECT id FROM temp_trash);
+        DELETE FROM task_dependencies
+            WHERE task IN (SELECT id FROM temp_trash) OR blocker IN (SELECT id FROM temp_trash);
+        DELETE FROM tasks WHERE id IN (SELECT id FROM temp_trash);
+        DELETE FROM sections WHERE deleted_at != 0 AND ({sections});
+        DELETE FROM projects WHERE deleted_at != 0 AND ({projects});
+
+        DROP TABLE temp_trash;
+        COMMIT;"
+    ))
+}
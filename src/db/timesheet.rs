@@ -0,0 +1,177 @@
+use gettextrs::gettext;
+use gtk::glib;
+use rusqlite::Result;
+use std::collections::HashMap;
+
+use crate::db::get_connection;
+
+struct TimesheetRow {
+    start: i64,
+    duration: i64,
+    task: i64,
+    section: String,
+    project: String,
+}
+
+/// Export the finished records that started in the time range as CSV.
+/// Each record is a row, or each day and task when aggregated.
+pub fn export_timesheet(
+    start: i64,
+    end: i64,
+    project_id: Option<i64>,
+    aggregate: bool,
+) -> Result<String> {
+    let conn = get_connection();
+    let project_filter = if let Some(project_id) = project_id {
+        format!("AND tasks.project = {project_id}")
+    } else {
+        String::new()
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT records.start, records.duration, records.task, coalesce(sections.name, ''), projects.name
+        FROM records
+        JOIN tasks ON tasks.id = records.task
+        JOIN projects ON projects.id = tasks.project
+        LEFT JOIN sections ON sections.id = tasks.section
+        WHERE records.duration > 0 AND records.start >= ?1 AND records.start < ?2
+        AND tasks.deleted_at = 0 {project_filter}
+        ORDER BY records.start"
+    ))?;
+    let rows = stmt
+        .query_map((start, end), |row| {
+            Ok(TimesheetRow {
+                start: row.get(0)?,
+                duration: row.get(1)?,
+                task: row.get(2)?,
+                section: row.get(3)?,
+                project: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<TimesheetRow>>>()?;
+
+    // Name and parent of tasks for the parent path
+    let mut stmt = conn.prepare("SELECT id, name, parent FROM tasks")?;
+    let tasks = stmt
+        .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+        .collect::<Result<HashMap<i64, (String, i64)>>>()?;
+
+    let mut lines = vec![];
+    if aggregate {
+        lines.push(vec![
+            gettext("Date"),
+            gettext("Duration"),
+            gettext("Hours"),
+            gettext("Task"),
+            gettext("Parent Tasks"),
+            gettext("Section"),
+            gettext("Project"),
+        ]);
+        // Keep the order of the first record of each day and task
+        let mut totals: Vec<(String, TimesheetRow)> = vec![];
+        for row in rows {
+            let date = format_unix(row.start, "%F");
+            if let Some((_, total)) = totals
+                .iter_mut()
+                .find(|(total_date, total)| total_date == &date && total.task == row.task)
+            {
+                total.duration += row.duration;
+            } else {
+                totals.push((date, row));
+            }
+        }
+        for (date, row) in totals {
+            let (task_name, _) = tasks.get(&row.task).cloned().unwrap_or_default();
+            lines.push(vec![
+                date,
+                duration_text(row.duration),
+                hours_text(row.duration),
+                task_name,
+                parent_path(&tasks, row.task),
+                row.section,
+                row.project,
+            ]);
+        }
+    } else {
+        lines.push(vec![
+            gettext("Date"),
+            gettext("Start"),
+            gettext("End"),
+            gettext("Duration"),
+            gettext("Hours"),
+            gettext("Task"),
+            gettext("Parent Tasks"),
+            gettext("Section"),
+            gettext("Project"),
+        ]);
+        for row in rows {
+            let (task_name, _) = tasks.get(&row.task).cloned().unwrap_or_default();
+            lines.push(vec![
+                format_unix(row.start, "%F"),
+                format_unix(row.start, "%T"),
+                format_unix(row.start + row.duration, "%T"),
+                duration_text(row.duration),
+                hours_text(row.duration),
+                task_name,
+                parent_path(&tasks, row.task),
+                row.section,
+                row.project,
+            ]);
+        }
+    }
+
+    let mut csv = String::new();
+    for line in lines {
+        let fields = line
+            .iter()
+            .map(|field| escape_field(field))
+            .collect::<Vec<String>>();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    Ok(csv)
+}
+
+/// Names of the parent tasks from the top level one, separated by slash
+fn parent_path(tasks: &HashMap<i64, (String, i64)>, task_id: i64) -> String {
+    let mut names = vec![];
+    let mut parent = tasks.get(&task_id).map(|(_, parent)| *parent).unwrap_or(0);
+    while let Some((name, next_parent)) = tasks.get(&parent) {
+        // Prevent from looping on broken parents
+        if names.len() == tasks.len() {
+            break;
+        }
+        names.insert(0, name.as_str());
+        parent = *next_parent;
+    }
+    names.join(" / ")
+}
+
+fn format_unix(unix: i64, format: &str) -> String {
+    glib::DateTime::from_unix_local(unix)
+        .unwrap()
+        .format(format)
+        .unwrap()
+        .to_string()
+}
+
+fn duration_text(duration: i64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        duration / 3600,
+        duration % 3600 / 60,
+        duration % 60
+    )
+}
+
+fn hours_text(duration: i64) -> String {
+    format!("{:.2}", duration as f64 / 3600.0)
+}
+
+/// Quote fields with separators, quotes or line breaks as RFC 4180
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,280 @@
+/* dbus.rs
+ *
+ * Copyright 2023 Iman Salmani
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use gtk::{gio, glib, prelude::*};
+use std::collections::HashMap;
+
+use crate::application::IPlanApplication;
+use crate::db::models::Task;
+use crate::db::operations::{
+    create_next_occurrence, create_task, new_subtask_position, new_task_position, read_projects,
+    read_reminders, read_section, read_sections, read_task, read_tasks, revert_next_occurrence,
+    update_task, without_history,
+};
+
+pub const INTERFACE_NAME: &str = "ir.imansalmani.IPlan.Tasks";
+const INTERFACE_XML: &str = include_str!("../data/ir.imansalmani.IPlan.Tasks.xml");
+
+enum MethodError {
+    NotFound(&'static str),
+    InvalidArgs(&'static str),
+    Failed(String),
+}
+
+impl From<rusqlite::Error> for MethodError {
+    fn from(value: rusqlite::Error) -> Self {
+        MethodError::Failed(value.to_string())
+    }
+}
+
+type MethodResult = Result<Option<glib::Variant>, MethodError>;
+
+pub fn register_object(
+    application: &IPlanApplication,
+    connection: &gio::DBusConnection,
+    object_path: &str,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let interface_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?
+        .lookup_interface(INTERFACE_NAME)
+        .unwrap();
+    connection.register_object(
+        object_path,
+        &interface_info,
+        glib::clone!(@weak application => move |_, _, _, _, method, parameters, invocation| {
+            // Calls from other apps are not undone in this one
+            let result = without_history(|| match method {
+                "CreateTask" => create(&application, parameters),
+                "GetTask" => get(parameters),
+                "QueryTasks" => query(parameters),
+                "CompleteTask" => complete(&application, parameters),
+                "MoveTask" => move_to_section(&application, parameters),
+                "StartTimer" => start_timer(&application, parameters),
+                "StopTimer" => stop_timer(&application, parameters),
+                _ => Err(MethodError::InvalidArgs("Unknown method")),
+            });
+            match result {
+                Ok(value) => invocation.return_value(value.as_ref()),
+                Err(MethodError::NotFound(message)) => {
+                    invocation.return_dbus_error("ir.imansalmani.IPlan.Error.NotFound", message)
+                }
+                Err(MethodError::InvalidArgs(message)) => {
+                    invocation.return_dbus_error("ir.imansalmani.IPlan.Error.InvalidArgs", message)
+                }
+                Err(MethodError::Failed(message)) => {
+                    invocation.return_dbus_error("ir.imansalmani.IPlan.Error.Failed", &message)
+                }
+            }
+        }),
+        // The interface doesn't have properties
+        |_, _, _, _, _| unreachable!(),
+        |_, _, _, _, _, _| unreachable!(),
+    )
+}
+
+fn task_dict(task: &Task) -> glib::Variant {
+    let mut dict: HashMap<String, glib::Variant> = HashMap::new();
+    dict.insert("id".to_string(), task.id().to_variant());
+    dict.insert("name".to_string(), task.name().to_variant());
+    dict.insert("done".to_string(), task.done().to_variant());
+    dict.insert("project".to_string(), task.project().to_variant());
+    dict.insert("section".to_string(), task.section().to_variant());
+    dict.insert("position".to_string(), task.position().to_variant());
+    dict.insert("parent".to_string(), task.parent().to_variant());
+    dict.insert("description".to_string(), task.description().to_variant());
+    dict.insert("date".to_string(), task.date().to_variant());
+    dict.insert("repeat".to_string(), task.repeat().to_variant());
+    dict.insert("duration".to_string(), task.duration().to_variant());
+    dict.to_variant()
+}
+
+fn lookup<T: glib::FromVariant>(
+    dict: &glib::VariantDict,
+    key: &str,
+) -> Result<Option<T>, MethodError> {
+    dict.lookup::<T>(key)
+        .map_err(|_| MethodError::InvalidArgs("Invalid type for a dictionary value"))
+}
+
+fn find_task(task_id: i64) -> Result<Task, MethodError> {
+    read_task(task_id).map_err(|_| MethodError::NotFound("Task not found"))
+}
+
+fn create(application: &IPlanApplication, parameters: glib::Variant) -> MethodResult {
+    let (properties,): (glib::VariantDict,) = parameters.get().unwrap();
+    let name: String =
+        lookup(&properties, "name")?.ok_or(MethodError::InvalidArgs("Task name is required"))?;
+    let description: String = lookup(&properties, "description")?.unwrap_or_default();
+    let date: i64 = lookup(&properties, "date")?.unwrap_or_default();
+
+    let (project, section, parent, position) =
+        if let Some(parent_id) = lookup::<i64>(&properties, "parent")? {
+            let parent = find_task(parent_id)?;
+            (
+                parent.project(),
+                parent.section(),
+                parent_id,
+                new_subtask_position(parent_id),
+            )
+        } else {
+            let project = if let Some(project) = lookup::<i64>(&properties, "project")? {
+                project
+            } else {
+                read_projects(true)?
+                    .first()
+                    .ok_or(MethodError::NotFound("Project not found"))?
+                    .id()
+            };
+            let section = if let Some(section) = lookup::<i64>(&properties, "section")? {
+                let section = read_section(section)
+                    .map_err(|_| MethodError::NotFound("Section not found"))?;
+                if section.project() != project {
+                    return Err(MethodError::InvalidArgs(
+                        "Section doesn't belong to the project",
+                    ));
+                }
+                section.id()
+            } else {
+                read_sections(project)?
+                    .first()
+                    .ok_or(MethodError::NotFound("Section not found"))?
+                    .id()
+            };
+            (project, section, 0, new_task_position(section))
+        };
+
+    let task = create_task(Task::new(&[
+        ("name", &name),
+        ("project", &project),
+        ("section", &section),
+        ("position", &position),
+        ("parent", &parent),
+        ("description", &description),
+        ("date", &date),
+    ]))?;
+    application.refresh_task(&task);
+    Ok(Some((task.id(),).to_variant()))
+}
+
+fn get(parameters: glib::Variant) -> MethodResult {
+    let (task_id,): (i64,) = parameters.get().unwrap();
+    let task = find_task(task_id)?;
+    Ok(Some(glib::Variant::tuple_from_iter([task_dict(&task)])))
+}
+
+fn query(parameters: glib::Variant) -> MethodResult {
+    let (filters,): (glib::VariantDict,) = parameters.get().unwrap();
+    let time_range = match (
+        lookup::<i64>(&filters, "start")?,
+        lookup::<i64>(&filters, "end")?,
+    ) {
+        (Some(start), Some(end)) => Some((start, end)),
+        (None, None) => None,
+        _ => {
+            return Err(MethodError::InvalidArgs(
+                "Start and end must be used together",
+            ))
+        }
+    };
+    let tasks = read_tasks(
+        lookup(&filters, "project")?,
+        lookup(&filters, "section")?,
+        lookup(&filters, "done")?,
+        lookup(&filters, "parent")?,
+        time_range,
+        false,
+    )?;
+    let tasks = glib::Variant::array_from_iter_with_type(
+        glib::VariantTy::VARDICT,
+        tasks.iter().map(task_dict),
+    );
+    Ok(Some(glib::Variant::tuple_from_iter([tasks])))
+}
+
+fn complete(application: &IPlanApplication, parameters: glib::Variant) -> MethodResult {
+    let (task_id, done): (i64, bool) = parameters.get().unwrap();
+    let task = find_task(task_id)?;
+    if task.done() == done {
+        return Ok(Some((0_i64,).to_variant()));
+    }
+
+    task.set_done(done);
+    update_task(&task)?;
+    let mut next_id = 0;
+    if done {
+        application.timer().stop(&task)?;
+        if let Some(next_task) = create_next_occurrence(&task)? {
+            for reminder in read_reminders(Some(next_task.id()))? {
+                application.send_reminder(reminder);
+            }
+            application.refresh_task(&next_task);
+            next_id = next_task.id();
+        }
+    } else if let Some(next_task) = revert_next_occurrence(&task)? {
+        application.remove_task_row(&next_task);
+    }
+    application.refresh_task(&task);
+    application.refresh_dependents(&task);
+    Ok(Some((next_id,).to_variant()))
+}
+
+fn move_to_section(application: &IPlanApplication, parameters: glib::Variant) -> MethodResult {
+    let (task_id, section_id): (i64, i64) = parameters.get().unwrap();
+    let task = find_task(task_id)?;
+    let section =
+        read_section(section_id).map_err(|_| MethodError::NotFound("Section not found"))?;
+    if task.parent() == 0 && task.section() == section_id {
+        return Ok(None);
+    }
+
+    let parent = task.parent();
+    if parent == 0 {
+        application.remove_task_row(&task);
+    }
+    task.set_position(new_task_position(section_id));
+    task.set_parent(0);
+    task.set_section(section_id);
+    task.set_project(section.project());
+    update_task(&task)?;
+    application.refresh_task(&task);
+    if parent != 0 {
+        application.refresh_task(&find_task(parent)?);
+    }
+    Ok(None)
+}
+
+fn start_timer(application: &IPlanApplication, parameters: glib::Variant) -> MethodResult {
+    let (task_id,): (i64,) = parameters.get().unwrap();
+    let task = find_task(task_id)?;
+    if task.done() {
+        return Err(MethodError::InvalidArgs("Task is done"));
+    }
+    let record = application.timer().start(&task)?;
+    Ok(Some((record.id(),).to_variant()))
+}
+
+fn stop_timer(application: &IPlanApplication, parameters: glib::Variant) -> MethodResult {
+    let (task_id,): (i64,) = parameters.get().unwrap();
+    let task = find_task(task_id)?;
+    let record = application
+        .timer()
+        .stop(&task)?
+        .ok_or(MethodError::NotFound("Timer is not running"))?;
+    Ok(Some((record.duration(),).to_variant()))
+}
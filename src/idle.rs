@@ -0,0 +1,213 @@
+/* idle.rs
+ *
+ * Copyright 2023 Iman Salmani
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use gtk::{gio, glib};
+
+// GNOME idle monitor, replies with milliseconds
+const MUTTER_NAME: &str = "org.gnome.Mutter.IdleMonitor";
+const MUTTER_PATH: &str = "/org/gnome/Mutter/IdleMonitor/Core";
+
+// Freedesktop screensaver for other desktops, replies with seconds
+const SCREENSAVER_NAME: &str = "org.freedesktop.ScreenSaver";
+const SCREENSAVER_PATH: &str = "/org/freedesktop/ScreenSaver";
+
+/// Seconds since the last user input in the session, None without an idle monitor
+pub fn query_idle_time<F: FnOnce(Option<i64>) + 'static>(callback: F) {
+    gio::bus_get(
+        gio::BusType::Session,
+        gio::Cancellable::NONE,
+        move |connection| match connection {
+            Ok(connection) => query_mutter(connection, callback),
+            Err(_) => callback(None),
+        },
+    );
+}
+
+fn query_mutter<F: FnOnce(Option<i64>) + 'static>(connection: gio::DBusConnection, callback: F) {
+    connection.clone().call(
+        Some(MUTTER_NAME),
+        MUTTER_PATH,
+        MUTTER_NAME,
+        "GetIdletime",
+        None,
+        Some(glib::VariantTy::new("(t)").unwrap()),
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+        move |reply| match reply {
+            Ok(reply) => {
+                let (milliseconds,) = reply.get::<(u64,)>().unwrap();
+                callback(Some((milliseconds / 1000) as i64));
+            }
+            Err(_) => query_screensaver(connection, callback),
+        },
+    );
+}
+
+fn query_screensaver<F: FnOnce(Option<i64>) + 'static>(
+    connection: gio::DBusConnection,
+    callback: F,
+) {
+    connection.call(
+        Some(SCREENSAVER_NAME),
+        SCREENSAVER_PATH,
+        SCREENSAVER_NAME,
+        "GetSessionIdleTime",
+        None,
+        Some(glib::VariantTy::new("(u)").unwrap()),
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+        move |reply| {
+            callback(
+                reply
+                    .ok()
+                    .map(|reply| reply.get::<(u32,)>().unwrap().0 as i64),
+            )
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gtk::prelude::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const INTERFACES_XML: &str = r#"<node>
+      <interface name="org.gnome.Mutter.IdleMonitor">
+        <method name="GetIdletime">
+          <arg name="idletime" type="t" direction="out" />
+        </method>
+      </interface>
+      <interface name="org.freedesktop.ScreenSaver">
+        <method name="GetSessionIdleTime">
+          <arg name="seconds" type="u" direction="out" />
+        </method>
+      </interface>
+    </node>"#;
+
+    /// Private session bus, gio doesn't bind GTestDBus yet
+    struct TestBus(*mut gio::ffi::GTestDBus);
+
+    impl TestBus {
+        fn up() -> Self {
+            unsafe {
+                let bus = gio::ffi::g_test_dbus_new(gio::ffi::G_TEST_DBUS_NONE);
+                gio::ffi::g_test_dbus_up(bus);
+                Self(bus)
+            }
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            unsafe {
+                gio::ffi::g_test_dbus_down(self.0);
+                glib::gobject_ffi::g_object_unref(self.0 as *mut _);
+            }
+        }
+    }
+
+    fn wait_idle_time(context: &glib::MainContext) -> Option<i64> {
+        let reply = Rc::new(RefCell::new(None));
+        query_idle_time(glib::clone!(@strong reply => move |idle_time| {
+            reply.replace(Some(idle_time));
+        }));
+        while reply.borrow().is_none() {
+            context.iteration(true);
+        }
+        reply.take().unwrap()
+    }
+
+    /// Serve a monitor stub on its own connection, so the app connection sees it as another peer
+    fn serve(connection: &gio::DBusConnection, name: &str, path: &str, reply: glib::Variant) {
+        let interface_info = gio::DBusNodeInfo::for_xml(INTERFACES_XML)
+            .unwrap()
+            .lookup_interface(name)
+            .unwrap();
+        let reply = reply.to_string();
+        connection
+            .register_object(
+                path,
+                &interface_info,
+                move |_, _, _, _, _, _, invocation| {
+                    let reply = glib::Variant::parse(None, &reply).unwrap();
+                    invocation.return_value(Some(&reply));
+                },
+                |_, _, _, _, _| unreachable!(),
+                |_, _, _, _, _, _| unreachable!(),
+            )
+            .unwrap();
+        connection
+            .call_sync(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "RequestName",
+                Some(&(name, 0_u32).to_variant()),
+                None,
+                gio::DBusCallFlags::NONE,
+                -1,
+                gio::Cancellable::NONE,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn idle_time_from_session_bus() {
+        let _bus = TestBus::up();
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                assert_eq!(wait_idle_time(&context), None);
+
+                let address = std::env::var("DBUS_SESSION_BUS_ADDRESS").unwrap();
+                let service = gio::DBusConnection::for_address_sync(
+                    &address,
+                    gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                        | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+                    None,
+                    gio::Cancellable::NONE,
+                )
+                .unwrap();
+                serve(
+                    &service,
+                    SCREENSAVER_NAME,
+                    SCREENSAVER_PATH,
+                    (7_u32,).to_variant(),
+                );
+                assert_eq!(wait_idle_time(&context), Some(7));
+
+                // Mutter replies with milliseconds and is asked first
+                serve(
+                    &service,
+                    MUTTER_NAME,
+                    MUTTER_PATH,
+                    (42_500_u64,).to_variant(),
+                );
+                assert_eq!(wait_idle_time(&context), Some(42));
+
+                service.close_sync(gio::Cancellable::NONE).unwrap();
+            })
+            .unwrap();
+    }
+}
@@ -19,8 +19,14 @@
  */
 
 mod application;
+mod caldav;
+mod cli;
 mod config;
 mod db;
+mod dbus;
+mod idle;
+mod pomodoro;
+mod timer;
 mod views;
 
 use self::application::IPlanApplication;
@@ -48,7 +54,7 @@ fn main() -> glib::ExitCode {
     // application windows, integration with the window manager/compositor, and
     // desktop features such as file opening and single-instance applications.
 
-    let app = IPlanApplication::new(APPLICATION_ID, &gio::ApplicationFlags::empty());
+    let app = IPlanApplication::new(APPLICATION_ID, &gio::ApplicationFlags::HANDLES_COMMAND_LINE);
 
     // Run the application. This function will block until the application
     // exits. Upon return, we have our exit code to return to the shell. (This
@@ -0,0 +1,101 @@
+/* pomodoro.rs
+ *
+ * Copyright 2023 Iman Salmani
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use gettextrs::gettext;
+use gtk::{gio, prelude::*};
+
+use crate::db::models::Record;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum PomodoroPhase {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    pub fn display(&self) -> String {
+        match self {
+            Self::Work => gettext("Work"),
+            Self::ShortBreak => gettext("Short Break"),
+            Self::LongBreak => gettext("Long Break"),
+        }
+    }
+
+    /// Length in seconds from the settings
+    pub fn length(&self, settings: &gio::Settings) -> i64 {
+        let key = match self {
+            Self::Work => "pomodoro-work",
+            Self::ShortBreak => "pomodoro-short-break",
+            Self::LongBreak => "pomodoro-long-break",
+        };
+        settings.int(key) as i64 * 60
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Pomodoro {
+    pub task: i64,
+    pub phase: PomodoroPhase,
+    /// Unix time of the phase start, None while waiting for the user
+    pub start: Option<i64>,
+    /// Finished work intervals of the current cycle
+    pub intervals: i32,
+}
+
+impl Pomodoro {
+    pub fn new(task: i64, start: i64) -> Self {
+        Self {
+            task,
+            phase: PomodoroPhase::Work,
+            start: Some(start),
+            intervals: 0,
+        }
+    }
+
+    /// Seconds left from the running phase
+    pub fn remaining(&self, settings: &gio::Settings, now: i64) -> Option<i64> {
+        self.start
+            .map(|start| start + self.phase.length(settings) - now)
+    }
+
+    pub fn label(&self, settings: &gio::Settings, now: i64) -> Option<String> {
+        self.remaining(settings, now).map(|remaining| {
+            format!(
+                "{} {}",
+                self.phase.display(),
+                Record::duration_display(remaining.max(0))
+            )
+        })
+    }
+
+    /// Finish the work interval and choose the break
+    pub fn finish_work(&mut self, settings: &gio::Settings) {
+        self.intervals += 1;
+        if self.intervals >= settings.int("pomodoro-cycles") {
+            self.intervals = 0;
+            self.phase = PomodoroPhase::LongBreak;
+        } else {
+            self.phase = PomodoroPhase::ShortBreak;
+        }
+        self.start = None;
+    }
+}
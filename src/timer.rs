@@ -0,0 +1,427 @@
+/* timer.rs
+ *
+ * Copyright 2023 Iman Salmani
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ */
+
+use glib::{once_cell::sync::Lazy, subclass::Signal};
+use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+use rusqlite::Result;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::db::models::{Record, Task};
+use crate::db::operations::{
+    create_record, read_record, read_records, read_task, read_trashed_task,
+    repair_incomplete_records, start_record, update_record, without_history, TimerPolicy,
+};
+use crate::idle::query_idle_time;
+use crate::pomodoro::{Pomodoro, PomodoroPhase};
+
+// Seconds between the idle time checks
+const IDLE_CHECK_INTERVAL: u32 = 5;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct Timer {
+        /// Open records by their task
+        pub records: RefCell<HashMap<i64, Record>>,
+        pub pomodoro: RefCell<Option<Pomodoro>>,
+        pub ticking: Cell<bool>,
+        pub ticks: Cell<u32>,
+        /// Unix time of the last input when the user is away
+        pub idle_start: Cell<Option<i64>>,
+        pub settings: RefCell<Option<gio::Settings>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Timer {
+        const NAME: &'static str = "Timer";
+        type Type = super::Timer;
+    }
+
+    impl ObjectImpl for Timer {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![
+                    Signal::builder("started")
+                        .param_types([Task::static_type(), Record::static_type()])
+                        .build(),
+                    Signal::builder("stopped")
+                        .param_types([Task::static_type(), Record::static_type()])
+                        .build(),
+                    Signal::builder("tick").build(),
+                    // Back from idle with the idle start time
+                    Signal::builder("idle-finished")
+                        .param_types([i64::static_type()])
+                        .build(),
+                    // The boolean is true for the end of a work interval
+                    Signal::builder("pomodoro-finished")
+                        .param_types([Task::static_type(), bool::static_type()])
+                        .build(),
+                ]
+            });
+            SIGNALS.as_ref()
+        }
+
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.settings
+                .replace(Some(gio::Settings::new("ir.imansalmani.IPlan.State")));
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct Timer(ObjectSubclass<imp::Timer>);
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continue the records left open from the last run
+    pub fn load_records(&self) {
+        repair_incomplete_records(self.policy()).expect("Failed to repair records");
+        let records = read_records(None, true, None, None).expect("Failed to read records");
+        for record in records {
+            self.imp()
+                .records
+                .borrow_mut()
+                .insert(record.task(), record);
+        }
+        self.watch();
+    }
+
+    pub fn is_running(&self, task_id: i64) -> bool {
+        self.imp().records.borrow().contains_key(&task_id)
+    }
+
+    /// The open record of the task with the duration until now
+    pub fn record(&self, task_id: i64) -> Option<Record> {
+        let record = self.imp().records.borrow().get(&task_id)?.to_owned();
+        let duration = now() - record.start();
+        Some(Record::new(
+            record.id(),
+            record.start(),
+            duration,
+            record.task(),
+        ))
+    }
+
+    /// Open records with the duration until now
+    pub fn running_records(&self) -> Vec<Record> {
+        let task_ids: Vec<i64> = self.imp().records.borrow().keys().copied().collect();
+        task_ids
+            .into_iter()
+            .filter_map(|task_id| self.record(task_id))
+            .collect()
+    }
+
+    /// Pomodoro phase or the running duration of the task
+    pub fn label(&self, task_id: i64) -> Option<String> {
+        if let Some(label) = self.pomodoro_label(task_id) {
+            return Some(label);
+        }
+        self.record(task_id)
+            .map(|record| Record::duration_display(record.duration()))
+    }
+
+    pub fn start(&self, task: &Task) -> Result<Record> {
+        if let Some(record) = self.record(task.id()) {
+            return Ok(record);
+        }
+        self.remove_trashed_records()?;
+        let (record, closed_records) = start_record(task.id(), now(), self.policy())?;
+        for closed_record in closed_records {
+            self.stop_pomodoro(closed_record.task());
+            let closed_task = read_task(closed_record.task())?;
+            self.remove_record(&closed_task, &closed_record);
+        }
+        self.start_pomodoro(task, &record);
+        self.add_record(task, record.clone());
+        Ok(record)
+    }
+
+    /// Follow a record opened somewhere else
+    pub fn add_record(&self, task: &Task, record: Record) {
+        self.imp()
+            .records
+            .borrow_mut()
+            .insert(task.id(), record.clone());
+        self.emit_by_name::<()>("started", &[task, &record]);
+        self.watch();
+    }
+
+    /// Save the open record of the task
+    pub fn stop(&self, task: &Task) -> Result<Option<Record>> {
+        let Some(record) = self.record(task.id()) else {
+            return Ok(None);
+        };
+        // Zero is for open records
+        record.set_duration(record.duration().max(1));
+        update_record(&record)?;
+        self.stop_pomodoro(task.id());
+        self.remove_record(task, &record);
+        Ok(Some(record))
+    }
+
+    /// Forget a record closed somewhere else
+    pub fn remove_record(&self, task: &Task, record: &Record) {
+        if self.imp().records.borrow_mut().remove(&task.id()).is_some() {
+            self.emit_by_name::<()>("stopped", &[task, record]);
+        }
+    }
+
+    /// Forget the records of the tasks moved to the trash, the trash saves them
+    pub fn remove_trashed_records(&self) -> Result<()> {
+        let records: Vec<Record> = self.imp().records.borrow().values().cloned().collect();
+        for record in records {
+            match read_task(record.task()) {
+                Err(rusqlite::Error::QueryReturnedNoRows) => (),
+                result => {
+                    result?;
+                    continue;
+                }
+            }
+            self.stop_pomodoro(record.task());
+            if let Ok(task) = read_trashed_task(record.task()) {
+                self.remove_record(&task, &read_record(record.id())?);
+            } else {
+                // Deleted for good with its records
+                self.imp().records.borrow_mut().remove(&record.task());
+            }
+        }
+        Ok(())
+    }
+
+    fn watch(&self) {
+        let imp = self.imp();
+        if imp.ticking.get() {
+            return;
+        }
+        imp.ticking.set(true);
+        glib::timeout_add_seconds_local(
+            1,
+            glib::clone!(@weak self as obj => @default-return glib::ControlFlow::Break, move || {
+                obj.tick()
+            }),
+        );
+    }
+
+    fn tick(&self) -> glib::ControlFlow {
+        let imp = self.imp();
+        self.pomodoro_tick();
+        self.emit_by_name::<()>("tick", &[]);
+        imp.ticks.set(imp.ticks.get().wrapping_add(1));
+        if imp.ticks.get() % IDLE_CHECK_INTERVAL == 0 {
+            self.check_idle();
+        }
+        if imp.records.borrow().is_empty() && imp.pomodoro.borrow().is_none() {
+            imp.ticking.set(false);
+            return glib::ControlFlow::Break;
+        }
+        glib::ControlFlow::Continue
+    }
+
+    fn check_idle(&self) {
+        let settings = self.settings();
+        if !settings.boolean("idle-detection") || self.imp().records.borrow().is_empty() {
+            return;
+        }
+        let threshold = settings.int("idle-threshold") as i64 * 60;
+        query_idle_time(glib::clone!(@weak self as obj => move |idle_time| {
+            let Some(idle_time) = idle_time else {
+                return;
+            };
+            let imp = obj.imp();
+            if idle_time >= threshold {
+                if imp.idle_start.get().is_none() {
+                    imp.idle_start.set(Some(now() - idle_time));
+                }
+            } else if let Some(idle_start) = imp.idle_start.take() {
+                if !imp.records.borrow().is_empty() {
+                    obj.emit_by_name::<()>("idle-finished", &[&idle_start]);
+                }
+            }
+        }));
+    }
+
+    /// Remove the idle time from the open records by splitting them at the idle start,
+    /// the records started while idle start from now
+    pub fn discard_idle_time(&self, idle_start: i64) -> Result<()> {
+        self.remove_trashed_records()?;
+        let records: Vec<Record> = self.imp().records.borrow().values().cloned().collect();
+        let now = now();
+        for record in records {
+            let task = read_task(record.task())?;
+            if record.start() < idle_start {
+                record.set_duration((idle_start - record.start()).max(1));
+                update_record(&record)?;
+                self.remove_record(&task, &record);
+                self.add_record(&task, create_record(now, task.id(), 0)?);
+            } else {
+                record.set_start(now);
+                update_record(&record)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop the open records at the idle start
+    pub fn stop_at_idle_start(&self, idle_start: i64) -> Result<()> {
+        self.remove_trashed_records()?;
+        let records: Vec<Record> = self.imp().records.borrow().values().cloned().collect();
+        for record in records {
+            let task = read_task(record.task())?;
+            record.set_duration((idle_start - record.start()).max(1));
+            update_record(&record)?;
+            self.stop_pomodoro(task.id());
+            self.remove_record(&task, &record);
+        }
+        Ok(())
+    }
+
+    fn settings(&self) -> gio::Settings {
+        self.imp().settings.borrow().clone().unwrap()
+    }
+
+    fn policy(&self) -> TimerPolicy {
+        TimerPolicy::from_name(&self.settings().string("timer-policy"))
+    }
+
+    fn pomodoro_label(&self, task_id: i64) -> Option<String> {
+        let pomodoro = self.imp().pomodoro.borrow();
+        let pomodoro = pomodoro
+            .as_ref()
+            .filter(|pomodoro| pomodoro.task == task_id)?;
+        pomodoro.label(&self.settings(), now())
+    }
+
+    pub fn pomodoro_phase(&self) -> Option<PomodoroPhase> {
+        self.imp()
+            .pomodoro
+            .borrow()
+            .as_ref()
+            .map(|pomodoro| pomodoro.phase)
+    }
+
+    fn start_pomodoro(&self, task: &Task, record: &Record) {
+        if !self.settings().boolean("pomodoro-enabled") {
+            return;
+        }
+        let mut pomodoro = self.imp().pomodoro.borrow_mut();
+        match pomodoro.as_mut() {
+            // Keep the cycle when working on the same task
+            Some(pomodoro) if pomodoro.task == task.id() => {
+                pomodoro.phase = PomodoroPhase::Work;
+                pomodoro.start = Some(record.start());
+            }
+            _ => *pomodoro = Some(Pomodoro::new(task.id(), record.start())),
+        }
+    }
+
+    fn stop_pomodoro(&self, task_id: i64) {
+        let imp = self.imp();
+        if imp
+            .pomodoro
+            .borrow()
+            .as_ref()
+            .is_some_and(|pomodoro| pomodoro.task == task_id)
+        {
+            imp.pomodoro.replace(None);
+        }
+    }
+
+    pub fn start_pomodoro_break(&self) {
+        if let Some(pomodoro) = self.imp().pomodoro.borrow_mut().as_mut() {
+            if pomodoro.phase != PomodoroPhase::Work && pomodoro.start.is_none() {
+                pomodoro.start = Some(now());
+            }
+        }
+        self.watch();
+    }
+
+    /// Start the next work interval
+    pub fn continue_pomodoro(&self) -> Result<()> {
+        let Some(task_id) = self
+            .imp()
+            .pomodoro
+            .borrow()
+            .as_ref()
+            .map(|pomodoro| pomodoro.task)
+        else {
+            return Ok(());
+        };
+        self.start(&read_task(task_id)?)?;
+        Ok(())
+    }
+
+    fn pomodoro_tick(&self) {
+        let settings = self.settings();
+        let mut pomodoro = self.imp().pomodoro.borrow_mut();
+        let Some(state) = pomodoro.as_mut() else {
+            return;
+        };
+        let Ok(task) = read_task(state.task) else {
+            *pomodoro = None;
+            return;
+        };
+        let (Some(start), Some(remaining)) = (state.start, state.remaining(&settings, now()))
+        else {
+            // Waiting for the user
+            return;
+        };
+        if remaining > 0 {
+            return;
+        }
+
+        let is_work = state.phase == PomodoroPhase::Work;
+        if is_work {
+            let Some(record) = self.imp().records.borrow().get(&task.id()).cloned() else {
+                // The timer stopped somewhere else
+                *pomodoro = None;
+                return;
+            };
+            state.finish_work(&settings);
+            drop(pomodoro);
+
+            let work_end = start + PomodoroPhase::Work.length(&settings);
+            record.set_duration((work_end - record.start()).max(1));
+            without_history(|| update_record(&record)).expect("Failed to update record");
+            self.remove_record(&task, &record);
+        } else {
+            state.phase = PomodoroPhase::Work;
+            state.start = None;
+            drop(pomodoro);
+        }
+        self.emit_by_name::<()>("pomodoro-finished", &[&task, &is_work]);
+    }
+}
+
+fn now() -> i64 {
+    glib::DateTime::now_local().unwrap().to_unix()
+}
@@ -1,9 +1,15 @@
 use adw::{prelude::*, subclass::prelude::*};
 use gettextrs::gettext;
 use gtk::{gdk, gio, glib};
+use std::cell::RefCell;
 use std::fs;
 
 use crate::db::check_database;
+use crate::db::ics::{export_ics, import_ics, IcsImport};
+use crate::db::json::{export_json, import_json, ImportMode};
+use crate::db::operations::{clear_history, read_projects, read_reminder, read_sections};
+use crate::db::timesheet::export_timesheet;
+use crate::views::snippets::DateRow;
 use crate::IPlanApplication;
 
 use super::IPlanWindow;
@@ -16,6 +22,22 @@ mod imp {
     pub struct BackupWindow {
         #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub ics_project_row: TemplateChild<adw::ComboRow>,
+        pub ics_projects: RefCell<Vec<i64>>,
+        #[template_child]
+        pub ics_import_project_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub ics_import_section_row: TemplateChild<adw::ComboRow>,
+        pub ics_sections: RefCell<Vec<i64>>,
+        #[template_child]
+        pub timesheet_start_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub timesheet_end_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub timesheet_project_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub timesheet_aggregate_switch: TemplateChild<gtk::Switch>,
     }
 
     #[glib::object_subclass]
@@ -53,9 +75,336 @@ impl BackupWindow {
             .property("application", application)
             .build();
         win.set_transient_for(Some(app_window));
+        win.add_projects();
+        win.init_timesheet_range();
         win
     }
 
+    fn add_projects(&self) {
+        let imp = self.imp();
+        let model = imp
+            .ics_project_row
+            .model()
+            .and_downcast::<gtk::StringList>()
+            .unwrap();
+        let import_model = imp
+            .ics_import_project_row
+            .model()
+            .and_downcast::<gtk::StringList>()
+            .unwrap();
+        let timesheet_model = imp
+            .timesheet_project_row
+            .model()
+            .and_downcast::<gtk::StringList>()
+            .unwrap();
+        let mut project_ids = vec![];
+        for project in read_projects(true).expect("Failed to read projects") {
+            model.append(&project.name());
+            import_model.append(&project.name());
+            timesheet_model.append(&project.name());
+            project_ids.push(project.id());
+        }
+        imp.ics_projects.replace(project_ids);
+        self.refresh_ics_sections();
+    }
+
+    fn init_timesheet_range(&self) {
+        // Current month by default
+        let imp = self.imp();
+        let today = glib::DateTime::now_local().unwrap();
+        let month_start = today.add_days(1 - today.day_of_month()).unwrap();
+        imp.timesheet_start_row.set_datetime(&month_start);
+        imp.timesheet_end_row.set_datetime(&today);
+    }
+
+    fn refresh_ics_sections(&self) {
+        let imp = self.imp();
+        let model = imp
+            .ics_import_section_row
+            .model()
+            .and_downcast::<gtk::StringList>()
+            .unwrap();
+        model.splice(0, model.n_items(), &[]);
+        let mut section_ids = vec![];
+        let selected = imp.ics_import_project_row.selected() as usize;
+        if let Some(project_id) = imp.ics_projects.borrow().get(selected) {
+            for section in read_sections(*project_id).expect("Failed to read sections") {
+                model.append(&section.name());
+                section_ids.push(section.id());
+            }
+        }
+        imp.ics_sections.replace(section_ids);
+    }
+
+    #[template_callback]
+    fn ics_import_project_selected(&self, _: glib::ParamSpec, _: adw::ComboRow) {
+        self.refresh_ics_sections();
+    }
+
+    #[template_callback]
+    fn ics_import_activated(&self, _: adw::ActionRow) {
+        let imp = self.imp();
+        let project_id = imp
+            .ics_projects
+            .borrow()
+            .get(imp.ics_import_project_row.selected() as usize)
+            .copied();
+        let section_id = imp
+            .ics_sections
+            .borrow()
+            .get(imp.ics_import_section_row.selected() as usize)
+            .copied();
+        let (project_id, section_id) =
+            if let (Some(project_id), Some(section_id)) = (project_id, section_id) {
+                (project_id, section_id)
+            } else {
+                imp.toast_overlay
+                    .add_toast(adw::Toast::new(&gettext("Select a project and a section")));
+                return;
+            };
+
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Import")));
+        dialog.open(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj => move |file| {
+                if let Ok(file) = file {
+                    let toast_overlay = obj.imp().toast_overlay.to_owned();
+                    let result = fs::read_to_string(file.path().unwrap())
+                        .map_err(|err| err.to_string())
+                        .and_then(|content| {
+                            import_ics(&content, project_id, section_id).map_err(|err| err.to_string())
+                        });
+                    match result {
+                        Ok(result) => {
+                            let application = obj.application().and_downcast::<IPlanApplication>().unwrap();
+                            for task in &result.tasks {
+                                application.refresh_task(task);
+                            }
+                            for reminder in result.reminders.iter() {
+                                application.send_reminder(reminder.clone());
+                            }
+                            obj.show_ics_import_summary(&result);
+                        }
+                        Err(err) => toast_overlay.add_toast(adw::Toast::new(&err)),
+                    }
+                }
+            }),
+        );
+    }
+
+    fn show_ics_import_summary(&self, result: &IcsImport) {
+        let mut body = gettext("{} tasks and {} reminders created")
+            .replacen("{}", &result.tasks.len().to_string(), 1)
+            .replacen("{}", &result.reminders.len().to_string(), 1);
+        if !result.skipped.is_empty() {
+            body.push_str("\n\n");
+            body.push_str(
+                &gettext("{} items skipped:").replace("{}", &result.skipped.len().to_string()),
+            );
+            for (summary, reason) in &result.skipped {
+                body.push_str(&format!("\n{summary} ({reason})"));
+            }
+        }
+        let dialog =
+            adw::MessageDialog::new(Some(self), Some(&gettext("Import Finished")), Some(&body));
+        dialog.add_response("close", &gettext("_Close"));
+        dialog.present();
+    }
+
+    #[template_callback]
+    fn ics_export_activated(&self, _: adw::ActionRow) {
+        let imp = self.imp();
+        // First item is all projects
+        let selected = imp.ics_project_row.selected() as usize;
+        let project_id = if selected == 0 {
+            None
+        } else {
+            imp.ics_projects.borrow().get(selected - 1).copied()
+        };
+
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Export")));
+        let now = glib::DateTime::now_local().unwrap();
+        dialog.set_initial_name(Some(&now.format("IPlan tasks %F %R.ics").unwrap()));
+        let toast_overlay = imp.toast_overlay.to_owned();
+        dialog.save(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak toast_overlay => move |file| {
+                if let Ok(file) = file {
+                    let export_path = file.path().unwrap();
+                    let result = export_ics(project_id)
+                        .map_err(|err| err.to_string())
+                        .and_then(|ics| fs::write(export_path, ics).map_err(|err| err.to_string()));
+                    if let Err(err) = result {
+                        let toast = adw::Toast::new(&err);
+                        toast_overlay.add_toast(toast);
+                    } else {
+                        toast_overlay.add_toast(adw::Toast::new(&gettext("Tasks exported")));
+                    }
+                }
+            }),
+        );
+    }
+
+    #[template_callback]
+    fn timesheet_export_activated(&self, _: adw::ActionRow) {
+        let imp = self.imp();
+        // First item is all projects
+        let selected = imp.timesheet_project_row.selected() as usize;
+        let project_id = if selected == 0 {
+            None
+        } else {
+            imp.ics_projects.borrow().get(selected - 1).copied()
+        };
+        // End date is included
+        let start = imp.timesheet_start_row.date().to_unix();
+        let end = imp.timesheet_end_row.date().add_days(1).unwrap().to_unix();
+        if start >= end {
+            imp.toast_overlay.add_toast(adw::Toast::new(&gettext(
+                "Start date should be before the end date",
+            )));
+            return;
+        }
+        let aggregate = imp.timesheet_aggregate_switch.is_active();
+
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Export")));
+        dialog.set_initial_name(Some(&format!(
+            "IPlan timesheet {} {}.csv",
+            imp.timesheet_start_row.date().format("%F").unwrap(),
+            imp.timesheet_end_row.date().format("%F").unwrap()
+        )));
+        let toast_overlay = imp.toast_overlay.to_owned();
+        dialog.save(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak toast_overlay => move |file| {
+                if let Ok(file) = file {
+                    let export_path = file.path().unwrap();
+                    let result = export_timesheet(start, end, project_id, aggregate)
+                        .map_err(|err| err.to_string())
+                        .and_then(|csv| fs::write(export_path, csv).map_err(|err| err.to_string()));
+                    if let Err(err) = result {
+                        toast_overlay.add_toast(adw::Toast::new(&err));
+                    } else {
+                        toast_overlay.add_toast(adw::Toast::new(&gettext("Timesheet exported")));
+                    }
+                }
+            }),
+        );
+    }
+
+    #[template_callback]
+    fn json_export_activated(&self, _: adw::ActionRow) {
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Export")));
+        let now = glib::DateTime::now_local().unwrap();
+        dialog.set_initial_name(Some(&now.format("IPlan data %F %R.json").unwrap()));
+        let toast_overlay = self.imp().toast_overlay.to_owned();
+        dialog.save(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak toast_overlay => move |file| {
+                if let Ok(file) = file {
+                    let export_path = file.path().unwrap();
+                    let result = export_json()
+                        .map_err(|err| err.to_string())
+                        .and_then(|json| fs::write(export_path, json).map_err(|err| err.to_string()));
+                    if let Err(err) = result {
+                        toast_overlay.add_toast(adw::Toast::new(&err));
+                    } else {
+                        toast_overlay.add_toast(adw::Toast::new(&gettext("Data exported")));
+                    }
+                }
+            }),
+        );
+    }
+
+    #[template_callback]
+    fn json_import_activated(&self, _: adw::ActionRow) {
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Import")));
+        dialog.open(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj => move |file| {
+                if let Ok(file) = file {
+                    match fs::read_to_string(file.path().unwrap()) {
+                        Ok(content) => obj.choose_json_import_mode(content),
+                        Err(err) => obj.imp().toast_overlay.add_toast(adw::Toast::new(&err.to_string())),
+                    }
+                }
+            }),
+        );
+    }
+
+    fn choose_json_import_mode(&self, content: String) {
+        let body = gettext(
+            "Replace removes the current data after caching it. Merge adds the backup next to the current data.",
+        );
+        let dialog =
+            adw::MessageDialog::new(Some(self), Some(&gettext("Import Mode")), Some(&body));
+        dialog.add_response("cancel", &gettext("_Cancel"));
+        dialog.add_response("merge", &gettext("_Merge"));
+        dialog.add_response("replace", &gettext("_Replace"));
+        dialog.set_response_appearance("replace", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("merge"));
+        dialog.set_close_response("cancel");
+        dialog.connect_response(
+            None,
+            glib::clone!(@weak self as obj => move |_, response| {
+                let mode = match response {
+                    "merge" => ImportMode::Merge,
+                    "replace" => ImportMode::Replace,
+                    _ => return,
+                };
+                obj.run_json_import(&content, mode);
+            }),
+        );
+        dialog.present();
+    }
+
+    fn run_json_import(&self, content: &str, mode: ImportMode) {
+        let toast_overlay = self.imp().toast_overlay.to_owned();
+        if mode == ImportMode::Replace {
+            if let Err(err) = cache_data() {
+                let toast = adw::Toast::new(&format!(
+                    "{}: {}",
+                    gettext("Error while caching previous data"),
+                    err
+                ));
+                toast_overlay.add_toast(toast);
+                return;
+            }
+        }
+
+        match import_json(content, mode) {
+            Ok(result) => {
+                let application = self
+                    .application()
+                    .and_downcast::<IPlanApplication>()
+                    .unwrap();
+                for reminder_id in result.reminders {
+                    if let Ok(reminder) = read_reminder(reminder_id) {
+                        application.send_reminder(reminder);
+                    }
+                }
+                // The imported data is the start of the history
+                clear_history().expect("Failed to clear history");
+                let iplan_window = self.transient_for().and_downcast::<IPlanWindow>().unwrap();
+                iplan_window.reset();
+                let message = gettext("{} projects and {} tasks imported")
+                    .replacen("{}", &result.projects.to_string(), 1)
+                    .replacen("{}", &result.tasks.to_string(), 1);
+                toast_overlay.add_toast(adw::Toast::new(&message));
+            }
+            Err(err) => toast_overlay.add_toast(adw::Toast::new(&err)),
+        }
+    }
+
     #[template_callback]
     fn export_activated(&self, _: adw::ActionRow) {
         let dialog = gtk::FileDialog::new();
@@ -101,13 +450,10 @@ impl BackupWindow {
             Some(&gio::Cancellable::new()),
             glib::clone!(@weak self as obj => move |file| {
                 if let Ok(file) = file {
-                    let now = glib::DateTime::now_local().unwrap();
-                    let cache_filename = now.format("IPlan data %F %R.db").unwrap();
-                    let cache_path = glib::user_cache_dir().join(cache_filename);
                     let data_path = glib::user_data_dir().join("data.db");
                     let import_path = file.path().unwrap();
                     let toast_overlay = obj.imp().toast_overlay.to_owned();
-                    if let Err(err) = fs::copy(data_path.to_str().unwrap(), cache_path) {
+                    if let Err(err) = cache_data() {
                         let toast = adw::Toast::new(&format!("{}: {}", gettext("Error while caching previous data"), err));
                         toast_overlay.add_toast(toast);
                     } else if let Err(err) = fs::copy(import_path, data_path) {
@@ -115,6 +461,7 @@ impl BackupWindow {
                         toast_overlay.add_toast(toast);
                     } else {
                         check_database().expect("Database check has failed(after importing data)");
+                        clear_history().expect("Failed to clear history");
                         let iplan_window = obj.transient_for().and_downcast::<IPlanWindow>().unwrap();
                         iplan_window.reset();
                     }
@@ -123,3 +470,12 @@ impl BackupWindow {
         );
     }
 }
+
+/// Copy the database to the cache directory before replacing it
+fn cache_data() -> std::io::Result<u64> {
+    let now = glib::DateTime::now_local().unwrap();
+    let cache_filename = now.format("IPlan data %F %R.db").unwrap();
+    let cache_path = glib::user_cache_dir().join(cache_filename);
+    let data_path = glib::user_data_dir().join("data.db");
+    fs::copy(data_path, cache_path)
+}
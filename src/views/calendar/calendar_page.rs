@@ -5,7 +5,7 @@ use std::thread;
 use std::time::Duration;
 
 use crate::db::models::Task;
-use crate::db::operations::{read_records, read_task, task_tree};
+use crate::db::operations::{read_records, read_repeating_tasks, read_task, task_tree};
 use crate::views::calendar::{DayIndicator, DayView};
 use crate::views::task::TaskRow;
 use crate::views::ActionScope;
@@ -21,6 +21,7 @@ mod imp {
         pub datetime: RefCell<glib::DateTime>,
         #[property(get, set)]
         pub scroll: Cell<i8>,
+        pub repeating_tasks: RefCell<Vec<Task>>,
         #[template_child]
         pub page_header: TemplateChild<adw::HeaderBar>,
         #[template_child]
@@ -89,6 +90,7 @@ mod imp {
             Self {
                 datetime: RefCell::new(glib::DateTime::now_local().unwrap()),
                 scroll: Cell::new(0),
+                repeating_tasks: RefCell::new(vec![]),
                 page_header: TemplateChild::default(),
                 toggle_sidebar_button: TemplateChild::default(),
                 navigation_bar: TemplateChild::default(),
@@ -153,8 +155,10 @@ impl CalendarPage {
             imp.days_box.remove(&imp.days_box.first_child().unwrap());
         }
 
+        imp.repeating_tasks.replace(read_repeating_tasks().unwrap());
         for i in -7..14 {
-            let day_view = DayView::new(datetime.add_days(i).unwrap());
+            let day_view =
+                DayView::new(datetime.add_days(i).unwrap(), &imp.repeating_tasks.borrow());
             imp.days_box.append(&day_view);
         }
 
@@ -205,6 +209,15 @@ impl CalendarPage {
         if task.suspended() {
             self.set_subtasks_suspended(task_id, true);
         }
+        self.refresh_days_views_estimate();
+
+        let old_task_repeat = self
+            .task_row(task_id)
+            .map(|(_, row)| row.task().repeat())
+            .unwrap_or_default();
+        if !task.repeat().is_empty() || !old_task_repeat.is_empty() {
+            self.refresh_occurrences();
+        }
 
         if let Some((day_view, row)) = self.task_row(task_id) {
             let old_task = row.task();
@@ -247,6 +260,17 @@ impl CalendarPage {
         }
     }
 
+    pub fn refresh_occurrences(&self) {
+        let imp = self.imp();
+        imp.repeating_tasks.replace(read_repeating_tasks().unwrap());
+        let repeating_tasks = imp.repeating_tasks.borrow();
+        let days_views = imp.days_box.observe_children();
+        for i in 0..days_views.n_items() {
+            let day_view = days_views.item(i).and_downcast::<DayView>().unwrap();
+            day_view.set_occurrences(&repeating_tasks);
+        }
+    }
+
     pub fn set_subtasks_suspended(&self, task_id: i64, suspended: bool) {
         let subtasks = task_tree(task_id, true).unwrap();
         for subtask in subtasks {
@@ -294,6 +318,14 @@ impl CalendarPage {
         }
     }
 
+    pub fn refresh_days_views_estimate(&self) {
+        let days_views = self.imp().days_box.observe_children();
+        for i in 0..days_views.n_items() {
+            let day_view = days_views.item(i).and_downcast::<DayView>().unwrap();
+            day_view.refresh_estimate();
+        }
+    }
+
     pub fn task_row(&self, task_id: i64) -> Option<(DayView, TaskRow)> {
         let imp = self.imp();
         let days_views = imp.days_box.observe_children();
@@ -408,7 +440,7 @@ impl CalendarPage {
                 let first_day_view_date = first_day_view.datetime();
                 if top_edge_day_view_date.difference(&first_day_view_date).as_days() < 7 {
                     let date = first_day_view_date.add_days(-1).unwrap();
-                    let day_view = DayView::new(date);
+                    let day_view = DayView::new(date, &imp.repeating_tasks.borrow());
                     imp.days_box.prepend(&day_view);
                     let (tx, rx) = glib::MainContext::channel(glib::Priority::DEFAULT);
                     glib::idle_add(move || {
@@ -435,7 +467,7 @@ impl CalendarPage {
                 let last_day_view_date = last_day_view.datetime();
                 if last_day_view_date.difference(&top_edge_day_view_date).as_days() < 14 {
                     let date = last_day_view_date.add_days(1).unwrap();
-                    let day_view = DayView::new(date);
+                    let day_view = DayView::new(date, &imp.repeating_tasks.borrow());
                     imp.days_box.append(&day_view);
                 }
             }
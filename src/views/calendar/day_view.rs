@@ -5,7 +5,7 @@ use gtk::{glib, subclass::prelude::*};
 use std::cell::{Cell, RefCell};
 
 use crate::db::models::{Record, Task};
-use crate::db::operations::{read_records, read_tasks};
+use crate::db::operations::{date_estimate, read_records, read_tasks};
 use crate::views::task::{TaskRow, TaskWindow, TasksBox, TasksBoxWrapper};
 
 mod imp {
@@ -19,12 +19,18 @@ mod imp {
         pub datetime: RefCell<glib::DateTime>,
         #[property(get, set)]
         pub duration: Cell<i64>,
+        #[property(get, set)]
+        pub estimate: Cell<i64>,
         #[template_child]
         pub name: TemplateChild<gtk::Label>,
         #[template_child]
         pub duration_label: TemplateChild<gtk::Label>,
         #[template_child]
+        pub estimate_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub tasks_box: TemplateChild<TasksBox>,
+        #[template_child]
+        pub occurrences_box: TemplateChild<gtk::Box>,
     }
 
     #[glib::object_subclass]
@@ -46,9 +52,12 @@ mod imp {
             Self {
                 datetime: RefCell::new(glib::DateTime::now_local().unwrap()),
                 duration: Cell::new(0),
+                estimate: Cell::new(0),
                 name: TemplateChild::default(),
                 duration_label: TemplateChild::default(),
+                estimate_label: TemplateChild::default(),
                 tasks_box: TemplateChild::default(),
+                occurrences_box: TemplateChild::default(),
             }
         }
     }
@@ -84,7 +93,7 @@ glib::wrapper! {
 
 #[gtk::template_callbacks]
 impl DayView {
-    pub fn new(datetime: glib::DateTime) -> Self {
+    pub fn new(datetime: glib::DateTime, repeating_tasks: &[Task]) -> Self {
         let obj: DayView = glib::Object::new::<Self>();
         let imp = obj.imp();
         let end = datetime.add_days(1).unwrap().to_unix();
@@ -121,6 +130,8 @@ impl DayView {
 
         obj.set_datetime(datetime);
         obj.refresh_duration();
+        obj.refresh_estimate();
+        obj.set_occurrences(repeating_tasks);
         obj
     }
 
@@ -143,7 +154,48 @@ impl DayView {
     pub fn remove_row(&self, row: &TaskRow) {
         let imp = self.imp();
         imp.tasks_box.remove_item(row);
-        if imp.tasks_box.item_by_index(0).is_none() {
+        if imp.tasks_box.item_by_index(0).is_none() && imp.occurrences_box.first_child().is_none() {
+            imp.name.add_css_class("dim-label");
+        }
+    }
+
+    pub fn set_occurrences(&self, repeating_tasks: &[Task]) {
+        // Future occurrences of recurring tasks, they are not stored until the task is done
+        let imp = self.imp();
+        let datetime = self.datetime();
+
+        while let Some(child) = imp.occurrences_box.first_child() {
+            imp.occurrences_box.remove(&child);
+        }
+
+        for task in repeating_tasks {
+            let Some(repeat) = task.repeat_rule() else {
+                continue;
+            };
+            if !repeat.occurs_on(&task.date_datetime().unwrap(), &datetime) {
+                continue;
+            }
+
+            let occurrence = gtk::Box::builder().spacing(6).build();
+            occurrence.set_tooltip_text(Some(&repeat.display()));
+            occurrence.add_css_class("dim-label");
+            occurrence.append(&gtk::Image::from_icon_name(
+                "media-playlist-repeat-symbolic",
+            ));
+            occurrence.append(
+                &gtk::Label::builder()
+                    .label(task.name())
+                    .ellipsize(gtk::pango::EllipsizeMode::End)
+                    .build(),
+            );
+            imp.occurrences_box.append(&occurrence);
+        }
+
+        let has_occurrences = imp.occurrences_box.first_child().is_some();
+        imp.occurrences_box.set_visible(has_occurrences);
+        if has_occurrences || imp.tasks_box.item_by_index(0).is_some() {
+            imp.name.remove_css_class("dim-label");
+        } else {
             imp.name.add_css_class("dim-label");
         }
     }
@@ -159,6 +211,12 @@ impl DayView {
         self.set_duration(duration);
     }
 
+    pub fn refresh_estimate(&self) {
+        let start: glib::DateTime = self.datetime();
+        let end = start.add_days(1).unwrap().to_unix();
+        self.set_estimate(date_estimate(start.to_unix(), end).unwrap());
+    }
+
     fn add_bindings(&self) {
         self.bind_property::<gtk::Label>("duration", &self.imp().duration_label.get(), "label")
             .transform_to(|binding, duration: i64| {
@@ -172,6 +230,18 @@ impl DayView {
                 }
             })
             .build();
+        self.bind_property::<gtk::Label>("estimate", &self.imp().estimate_label.get(), "label")
+            .transform_to(|binding, estimate: i64| {
+                let estimate_label = binding.target().unwrap();
+                if estimate == 0 {
+                    estimate_label.set_property("visible", false);
+                    Some(String::new())
+                } else {
+                    estimate_label.set_property("visible", true);
+                    Some(gettext("{} estimated").replace("{}", &Record::duration_display(estimate)))
+                }
+            })
+            .build();
     }
 
     #[template_callback]
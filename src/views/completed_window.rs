@@ -0,0 +1,140 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::db::operations::{read_completed_tasks, read_project};
+use crate::views::task::CompletionPeriod;
+use crate::IPlanApplication;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/completed_window.ui")]
+    pub struct CompletedWindow {
+        #[template_child]
+        pub period_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub tasks_box: TemplateChild<gtk::ListBox>,
+        /// Completion time of the listed tasks, in the order of the rows
+        pub completed: RefCell<Vec<glib::DateTime>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CompletedWindow {
+        const NAME: &'static str = "CompletedWindow";
+        type Type = super::CompletedWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for CompletedWindow {}
+    impl WidgetImpl for CompletedWindow {}
+    impl WindowImpl for CompletedWindow {}
+    impl AdwWindowImpl for CompletedWindow {}
+}
+
+glib::wrapper! {
+    pub struct CompletedWindow(ObjectSubclass<imp::CompletedWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+#[gtk::template_callbacks]
+impl CompletedWindow {
+    pub fn new(application: &IPlanApplication, app_window: &gtk::Window) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        let imp = win.imp();
+        // Day title above the first task of each day
+        imp.tasks_box.set_header_func(glib::clone!(
+        @weak imp => move |row, before| {
+            let completed = imp.completed.borrow();
+            let Some(day) = completed.get(row.index() as usize) else {
+                return;
+            };
+            let new_day = match before.and_then(|before| completed.get(before.index() as usize)) {
+                Some(before_day) => before_day.ymd() != day.ymd(),
+                None => true,
+            };
+            if new_day {
+                let label = gtk::Label::builder()
+                    .label(day_title(day))
+                    .halign(gtk::Align::Start)
+                    .margin_top(12)
+                    .margin_bottom(6)
+                    .margin_start(6)
+                    .build();
+                label.add_css_class("heading");
+                row.set_header(Some(&label));
+            } else {
+                row.set_header(None::<&gtk::Widget>);
+            }
+        }));
+        win.refresh();
+        win
+    }
+
+    fn refresh(&self) {
+        let imp = self.imp();
+        while let Some(row) = imp.tasks_box.first_child() {
+            imp.tasks_box.remove(&row);
+        }
+        let start = CompletionPeriod::from_index(imp.period_dropdown.selected()).start();
+        let tasks = read_completed_tasks(start, i64::MAX).expect("Failed to read completed tasks");
+        let mut projects = HashMap::new();
+        let mut completed = Vec::new();
+        for (task, completed_at) in tasks {
+            let completed_at = glib::DateTime::from_unix_local(completed_at).unwrap();
+            let project_name = projects
+                .entry(task.project())
+                .or_insert_with(|| {
+                    read_project(task.project())
+                        .map(|project| project.name())
+                        .unwrap_or_default()
+                })
+                .clone();
+            let row = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(&task.name()))
+                .subtitle(format!(
+                    "{} · {}",
+                    glib::markup_escape_text(&project_name),
+                    completed_at.format("%R").unwrap()
+                ))
+                .build();
+            imp.tasks_box.append(&row);
+            completed.push(completed_at);
+        }
+        imp.completed.replace(completed);
+        imp.tasks_box.invalidate_headers();
+    }
+
+    #[template_callback]
+    fn handle_period_selected(&self, _: glib::ParamSpec, _: gtk::DropDown) {
+        self.refresh();
+    }
+}
+
+fn day_title(day: &glib::DateTime) -> String {
+    let today = glib::DateTime::now_local().unwrap();
+    let yesterday = today.add_days(-1).unwrap();
+    if day.ymd() == today.ymd() {
+        gettext("Today")
+    } else if day.ymd() == yesterday.ymd() {
+        gettext("Yesterday")
+    } else {
+        day.format("%A, %B %e").unwrap().to_string()
+    }
+}
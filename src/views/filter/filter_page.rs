@@ -0,0 +1,196 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+use crate::db::models::{Filter, Task};
+use crate::db::operations::{update_filter, SmartView, TaskQuery};
+use crate::views::snippets::MenuItem;
+use crate::views::task::{QueryPage, TaskRow, TasksBoxWrapper};
+
+#[derive(Clone)]
+pub enum TaskView {
+    Smart(SmartView),
+    Saved(Filter),
+}
+
+impl TaskView {
+    pub fn query(&self) -> TaskQuery {
+        match self {
+            Self::Smart(view) => view.query(),
+            // Saved queries are checked before saving
+            Self::Saved(filter) => TaskQuery::parse(&filter.query()).unwrap_or_default(),
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/filter/filter_page.ui")]
+    pub struct FilterPage {
+        pub view: RefCell<Option<TaskView>>,
+        pub day_timeout: RefCell<Option<glib::SourceId>>,
+        #[template_child]
+        pub page: TemplateChild<QueryPage>,
+        #[template_child]
+        pub options_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub name_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub query_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub query_error_label: TemplateChild<gtk::Label>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for FilterPage {
+        const NAME: &'static str = "FilterPage";
+        type Type = super::FilterPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            MenuItem::ensure_type();
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for FilterPage {}
+    impl WidgetImpl for FilterPage {}
+    impl BoxImpl for FilterPage {}
+}
+
+glib::wrapper! {
+    pub struct FilterPage(ObjectSubclass<imp::FilterPage>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Buildable;
+}
+
+impl Default for FilterPage {
+    fn default() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl FilterPage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn view(&self) -> Option<TaskView> {
+        self.imp().view.borrow().clone()
+    }
+
+    pub fn open_view(&self, view: TaskView) {
+        let imp = self.imp();
+        let (name, icon_name, date) = match &view {
+            TaskView::Smart(smart_view) => (
+                smart_view.display(),
+                smart_view.icon_name(),
+                smart_view.new_task_date(),
+            ),
+            TaskView::Saved(filter) => (filter.name(), "system-search-symbolic", 0),
+        };
+        imp.page
+            .imp()
+            .menu_button
+            .set_visible(matches!(view, TaskView::Saved(_)));
+        imp.page.open_query(
+            &name,
+            icon_name,
+            view.query(),
+            TasksBoxWrapper::Filter(date),
+        );
+        imp.view.replace(Some(view));
+        self.reopen_tomorrow();
+    }
+
+    /// Views take their dates from today, they open again at the start of the next day
+    fn reopen_tomorrow(&self) {
+        let imp = self.imp();
+        if let Some(source) = imp.day_timeout.take() {
+            source.remove();
+        }
+        let now = glib::DateTime::now_local().unwrap();
+        let tomorrow =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap()
+                .add_days(1)
+                .unwrap();
+        let source = glib::timeout_add_seconds_local_once(
+            (tomorrow.to_unix() - now.to_unix()).max(1) as u32,
+            glib::clone!(@weak self as obj => move || {
+                // The source is done
+                obj.imp().day_timeout.take();
+                if let Some(view) = obj.view() {
+                    obj.open_view(view);
+                }
+            }),
+        );
+        imp.day_timeout.replace(Some(source));
+    }
+
+    /// Add, update or remove the task row by the query
+    pub fn reset_task(&self, task: &Task) {
+        self.imp().page.reset_task(task);
+    }
+
+    pub fn task_row(&self, task_id: i64) -> Option<TaskRow> {
+        self.imp().page.task_row(task_id)
+    }
+
+    #[template_callback]
+    fn handle_options_popover_show(&self, _: gtk::Popover) {
+        let imp = self.imp();
+        imp.name_entry.remove_css_class("error");
+        imp.query_entry.remove_css_class("error");
+        imp.query_error_label.set_visible(false);
+        if let Some(TaskView::Saved(filter)) = self.view() {
+            imp.name_entry.set_text(&filter.name());
+            imp.query_entry.set_text(&filter.query());
+        }
+    }
+
+    #[template_callback]
+    fn handle_entry_activate(&self, _: gtk::Entry) {
+        let imp = self.imp();
+        let Some(TaskView::Saved(filter)) = self.view() else {
+            return;
+        };
+        let name = imp.name_entry.text().trim().to_string();
+        let query_text = imp.query_entry.text().trim().to_string();
+        imp.name_entry.remove_css_class("error");
+        imp.query_entry.remove_css_class("error");
+        imp.query_error_label.set_visible(false);
+        if name.is_empty() {
+            imp.name_entry.add_css_class("error");
+            return;
+        }
+        match TaskQuery::parse(&query_text) {
+            Ok(query) if query != TaskQuery::default() => (),
+            Ok(_) => {
+                imp.query_entry.add_css_class("error");
+                return;
+            }
+            Err(error) => {
+                imp.query_entry.add_css_class("error");
+                imp.query_error_label.set_label(&error.to_string());
+                imp.query_error_label.set_visible(true);
+                return;
+            }
+        }
+
+        filter.set_name(name);
+        filter.set_query(query_text);
+        update_filter(&filter).expect("Failed to update filter");
+        imp.options_popover.popdown();
+        self.open_view(TaskView::Saved(filter.clone()));
+        self.activate_action("filter.changed", Some(&filter.id().to_variant()))
+            .unwrap();
+    }
+}
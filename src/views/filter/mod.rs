@@ -0,0 +1,2 @@
+mod filter_page;
+pub use filter_page::{FilterPage, TaskView};
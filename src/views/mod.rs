@@ -7,11 +7,22 @@ pub use preferences_window::PreferencesWindow;
 mod backup_window;
 pub use backup_window::BackupWindow;
 
+mod reports_window;
+pub use reports_window::ReportsWindow;
+
+mod trash_window;
+pub use trash_window::TrashWindow;
+
+mod completed_window;
+pub use completed_window::CompletedWindow;
+
 pub mod calendar;
+pub mod filter;
 pub mod project;
 pub mod record;
 pub mod reminder;
 pub mod search;
 pub mod sidebar;
 pub mod snippets;
+pub mod tag;
 pub mod task;
@@ -18,10 +18,14 @@
  * SPDX-License-Identifier: GPL-3.0-or-later
  */
 
+use adw::prelude::*;
 use adw::subclass::prelude::*;
-use gtk::{gio, glib, glib::Properties, prelude::*};
+use gettextrs::gettext;
+use gtk::{gio, glib, glib::Properties};
 use std::cell::RefCell;
 
+use crate::caldav;
+
 mod imp {
     use super::*;
 
@@ -33,6 +37,36 @@ mod imp {
         pub settings: RefCell<Option<gio::Settings>>,
         #[template_child]
         pub background_run: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub timer_policy_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub idle_detection: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub idle_threshold: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pomodoro_enabled: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub pomodoro_work: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pomodoro_short_break: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pomodoro_long_break: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pomodoro_cycles: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub trash_retention: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub caldav_enabled: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub caldav_url_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub caldav_username_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub caldav_password_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub caldav_conflict_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub caldav_sync_row: TemplateChild<adw::ActionRow>,
     }
 
     #[glib::object_subclass]
@@ -84,9 +118,14 @@ glib::wrapper! {
 #[gtk::template_callbacks]
 impl PreferencesWindow {
     pub fn new<P: glib::IsA<gtk::Application>>(application: &P) -> Self {
-        glib::Object::builder::<PreferencesWindow>()
+        let obj = glib::Object::builder::<PreferencesWindow>()
             .property("application", application)
-            .build()
+            .build();
+        application
+            .bind_property("sync-status", &obj.imp().caldav_sync_row.get(), "subtitle")
+            .sync_create()
+            .build();
+        obj
     }
 
     fn add_bindings(&self) {
@@ -96,5 +135,99 @@ impl PreferencesWindow {
         settings
             .bind("background-run", &imp.background_run.get(), "active")
             .build();
+        let timer_policy = if settings.string("timer-policy") == "parallel" {
+            1
+        } else {
+            0
+        };
+        imp.timer_policy_row.set_selected(timer_policy);
+        settings
+            .bind("idle-detection", &imp.idle_detection.get(), "active")
+            .build();
+        settings
+            .bind("idle-threshold", &imp.idle_threshold.get(), "value")
+            .build();
+        settings
+            .bind("pomodoro-enabled", &imp.pomodoro_enabled.get(), "active")
+            .build();
+        settings
+            .bind("pomodoro-work", &imp.pomodoro_work.get(), "value")
+            .build();
+        settings
+            .bind(
+                "pomodoro-short-break",
+                &imp.pomodoro_short_break.get(),
+                "value",
+            )
+            .build();
+        settings
+            .bind(
+                "pomodoro-long-break",
+                &imp.pomodoro_long_break.get(),
+                "value",
+            )
+            .build();
+        settings
+            .bind("pomodoro-cycles", &imp.pomodoro_cycles.get(), "value")
+            .build();
+        settings
+            .bind("trash-retention", &imp.trash_retention.get(), "value")
+            .build();
+        settings
+            .bind("caldav-enabled", &imp.caldav_enabled.get(), "active")
+            .build();
+        settings
+            .bind("caldav-url", &imp.caldav_url_row.get(), "text")
+            .build();
+        settings
+            .bind("caldav-username", &imp.caldav_username_row.get(), "text")
+            .build();
+        let conflict_policy = if settings.string("caldav-conflict-policy") == "local" {
+            1
+        } else {
+            0
+        };
+        imp.caldav_conflict_row.set_selected(conflict_policy);
+    }
+
+    #[template_callback]
+    fn handle_timer_policy_selected(&self, _: glib::ParamSpec, row: adw::ComboRow) {
+        let timer_policy = if row.selected() == 1 {
+            "parallel"
+        } else {
+            "single"
+        };
+        self.settings()
+            .unwrap()
+            .set_string("timer-policy", timer_policy)
+            .unwrap();
+    }
+
+    #[template_callback]
+    fn handle_caldav_conflict_selected(&self, _: glib::ParamSpec, row: adw::ComboRow) {
+        let conflict_policy = if row.selected() == 1 {
+            "local"
+        } else {
+            "server"
+        };
+        self.settings()
+            .unwrap()
+            .set_string("caldav-conflict-policy", conflict_policy)
+            .unwrap();
+    }
+
+    #[template_callback]
+    fn handle_caldav_password_apply(&self, row: adw::PasswordEntryRow) {
+        let password = row.text();
+        glib::MainContext::default().spawn_local(glib::clone!(@weak self as obj => async move {
+            let message = match caldav::store_password(&password).await {
+                Ok(_) => {
+                    obj.imp().caldav_password_row.set_text("");
+                    gettext("Password saved")
+                }
+                Err(err) => err,
+            };
+            obj.add_toast(adw::Toast::new(&message));
+        }));
     }
 }
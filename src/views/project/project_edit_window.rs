@@ -5,8 +5,9 @@ use gtk::glib;
 use gtk::glib::Properties;
 use std::cell::RefCell;
 
+use crate::application::IPlanApplication;
 use crate::db::models::Project;
-use crate::db::operations::{delete_project, update_project};
+use crate::db::operations::{trash_project, update_project};
 use crate::views::IPlanWindow;
 
 mod imp {
@@ -27,6 +28,10 @@ mod imp {
         #[template_child]
         pub description_buffer: TemplateChild<gtk::TextBuffer>,
         #[template_child]
+        pub goal_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub goal_period_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
         pub archive_switch: TemplateChild<gtk::Switch>,
     }
 
@@ -112,6 +117,22 @@ impl ProjectEditWindow {
             .bidirectional()
             .build();
 
+        project
+            .bind_property("goal", &imp.goal_spin_button.get(), "value")
+            .transform_to(|_, goal: i64| Some(goal as f64 / 3600.0))
+            .transform_from(|_, hours: f64| Some((hours * 3600.0) as i64))
+            .sync_create()
+            .bidirectional()
+            .build();
+
+        project
+            .bind_property("goal_period", &imp.goal_period_row.get(), "selected")
+            .transform_to(|_, period: u8| Some(period as u32))
+            .transform_from(|_, selected: u32| Some(selected.min(2) as u8))
+            .sync_create()
+            .bidirectional()
+            .build();
+
         project.connect_notify_local(
             None,
             glib::clone!(@weak self as obj => move|project, _| {
@@ -151,11 +172,15 @@ impl ProjectEditWindow {
         let dialog_heading = gettext("Delete “{}” project?");
         dialog.set_heading(Some(&dialog_heading.replace("{}", &project.name())));
         dialog.set_body(&gettext(
-            "The project and its tasks will be permanently lost.",
+            "The project and its tasks will be moved to the trash.",
         ));
         dialog.connect_response(Some("delete"), move |dialog, response| {
             if response == "delete" {
-                delete_project(project.id(), project.index()).unwrap();
+                trash_project(project.id(), project.index()).unwrap();
+                IPlanApplication::default()
+                    .timer()
+                    .remove_trashed_records()
+                    .expect("Failed to stop timer");
                 dialog
                     .transient_for()
                     .unwrap()
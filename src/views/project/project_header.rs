@@ -1,9 +1,12 @@
+use gettextrs::gettext;
 use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
 use std::cell::Cell;
 use std::thread;
 
 use crate::db::models::{Project, Record};
-use crate::db::operations::{project_duration, read_records, read_tasks};
+use crate::db::operations::{
+    project_duration, project_duration_since, project_estimate, read_daily_durations, read_project,
+};
 use crate::views::snippets::Chart;
 
 mod imp {
@@ -30,6 +33,16 @@ mod imp {
         #[template_child]
         pub total_time: TemplateChild<gtk::Label>,
         #[template_child]
+        pub total_estimate: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub goal_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub goal_title: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub goal_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub goal_level: TemplateChild<gtk::LevelBar>,
+        #[template_child]
         pub chart_header: TemplateChild<gtk::Box>,
         #[template_child]
         pub chart_title: TemplateChild<gtk::Label>,
@@ -61,6 +74,9 @@ mod imp {
         fn constructed(&self) {
             self.parent_constructed();
             self.project_popover.set_offset(0, 3);
+            for offset in ["low", "high", "full"] {
+                self.goal_level.remove_offset_value(Some(offset));
+            }
         }
         fn properties() -> &'static [glib::ParamSpec] {
             Self::derived_properties()
@@ -126,59 +142,68 @@ impl ProjectHeader {
         imp.chart.clear();
         imp.chart_header.set_visible(false);
 
+        let project = read_project(project_id).expect("Failed to read project");
+        let goal = project.goal();
+        let goal_start = project.goal_period_start().to_unix();
+        imp.goal_title.set_label(&project.goal_period_display());
+        imp.goal_box.set_visible(goal != 0);
+
         thread::spawn(move || {
             let now = glib::DateTime::now_local().unwrap();
-            let dates = &mut vec![];
-            dates.push(now.to_unix());
-            let tasks: Vec<crate::db::models::Task> =
-                read_tasks(Some(project_id), None, None, None, None, false).unwrap();
+            let today =
+                glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                    .unwrap();
+            let start = today.add_days(-6).unwrap();
+            let durations = read_daily_durations(
+                start.to_unix(),
+                today.add_days(1).unwrap().to_unix(),
+                Some(project_id),
+            )
+            .expect("Failed to read records");
 
-            let mut last_7_days = 0;
             let mut labels = vec![];
             let mut values = vec![];
-            let mut tooltips = vec![];
             for i in 0..7 {
-                let date = glib::DateTime::from_local(
-                    now.year(),
-                    now.month(),
-                    now.day_of_month(),
-                    0,
-                    0,
-                    0.0,
-                )
-                .unwrap();
-                let date = date.add_days(-i).unwrap();
-                let date_unix = date.to_unix();
-                let mut duration = 0;
-                for task in &tasks {
-                    let records = read_records(
-                        Some(task.id()),
-                        false,
-                        Some(date_unix),
-                        Some(dates[(i) as usize]),
-                    )
-                    .expect("Failed to read records");
-                    for record in records {
-                        duration += record.duration();
-                    }
-                }
-                last_7_days += duration;
-                dates.push(date_unix);
+                let date = today.add_days(-i).unwrap();
+                let date_key = date.format("%F").unwrap().to_string();
                 labels.push(date.format("%e ").unwrap().to_string());
-                values.push(duration);
-                tooltips.push(Record::duration_display(duration));
+                values.push(durations.get(&date_key).copied().unwrap_or(0));
             }
+            let last_7_days = values.iter().sum::<i64>();
 
             let total_time = project_duration(project_id).unwrap();
-            tx.send((total_time, last_7_days, labels, values, tooltips))
-                .unwrap();
+            let total_estimate = project_estimate(project_id).unwrap();
+            let goal_progress = project_duration_since(project_id, goal_start).unwrap();
+            tx.send((
+                total_time,
+                total_estimate,
+                goal_progress,
+                last_7_days,
+                labels,
+                values,
+            ))
+            .unwrap();
         });
         rx.attach(
             None,
             glib::clone!(
             @weak imp => @default-return glib::ControlFlow::Break,
             move |data| {
-                let (total_time, last_7_days, labels, values, tooltips) = data;
+                let (total_time, total_estimate, goal_progress, last_7_days, labels, values) = data;
+
+                if goal != 0 {
+                    imp.goal_label.set_label(&format!(
+                        "{} / {}",
+                        Record::duration_display(goal_progress),
+                        Record::duration_display(goal)
+                    ));
+                    imp.goal_level.set_value((goal_progress as f64 / goal as f64).min(1.0));
+                    if goal_progress >= goal {
+                        imp.goal_level.add_css_class("reached");
+                    } else {
+                        imp.goal_level.remove_css_class("reached");
+                    }
+                }
 
                 if total_time == 0 {
                     imp.placeholder.set_visible(true);
@@ -189,24 +214,21 @@ impl ProjectHeader {
                 }
 
                 imp.total_time.set_label(&Record::duration_display(total_time));
+                if total_estimate == 0 {
+                    imp.total_estimate.set_visible(false);
+                } else {
+                    imp.total_estimate.set_label(
+                        &gettext("{} estimated").replace("{}", &Record::duration_display(total_estimate)),
+                    );
+                    imp.total_estimate.set_visible(true);
+                }
                 imp.chart_title.set_label(&Record::duration_display(last_7_days));
 
                 let now = glib::DateTime::now_local().unwrap();
                 let start = now.add_days(-6).unwrap();
                 imp.chart_subtitle.set_label(&format!("{} - {}", start.format("%d").unwrap(), now.format("%d %b %Y").unwrap()));    // FIXME: add start month name if month changed or even year!
 
-                let bigger_duration = values.iter().max().unwrap().to_owned(); // FIXME: Check why returns option
-                let max_hour = (bigger_duration / 3600) + 2;
-                let max = max_hour * 3600;
-                let mut y_labels = vec![];
-                for i in 0..max_hour {
-                    y_labels.push(i.to_string());
-                }
-                let percentages = values.iter().map(|value| {
-                    let value = value.to_owned() as f64;
-                    value / max as f64
-                }).collect();
-                imp.chart.set_data(labels, percentages, y_labels, tooltips);
+                imp.chart.set_durations(labels, &values);
                 glib::ControlFlow::Break
             }),
         );
@@ -6,7 +6,7 @@ use std::thread;
 use std::time::Duration;
 
 use crate::db::models::{Project, Task};
-use crate::db::operations::{create_section, read_section, read_sections, read_task};
+use crate::db::operations::{create_section, read_section, read_sections, read_task, seal_history};
 use crate::views::project::{ProjectHeader, SectionBox};
 use crate::views::{task::TaskRow, ActionScope, IPlanWindow};
 
@@ -252,6 +252,7 @@ impl ProjectPage {
     pub fn new_section(&self, project_id: i64) {
         let imp = self.imp();
         let section = create_section(&gettext("New Section"), project_id).unwrap();
+        seal_history().expect("Failed to update history");
         let section_box = SectionBox::new(section, imp.layout.get(), 18);
         if imp.placeholder.parent().is_some() {
             imp.sections_box.remove(&imp.placeholder.get());
@@ -3,9 +3,11 @@ use gettextrs::gettext;
 use gtk::{gdk, glib, glib::Properties, subclass::prelude::*};
 use std::cell::RefCell;
 
+use crate::application::IPlanApplication;
 use crate::db::models::{Section, Task};
 use crate::db::operations::{
-    create_task, delete_section, new_task_position, read_section, read_tasks, update_section,
+    create_task, new_task_position, read_section, read_tasks, seal_history, trash_section,
+    update_section,
 };
 use crate::views::project::ProjectLayout;
 use crate::views::snippets::MenuItem;
@@ -35,6 +37,8 @@ mod imp {
         pub tasks_box: TemplateChild<TasksBox>,
         #[template_child]
         pub options_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub sort_button: TemplateChild<MenuItem>,
     }
 
     #[glib::object_subclass]
@@ -57,6 +61,7 @@ mod imp {
                     obj.activate_task_action("task.changed", &task);
 
                     if !task.done() {
+                        obj.add_new_task(task);
                         return;
                     }
 
@@ -136,7 +141,7 @@ impl SectionBox {
 
         imp.name_entry.buffer().set_text(section.name());
 
-        let tasks = read_tasks(
+        let mut tasks = read_tasks(
             Some(section.project()),
             Some(section.id()),
             Some(false),
@@ -145,8 +150,14 @@ impl SectionBox {
             false,
         )
         .unwrap();
+        if section.priority_sort() {
+            // Lazy rows are taken from the end
+            tasks.sort_by_key(|task| task.priority());
+        }
 
         obj.set_layout(&layout);
+        obj.set_sort_button_label();
+        imp.tasks_box.set_priority_sort(section.priority_sort());
         imp.tasks_box
             .set_items_wrapper(TasksBoxWrapper::Section(section.id(), section.project()));
         imp.tasks_box.add_tasks_lazy(tasks, max_height);
@@ -181,6 +192,17 @@ impl SectionBox {
         }
     }
 
+    pub fn add_new_task(&self, task: Task) {
+        // Tasks created outside of the section like the next occurrence of a recurring task
+        let imp = self.imp();
+        if task.parent() == 0
+            && task.section() == self.section().id()
+            && imp.tasks_box.item_by_id(task.id()).is_none()
+        {
+            imp.tasks_box.add_task(task);
+        }
+    }
+
     fn add_drag_drop_controllers(&self) {
         let imp = self.imp();
         let section_drag_source = gtk::DragSource::builder()
@@ -258,6 +280,8 @@ impl SectionBox {
                     row.reset(changed_task);
                 } else if task_id == changed_task.parent() {
                     row.reset_subtasks();
+                } else if !changed_task.done() {
+                    obj.add_new_task(changed_task);
                 }
             }),
         );
@@ -285,6 +309,7 @@ impl SectionBox {
         self.imp().name_button.set_visible(true);
         section.set_name(name);
         update_section(&section).expect("Failed to update section");
+        seal_history().expect("Failed to update history");
     }
 
     #[template_callback]
@@ -297,9 +322,31 @@ impl SectionBox {
             ("position", &new_task_position(section_id)),
         ]))
         .unwrap();
+        seal_history().expect("Failed to update history");
         self.imp().tasks_box.add_fresh_task(task);
     }
 
+    fn set_sort_button_label(&self) {
+        let label = if self.section().priority_sort() {
+            gettext("Sort Manually")
+        } else {
+            gettext("Sort by Priority")
+        };
+        self.imp().sort_button.set_label(label);
+    }
+
+    #[template_callback]
+    fn handle_sort_button_clicked(&self, _button: MenuItem) {
+        let imp = self.imp();
+        imp.options_button.popdown();
+        let section = self.section();
+        section.set_priority_sort(!section.priority_sort());
+        update_section(&section).expect("Failed to update section");
+        seal_history().expect("Failed to update history");
+        imp.tasks_box.set_priority_sort(section.priority_sort());
+        self.set_sort_button_label();
+    }
+
     #[template_callback]
     fn handle_delete_button_clicked(&self, _button: gtk::Button) {
         let imp = self.imp();
@@ -312,7 +359,7 @@ impl SectionBox {
         let dialog_heading = gettext("Delete “{}” section?");
         dialog.set_heading(Some(&dialog_heading.replace("{}", &self.section().name())));
         dialog.set_body(&gettext(
-            "The section and its tasks will be permanently lost.",
+            "The section and its tasks will be moved to the trash.",
         ));
 
         dialog.connect_response(
@@ -320,7 +367,11 @@ impl SectionBox {
             glib::clone!(
             @weak self as obj => move |_dialog, response| {
                 if response == "delete" {
-                    delete_section(obj.section().id()).expect("Failed to delete section");
+                    trash_section(obj.section().id()).expect("Failed to delete section");
+                    IPlanApplication::default()
+                        .timer()
+                        .remove_trashed_records()
+                        .expect("Failed to stop timer");
                     let sections_box = obj.parent().and_downcast::<gtk::Box>().unwrap();
                     let placeholder = obj.root()
                         .and_downcast::<IPlanWindow>()
@@ -368,6 +419,7 @@ impl SectionBox {
         if section.index() != section_db.index() {
             // TODO: add project condition
             update_section(&section).expect("Failed to update section");
+            seal_history().expect("Failed to update history");
         }
         true
     }
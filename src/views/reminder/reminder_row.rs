@@ -1,6 +1,6 @@
 use adw;
 use adw::subclass::prelude::*;
-use adw::traits::PreferencesRowExt;
+use adw::traits::{ActionRowExt, PreferencesRowExt};
 use glib::{once_cell::sync::Lazy, subclass::Signal};
 use gtk::{glib, glib::Properties, prelude::*};
 use rusqlite;
@@ -89,11 +89,22 @@ impl ReminderRow {
     fn set_labels(&self) {
         let reminder = self.reminder();
         let datetime = reminder.datetime_datetime();
-        self.set_title(&format!(
+        let datetime_text = format!(
             "{} {}",
             Task::date_display(&datetime),
             datetime.format("%H:%M").unwrap()
-        ));
+        );
+        if reminder.relative() {
+            self.set_title(&reminder.date_offset_display());
+            self.set_subtitle(&datetime_text);
+        } else {
+            self.set_title(&datetime_text);
+            let repeat_text = reminder
+                .repeat_rule()
+                .map(|repeat| repeat.display())
+                .unwrap_or_default();
+            self.set_subtitle(&repeat_text);
+        }
     }
 
     #[template_callback]
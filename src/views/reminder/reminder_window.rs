@@ -1,13 +1,14 @@
+use adw::prelude::*;
 use gettextrs::gettext;
 use glib::Properties;
-use gtk::{glib, prelude::*, subclass::prelude::*};
+use gtk::{glib, subclass::prelude::*};
 use std::cell::{Cell, RefCell};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::application::IPlanApplication;
-use crate::db::models::Reminder;
-use crate::db::operations::{create_reminder, delete_reminder, update_reminder};
-use crate::views::snippets::{DateRow, TimeRow};
+use crate::db::models::{Reminder, OFFSET_UNITS};
+use crate::db::operations::{create_reminder, delete_reminder, read_task, update_reminder};
+use crate::views::snippets::{DateRow, RepeatRow, TimeRow};
 
 mod imp {
     use super::*;
@@ -20,13 +21,27 @@ mod imp {
         pub reminder: RefCell<Reminder>,
         #[property(get, set)]
         pub state: Cell<bool>,
+        #[property(get, set)]
+        pub task_date: Cell<i64>,
         #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
         #[template_child]
+        pub mode_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub datetime_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
         pub date_row: TemplateChild<DateRow>,
         #[template_child]
         pub time_row: TemplateChild<TimeRow>,
         #[template_child]
+        pub repeat_row: TemplateChild<RepeatRow>,
+        #[template_child]
+        pub offset_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub offset_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub offset_unit_drop_down: TemplateChild<gtk::DropDown>,
+        #[template_child]
         pub delete_group: TemplateChild<adw::PreferencesGroup>,
     }
 
@@ -87,6 +102,18 @@ impl ReminderWindow {
         imp.date_row.set_datetime(&datetime);
         imp.time_row
             .set_time_from_digits(datetime.hour(), datetime.minute(), datetime.seconds());
+        imp.repeat_row.set_repeat(reminder.repeat_rule());
+        obj.set_task_date(
+            read_task(reminder.task())
+                .expect("Failed to read task")
+                .date(),
+        );
+        if reminder.relative() {
+            let (value, unit) = reminder.date_offset_parts();
+            imp.offset_spin_button.set_value(value as f64);
+            imp.offset_unit_drop_down.set_selected(unit as u32);
+            imp.mode_row.set_selected(1);
+        }
         if state {
             imp.delete_group.set_visible(true);
         }
@@ -101,7 +128,34 @@ impl ReminderWindow {
 
     #[template_callback]
     fn handle_done_button_clicked(&self, _button: gtk::Button) {
+        let imp = self.imp();
         let mut reminder = self.reminder();
+        if imp.mode_row.selected() == 1 {
+            let task_date = self.task_date();
+            if task_date == 0 {
+                let toast = adw::Toast::builder()
+                    .title(gettext("The task doesn't have a due date"))
+                    .build();
+                imp.toast_overlay.add_toast(toast);
+                return;
+            }
+            let offset = imp.offset_spin_button.value() as i64
+                * OFFSET_UNITS[imp.offset_unit_drop_down.selected() as usize];
+            reminder.set_relative(true);
+            reminder.set_date_offset(offset);
+            reminder.set_datetime(task_date - offset);
+            reminder.set_repeat(String::new());
+        } else {
+            let repeat = imp
+                .repeat_row
+                .repeat()
+                .map(|repeat| repeat.to_string())
+                .unwrap_or_default();
+            reminder.set_relative(false);
+            reminder.set_date_offset(0);
+            reminder.set_repeat(repeat);
+        }
+
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards");
@@ -110,15 +164,14 @@ impl ReminderWindow {
             let toast = adw::Toast::builder()
                 .title(gettext("Time can't be in the past"))
                 .build();
-            self.imp().toast_overlay.add_toast(toast);
+            imp.toast_overlay.add_toast(toast);
             return;
         }
 
         if self.state() {
             update_reminder(&reminder).expect("Failed to update reminder");
         } else {
-            reminder = create_reminder(reminder.datetime(), reminder.task(), reminder.priority())
-                .expect("Failed to create record");
+            reminder = create_reminder(reminder).expect("Failed to create reminder");
             self.transient_for()
                 .and_downcast::<gtk::Window>()
                 .unwrap()
@@ -133,6 +186,14 @@ impl ReminderWindow {
         self.close();
     }
 
+    #[template_callback]
+    fn handle_mode_selected(&self, _: glib::ParamSpec, mode_row: adw::ComboRow) {
+        let imp = self.imp();
+        let relative = mode_row.selected() == 1;
+        imp.datetime_group.set_visible(!relative);
+        imp.offset_group.set_visible(relative);
+    }
+
     #[template_callback]
     fn handle_date_changed(&self, datetime: glib::DateTime, _date_row: DateRow) {
         let imp = self.imp();
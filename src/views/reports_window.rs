@@ -0,0 +1,263 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib;
+use std::cell::RefCell;
+
+use crate::db::models::Record;
+use crate::db::operations::{read_projects, read_report, read_total_duration, ReportGroup};
+use crate::views::snippets::{Chart, DateRow};
+use crate::IPlanApplication;
+
+// Bigger groups are summed up as others
+const CHART_ITEMS: usize = 10;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/reports_window.ui")]
+    pub struct ReportsWindow {
+        #[template_child]
+        pub period_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub start_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub end_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub project_row: TemplateChild<adw::ComboRow>,
+        pub projects: RefCell<Vec<i64>>,
+        #[template_child]
+        pub group_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub total_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub average_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub previous_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub change_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub chart: TemplateChild<Chart>,
+        #[template_child]
+        pub placeholder: TemplateChild<gtk::Label>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ReportsWindow {
+        const NAME: &'static str = "ReportsWindow";
+        type Type = super::ReportsWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ReportsWindow {}
+    impl WidgetImpl for ReportsWindow {}
+    impl WindowImpl for ReportsWindow {}
+    impl AdwWindowImpl for ReportsWindow {}
+}
+
+glib::wrapper! {
+    pub struct ReportsWindow(ObjectSubclass<imp::ReportsWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+#[gtk::template_callbacks]
+impl ReportsWindow {
+    pub fn new(application: &IPlanApplication, app_window: &gtk::Window) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        win.add_projects();
+        win.init_custom_range();
+        win.refresh();
+        win
+    }
+
+    fn add_projects(&self) {
+        let imp = self.imp();
+        let model = imp
+            .project_row
+            .model()
+            .and_downcast::<gtk::StringList>()
+            .unwrap();
+        let mut project_ids = vec![];
+        for project in read_projects(true).expect("Failed to read projects") {
+            model.append(&project.name());
+            project_ids.push(project.id());
+        }
+        imp.projects.replace(project_ids);
+    }
+
+    fn init_custom_range(&self) {
+        // Current month by default
+        let imp = self.imp();
+        let today = glib::DateTime::now_local().unwrap();
+        let month_start = today.add_days(1 - today.day_of_month()).unwrap();
+        imp.start_row.set_datetime(&month_start);
+        imp.end_row.set_datetime(&today);
+    }
+
+    /// Start of the previous period, start and end of the selected period
+    fn range(&self) -> (glib::DateTime, glib::DateTime, glib::DateTime) {
+        let imp = self.imp();
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap();
+        match imp.period_row.selected() {
+            0 => {
+                let start = today.add_days(1 - today.day_of_week()).unwrap();
+                (
+                    start.add_weeks(-1).unwrap(),
+                    start.clone(),
+                    start.add_weeks(1).unwrap(),
+                )
+            }
+            1 => {
+                let start = today.add_days(1 - today.day_of_month()).unwrap();
+                (
+                    start.add_months(-1).unwrap(),
+                    start.clone(),
+                    start.add_months(1).unwrap(),
+                )
+            }
+            _ => {
+                // End date is included
+                let start = imp.start_row.date();
+                let end = imp.end_row.date().add_days(1).unwrap();
+                let days = days_between(&start, &end).max(1);
+                (start.add_days(-days).unwrap(), start, end)
+            }
+        }
+    }
+
+    fn refresh(&self) {
+        let imp = self.imp();
+        let (previous_start, start, end) = self.range();
+        let (previous_start, start, end) =
+            (previous_start.to_unix(), start.to_unix(), end.to_unix());
+        let selected = imp.project_row.selected() as usize;
+        // First item is all projects
+        let project_id = if selected == 0 {
+            None
+        } else {
+            imp.projects.borrow().get(selected - 1).copied()
+        };
+
+        let total = read_total_duration(start, end, project_id).expect("Failed to read records");
+        imp.total_label.set_label(&Record::duration_display(total));
+
+        // Days in the future are not part of the average
+        let now = glib::DateTime::now_local().unwrap();
+        let tomorrow =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap()
+                .add_days(1)
+                .unwrap();
+        let days = days_between(
+            &glib::DateTime::from_unix_local(start).unwrap(),
+            &glib::DateTime::from_unix_local(end.min(tomorrow.to_unix()).max(start)).unwrap(),
+        )
+        .max(1);
+        imp.average_label
+            .set_label(&Record::duration_display(total / days as i64));
+
+        let previous =
+            read_total_duration(previous_start, start, project_id).expect("Failed to read records");
+        imp.previous_row
+            .set_subtitle(&Record::duration_display(previous));
+        imp.change_label.remove_css_class("success");
+        imp.change_label.remove_css_class("error");
+        if previous == 0 {
+            imp.change_label.set_label("");
+        } else {
+            let change = (total - previous) as f64 / previous as f64 * 100.0;
+            imp.change_label.set_label(&format!("{change:+.0}%"));
+            if change >= 0.0 {
+                imp.change_label.add_css_class("success");
+            } else {
+                imp.change_label.add_css_class("error");
+            }
+        }
+
+        self.refresh_chart(start, end, project_id);
+    }
+
+    fn refresh_chart(&self, start: i64, end: i64, project_id: Option<i64>) {
+        let imp = self.imp();
+        let group = match imp.group_row.selected() {
+            0 => ReportGroup::Project,
+            1 => ReportGroup::Section,
+            2 => ReportGroup::Task,
+            _ => ReportGroup::Weekday,
+        };
+        let mut items = read_report(start, end, project_id, group).expect("Failed to read records");
+
+        if group == ReportGroup::Weekday {
+            // Every weekday from Monday with the local name
+            let monday = glib::DateTime::from_local(2024, 1, 1, 0, 0, 0.0).unwrap();
+            items = (0..7)
+                .map(|i| {
+                    let key = ((i + 1) % 7).to_string();
+                    let duration = items
+                        .iter()
+                        .find(|(name, _)| name == &key)
+                        .map(|(_, duration)| *duration)
+                        .unwrap_or(0);
+                    let name = monday.add_days(i).unwrap().format("%a").unwrap();
+                    (name.to_string(), duration)
+                })
+                .collect();
+        } else if items.len() > CHART_ITEMS {
+            let others = items
+                .split_off(CHART_ITEMS - 1)
+                .iter()
+                .map(|(_, duration)| duration)
+                .sum();
+            items.push((gettext("Others"), others));
+        }
+
+        imp.chart.clear();
+        if items.iter().all(|(_, duration)| *duration == 0) {
+            imp.placeholder.set_visible(true);
+            return;
+        }
+        imp.placeholder.set_visible(false);
+        let (labels, durations): (Vec<String>, Vec<i64>) = items.into_iter().unzip();
+        imp.chart.set_durations(labels, &durations);
+    }
+
+    #[template_callback]
+    fn period_selected(&self, _: glib::ParamSpec, period_row: adw::ComboRow) {
+        let imp = self.imp();
+        let custom = period_row.selected() == 2;
+        imp.start_row.set_visible(custom);
+        imp.end_row.set_visible(custom);
+        self.refresh();
+    }
+
+    #[template_callback]
+    fn date_changed(&self, _: glib::DateTime, _: DateRow) {
+        self.refresh();
+    }
+
+    #[template_callback]
+    fn option_selected(&self, _: glib::ParamSpec, _: adw::ComboRow) {
+        self.refresh();
+    }
+}
+
+/// Number of calendar days, rounded for the daylight saving changes
+fn days_between(start: &glib::DateTime, end: &glib::DateTime) -> i32 {
+    ((end.to_unix() - start.to_unix() + 43200) / 86400) as i32
+}
@@ -30,6 +30,8 @@ mod imp {
         #[template_child]
         pub name: TemplateChild<gtk::Label>,
         #[template_child]
+        pub snippet: TemplateChild<gtk::Label>,
+        #[template_child]
         pub type_label: TemplateChild<gtk::Label>,
     }
 
@@ -87,6 +89,14 @@ impl SearchResult {
         obj
     }
 
+    /// Show the matched words from the search
+    pub fn set_highlight(&self, name_markup: &str, snippet_markup: &str) {
+        let imp = self.imp();
+        imp.name.set_markup(name_markup);
+        imp.snippet.set_markup(snippet_markup);
+        imp.snippet.set_visible(!snippet_markup.is_empty());
+    }
+
     pub fn data(&self) -> SearchResultData {
         self.imp().data.take()
     }
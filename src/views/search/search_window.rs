@@ -3,7 +3,7 @@ use gtk::{gdk, glib, glib::Properties, prelude::*, subclass::prelude::*};
 use std::cell::RefCell;
 
 use crate::db::models::{Project, Task};
-use crate::db::operations::{find_projects, find_tasks};
+use crate::db::operations::{query_tasks, search_projects, TaskQuery};
 use crate::views::search::{SearchResult, SearchResultData};
 
 mod imp {
@@ -20,6 +20,8 @@ mod imp {
         #[template_child]
         pub show_done_tasks_toggle_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
+        pub query_error_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub search_results: TemplateChild<gtk::ListBox>,
         #[template_child]
         pub search_results_placeholder: TemplateChild<adw::StatusPage>,
@@ -114,7 +116,7 @@ impl SearchWindow {
                     }
 
                 }
-				glib::Propagation::Proceed
+                glib::Propagation::Proceed
         }));
         imp.search_entry.add_controller(search_entry_controller);
         win
@@ -157,31 +159,45 @@ impl SearchWindow {
             return;
         }
 
+        imp.search_entry.remove_css_class("error");
+        imp.query_error_label.set_visible(false);
         if text.is_empty() {
             self.clear_search_results();
             imp.search_results_placeholder.set_visible(false);
             return;
         } else {
+            let query = match TaskQuery::parse(text) {
+                Ok(query) => query,
+                Err(error) => {
+                    self.clear_search_results();
+                    imp.search_results_placeholder.set_visible(false);
+                    imp.search_entry.add_css_class("error");
+                    imp.query_error_label.set_label(&error.to_string());
+                    imp.query_error_label.set_visible(true);
+                    self.set_prev_search(String::new());
+                    return;
+                }
+            };
             let archive = imp.show_done_tasks_toggle_button.is_active();
-            let projects = find_projects(text, archive).expect("Failed to search projects");
-            let tasks = find_tasks(text, archive).expect("Failed to search tasks");
+            // Filters are only for tasks
+            let projects = if query.filters.is_empty() {
+                search_projects(&query.text, archive).expect("Failed to search projects")
+            } else {
+                Vec::new()
+            };
+            let tasks = query_tasks(&query, archive).expect("Failed to search tasks");
 
             imp.search_results_placeholder.set_visible(true);
-            if imp.search_results.observe_children().n_items()
-                == (projects.len() + tasks.len() + 1) as u32
-            {
-                // One for placeholder
-                // FIXME: check it work with fuzzy match
-                return;
-            }
             self.clear_search_results();
-            for project in projects {
-                imp.search_results
-                    .append(&SearchResult::new(SearchResultData::Project(project)));
+            for (project, name_markup, snippet_markup) in projects {
+                let result = SearchResult::new(SearchResultData::Project(project));
+                result.set_highlight(&name_markup, &snippet_markup);
+                imp.search_results.append(&result);
             }
-            for task in tasks {
-                imp.search_results
-                    .append(&SearchResult::new(SearchResultData::Task(task)));
+            for (task, name_markup, snippet_markup) in tasks {
+                let result = SearchResult::new(SearchResultData::Task(task));
+                result.set_highlight(&name_markup, &snippet_markup);
+                imp.search_results.append(&result);
             }
         }
 
@@ -3,3 +3,9 @@ pub use sidebar_projects::SidebarProjects;
 
 mod project_row;
 pub use project_row::ProjectRow;
+
+mod sidebar_tags;
+pub use sidebar_tags::SidebarTags;
+
+mod sidebar_filters;
+pub use sidebar_filters::SidebarFilters;
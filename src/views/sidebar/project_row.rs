@@ -1,7 +1,7 @@
 use gtk::{gdk, glib, glib::Properties, prelude::*, subclass::prelude::*};
 use std::cell::RefCell;
 
-use crate::db::models::Project;
+use crate::db::models::{Project, Record};
 use crate::db::operations::read_projects;
 use crate::views::sidebar::SidebarProjects;
 
@@ -18,6 +18,8 @@ mod imp {
         pub icon_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub name_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub goal_level: TemplateChild<gtk::LevelBar>,
     }
 
     #[glib::object_subclass]
@@ -39,6 +41,9 @@ mod imp {
     impl ObjectImpl for ProjectRow {
         fn constructed(&self) {
             self.parent_constructed();
+            for offset in ["low", "high", "full"] {
+                self.goal_level.remove_offset_value(Some(offset));
+            }
         }
 
         fn properties() -> &'static [glib::ParamSpec] {
@@ -78,9 +83,36 @@ impl ProjectRow {
         };
 
         obj.set_project(project);
+        obj.refresh_goal();
         obj
     }
 
+    pub fn refresh_goal(&self) {
+        let imp = self.imp();
+        let project = self.project();
+        let goal = project.goal();
+        if goal == 0 {
+            imp.goal_level.set_visible(false);
+            return;
+        }
+
+        let progress = project.goal_progress();
+        imp.goal_level
+            .set_value((progress as f64 / goal as f64).min(1.0));
+        imp.goal_level.set_tooltip_text(Some(&format!(
+            "{}: {} / {}",
+            project.goal_period_display(),
+            Record::duration_display(progress),
+            Record::duration_display(goal)
+        )));
+        if progress >= goal {
+            imp.goal_level.add_css_class("reached");
+        } else {
+            imp.goal_level.remove_css_class("reached");
+        }
+        imp.goal_level.set_visible(true);
+    }
+
     #[template_callback]
     fn handle_drag_prepare(&self, _x: f64, _y: f64) -> Option<gdk::ContentProvider> {
         Some(gdk::ContentProvider::for_value(&self.to_value()))
@@ -0,0 +1,192 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+use crate::db::models::Filter;
+use crate::db::operations::{create_filter, read_filters, seal_history, SmartView, TaskQuery};
+use crate::views::filter::TaskView;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/sidebar/sidebar_filters.ui")]
+    pub struct SidebarFilters {
+        #[template_child]
+        pub views_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub new_filter_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub name_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub query_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub query_error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub filters_box: TemplateChild<gtk::ListBox>,
+        pub filters: RefCell<Vec<Filter>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SidebarFilters {
+        const NAME: &'static str = "SidebarFilters";
+        type Type = super::SidebarFilters;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SidebarFilters {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            for view in SmartView::ALL {
+                self.views_box
+                    .append(&obj.create_row(view.icon_name(), &view.display()));
+            }
+            obj.refresh();
+        }
+    }
+    impl WidgetImpl for SidebarFilters {}
+    impl BoxImpl for SidebarFilters {}
+}
+
+glib::wrapper! {
+    pub struct SidebarFilters(ObjectSubclass<imp::SidebarFilters>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Buildable;
+}
+
+impl Default for SidebarFilters {
+    fn default() -> Self {
+        glib::Object::new::<SidebarFilters>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl SidebarFilters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch saved filters again when they are added, changed or deleted
+    pub fn refresh(&self) {
+        let imp = self.imp();
+        let filters = read_filters().expect("Failed to read filters");
+        let selected = imp
+            .filters_box
+            .selected_row()
+            .and_then(|row| imp.filters.borrow().get(row.index() as usize).cloned());
+        while let Some(row) = imp.filters_box.first_child() {
+            imp.filters_box.remove(&row);
+        }
+        for filter in &filters {
+            imp.filters_box
+                .append(&self.create_row("system-search-symbolic", &filter.name()));
+        }
+        imp.filters.replace(filters);
+        if let Some(filter) = selected {
+            self.select_view(Some(&TaskView::Saved(filter)));
+        }
+    }
+
+    pub fn select_view(&self, view: Option<&TaskView>) {
+        let imp = self.imp();
+        let (view_row, filter_row) = match view {
+            Some(TaskView::Smart(view)) => (imp.views_box.row_at_index(view.index() as i32), None),
+            Some(TaskView::Saved(filter)) => {
+                let index = imp
+                    .filters
+                    .borrow()
+                    .iter()
+                    .position(|saved| saved.id() == filter.id());
+                (
+                    None,
+                    index.and_then(|index| imp.filters_box.row_at_index(index as i32)),
+                )
+            }
+            None => (None, None),
+        };
+        imp.views_box.select_row(view_row.as_ref());
+        imp.filters_box.select_row(filter_row.as_ref());
+    }
+
+    fn create_row(&self, icon_name: &str, label: &str) -> gtk::Box {
+        let row = gtk::Box::builder().spacing(6).margin_start(6).build();
+        row.append(&gtk::Image::from_icon_name(icon_name));
+        row.append(
+            &gtk::Label::builder()
+                .label(label)
+                .halign(gtk::Align::Start)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .build(),
+        );
+        row
+    }
+
+    fn open_view(&self, kind: u8, id: i64) {
+        self.activate_action("filter.open", Some(&(kind, id).to_variant()))
+            .expect("Failed to send filter.open action");
+    }
+
+    #[template_callback]
+    fn handle_views_box_row_activated(&self, row: gtk::ListBoxRow, _: gtk::ListBox) {
+        self.open_view(0, row.index() as i64);
+    }
+
+    #[template_callback]
+    fn handle_filters_box_row_activated(&self, row: gtk::ListBoxRow, _: gtk::ListBox) {
+        let filter_id = self.imp().filters.borrow()[row.index() as usize].id();
+        self.open_view(1, filter_id);
+    }
+
+    #[template_callback]
+    fn handle_new_filter_popover_show(&self, _: gtk::Popover) {
+        let imp = self.imp();
+        imp.name_entry.set_text("");
+        imp.query_entry.set_text("");
+        imp.name_entry.remove_css_class("error");
+        imp.query_entry.remove_css_class("error");
+        imp.query_error_label.set_visible(false);
+        imp.name_entry.grab_focus();
+    }
+
+    #[template_callback]
+    fn handle_add_filter(&self, _: gtk::Widget) {
+        let imp = self.imp();
+        let name = imp.name_entry.text().trim().to_string();
+        let query = imp.query_entry.text().trim().to_string();
+        imp.name_entry.remove_css_class("error");
+        imp.query_entry.remove_css_class("error");
+        imp.query_error_label.set_visible(false);
+        if name.is_empty() {
+            imp.name_entry.add_css_class("error");
+            return;
+        }
+        match TaskQuery::parse(&query) {
+            Ok(task_query) if task_query != TaskQuery::default() => (),
+            Ok(_) => {
+                imp.query_entry.add_css_class("error");
+                return;
+            }
+            Err(error) => {
+                imp.query_entry.add_css_class("error");
+                imp.query_error_label.set_label(&error.to_string());
+                imp.query_error_label.set_visible(true);
+                return;
+            }
+        }
+
+        let filter = create_filter(&name, &query).expect("Failed to create filter");
+        seal_history().expect("Failed to update history");
+        imp.new_filter_popover.popdown();
+        self.refresh();
+        self.open_view(1, filter.id());
+    }
+}
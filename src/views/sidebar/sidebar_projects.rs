@@ -4,7 +4,8 @@ use std::cell::RefCell;
 
 use crate::db::models::Project;
 use crate::db::operations::{
-    new_task_position, read_project, read_projects, read_sections, update_project, update_task,
+    new_task_position, read_project, read_projects, read_sections, seal_history, update_project,
+    update_task,
 };
 use crate::views::ActionScope;
 use crate::views::{project::ProjectCreateWindow, sidebar::ProjectRow, task::TaskRow, IPlanWindow};
@@ -94,9 +95,16 @@ impl SidebarProjects {
             row_imp.name_label.remove_css_class("dim-label");
         }
         row.set_project(project);
+        row.refresh_goal();
         row.changed();
     }
 
+    pub fn refresh_project_goal(&self, project_id: i64) {
+        if let Some(row) = self.project_row_by_id(project_id) {
+            row.refresh_goal();
+        }
+    }
+
     pub fn delete_project(&self, project_id: i64) {
         let imp = self.imp();
         let target_row = self.project_row_by_id(project_id).unwrap();
@@ -282,6 +290,7 @@ impl SidebarProjects {
         let project_db = read_project(project.id()).expect("Failed to read project");
         if project_db.index() != project.index() {
             update_project(&project).expect("Failed to update project");
+            seal_history().expect("Failed to update history");
         }
         self.select_active_project();
         true
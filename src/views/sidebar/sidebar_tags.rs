@@ -0,0 +1,119 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+use crate::db::models::Tag;
+use crate::db::operations::read_tags;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/sidebar/sidebar_tags.ui")]
+    pub struct SidebarTags {
+        #[template_child]
+        pub tags_box: TemplateChild<gtk::ListBox>,
+        pub tags: RefCell<Vec<Tag>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SidebarTags {
+        const NAME: &'static str = "SidebarTags";
+        type Type = super::SidebarTags;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SidebarTags {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().refresh();
+        }
+    }
+    impl WidgetImpl for SidebarTags {}
+    impl BoxImpl for SidebarTags {}
+}
+
+glib::wrapper! {
+    pub struct SidebarTags(ObjectSubclass<imp::SidebarTags>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Buildable;
+}
+
+impl Default for SidebarTags {
+    fn default() -> Self {
+        glib::Object::new::<SidebarTags>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl SidebarTags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch tags again when they are added, renamed or deleted
+    pub fn refresh(&self) {
+        let imp = self.imp();
+        let tags = read_tags().expect("Failed to read tags");
+        let is_same = {
+            let current_tags = imp.tags.borrow();
+            current_tags.len() == tags.len()
+                && current_tags
+                    .iter()
+                    .zip(&tags)
+                    .all(|(current, tag)| current.id() == tag.id() && current.name() == tag.name())
+        };
+        if is_same && imp.tags_box.first_child().is_some() {
+            return;
+        }
+
+        let selected = self.selected_tag_id();
+        while let Some(row) = imp.tags_box.first_child() {
+            imp.tags_box.remove(&row);
+        }
+        for tag in &tags {
+            let label = gtk::Label::builder()
+                .label(tag.name())
+                .halign(gtk::Align::Start)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .margin_start(6)
+                .build();
+            imp.tags_box.append(&label);
+        }
+        self.set_visible(!tags.is_empty());
+        imp.tags.replace(tags);
+        self.select_tag(selected);
+    }
+
+    pub fn select_tag(&self, tag_id: Option<i64>) {
+        let imp = self.imp();
+        let index =
+            tag_id.and_then(|tag_id| imp.tags.borrow().iter().position(|tag| tag.id() == tag_id));
+        let row = index.and_then(|index| imp.tags_box.row_at_index(index as i32));
+        imp.tags_box.select_row(row.as_ref());
+    }
+
+    fn selected_tag_id(&self) -> Option<i64> {
+        let imp = self.imp();
+        let row = imp.tags_box.selected_row()?;
+        imp.tags
+            .borrow()
+            .get(row.index() as usize)
+            .map(|tag| tag.id())
+    }
+
+    #[template_callback]
+    fn handle_tags_box_row_activated(&self, row: gtk::ListBoxRow, _: gtk::ListBox) {
+        let tag_id = self.imp().tags.borrow()[row.index() as usize].id();
+        self.activate_action("tag.open", Some(&tag_id.to_variant()))
+            .expect("Failed to send tag.open action");
+    }
+}
@@ -1,5 +1,7 @@
 use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::Cell;
 
+use crate::db::models::Record;
 use crate::views::snippets::ChartBar;
 
 mod imp {
@@ -14,6 +16,8 @@ mod imp {
         pub levels: TemplateChild<gtk::Box>,
         #[template_child]
         pub lines: TemplateChild<gtk::Box>,
+        /// Number of bars, y labels are in the row after them
+        pub rows: Cell<i32>,
     }
 
     #[glib::object_subclass]
@@ -65,6 +69,7 @@ impl Chart {
         let imp = self.imp();
         let row: i32 = x_labels.len() as i32;
         let column = y_labels.len() as i32;
+        imp.rows.set(row);
 
         for (i, label) in x_labels.iter().enumerate() {
             self.add_x_label(label, i as i32);
@@ -91,6 +96,26 @@ impl Chart {
         overlay_layout_child.set_property("row-span", row);
     }
 
+    /// Show durations with an hour scale that fits the biggest one
+    pub fn set_durations(&self, x_labels: Vec<String>, durations: &[i64]) {
+        let biggest_hour = durations.iter().max().copied().unwrap_or(0) / 3600;
+        // Keep the scale readable for long periods
+        let step = (biggest_hour + 2 + 11) / 12;
+        let lines = biggest_hour / step + 2;
+        let max = (lines * step * 3600) as f64;
+
+        let y_labels = (0..lines).map(|i| (i * step).to_string()).collect();
+        let percentages = durations
+            .iter()
+            .map(|duration| *duration as f64 / max)
+            .collect();
+        let tooltips = durations
+            .iter()
+            .map(|duration| Record::duration_display(*duration))
+            .collect();
+        self.set_data(x_labels, percentages, y_labels, tooltips);
+    }
+
     pub fn clear(&self) {
         let imp = self.imp();
 
@@ -106,12 +131,17 @@ impl Chart {
             imp.lines.remove(&bar);
         }
 
-        self.remove_row(7);
-        self.remove_column(0);
+        if imp.rows.get() != 0 {
+            self.remove_row(imp.rows.get());
+            self.remove_column(0);
+            imp.rows.set(0);
+        }
     }
 
     fn add_x_label(&self, label: &str, row: i32) {
         let label = gtk::Label::new(Some(label));
+        label.set_max_width_chars(16);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::End);
         label.set_vexpand(true);
         label.set_valign(gtk::Align::Center);
         label.add_css_class("dim-label");
@@ -124,7 +154,7 @@ impl Chart {
         label.set_halign(gtk::Align::Start);
         label.add_css_class("dim-label");
         label.add_css_class("chart-y-label");
-        self.attach(&label, column, 7, 1, 1);
+        self.attach(&label, column, self.imp().rows.get(), 1, 1);
     }
 
     fn add_line(&self) {
@@ -12,3 +12,6 @@ pub use chart_bar::ChartBar;
 
 mod menu_item;
 pub use menu_item::MenuItem;
+
+mod repeat_row;
+pub use repeat_row::RepeatRow;
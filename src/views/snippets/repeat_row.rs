@@ -0,0 +1,247 @@
+use adw::subclass::prelude::*;
+use adw::traits::ActionRowExt;
+use gettextrs::gettext;
+use gtk::glib::{once_cell::sync::Lazy, subclass::*, Properties};
+use gtk::{glib, prelude::*};
+use std::cell::Cell;
+
+use crate::db::models::{Repeat, RepeatFrequency};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate, Properties)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/snippets/repeat_row.ui")]
+    #[properties(wrapper_type=super::RepeatRow)]
+    pub struct RepeatRow {
+        #[template_child]
+        pub menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub frequency_drop_down: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub interval_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub interval_prefix_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub interval_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub interval_unit_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub weekdays_box: TemplateChild<gtk::Box>,
+        #[property(get, set)]
+        pub skip: Cell<bool>,
+        #[property(get, set)]
+        pub completion_option: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RepeatRow {
+        const NAME: &'static str = "RepeatRow";
+        type Type = super::RepeatRow;
+        type ParentType = adw::ActionRow;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for RepeatRow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.init_weekdays();
+            obj.refresh_frequencies();
+            obj.connect_completion_option_notify(|obj| obj.refresh_frequencies());
+            obj.refresh_row();
+        }
+
+        fn signals() -> &'static [glib::subclass::Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("repeat-changed")
+                    .param_types([String::static_type()])
+                    .build()]
+            });
+            SIGNALS.as_ref()
+        }
+
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+    }
+    impl WidgetImpl for RepeatRow {}
+    impl ListBoxRowImpl for RepeatRow {}
+    impl PreferencesRowImpl for RepeatRow {}
+    impl ActionRowImpl for RepeatRow {}
+}
+
+glib::wrapper! {
+    pub struct RepeatRow(ObjectSubclass<imp::RepeatRow>)
+        @extends gtk::Widget, gtk::ListBoxRow, adw::PreferencesRow, adw::ActionRow,
+        @implements gtk::Buildable, gtk::Actionable, gtk::Accessible, gtk::ConstraintTarget;
+}
+
+impl Default for RepeatRow {
+    fn default() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl RepeatRow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn repeat(&self) -> Option<Repeat> {
+        let imp = self.imp();
+        let selected = imp.frequency_drop_down.selected() as usize;
+        if selected == 0 {
+            return None;
+        }
+
+        let mut weekdays = 0;
+        let mut day = 0;
+        let mut child = imp.weekdays_box.first_child();
+        while let Some(button) = child.and_downcast::<gtk::ToggleButton>() {
+            if button.is_active() {
+                weekdays |= 1 << day;
+            }
+            day += 1;
+            child = button.next_sibling();
+        }
+
+        Some(Repeat::new(
+            RepeatFrequency::ALL[selected - 1],
+            imp.interval_spin_button.value() as u32,
+            weekdays,
+        ))
+    }
+
+    pub fn set_repeat(&self, repeat: Option<Repeat>) {
+        let imp = self.imp();
+        self.set_skip(true);
+        let repeat_or_default = repeat.unwrap_or_default();
+        let selected = if let Some(repeat) = repeat {
+            RepeatFrequency::ALL
+                .iter()
+                .position(|frequency| frequency == &repeat.frequency)
+                .unwrap()
+                + 1
+        } else {
+            0
+        };
+        imp.frequency_drop_down.set_selected(selected as u32);
+        imp.interval_spin_button
+            .set_value(repeat_or_default.interval as f64);
+        let mut day = 1;
+        let mut child = imp.weekdays_box.first_child();
+        while let Some(button) = child.and_downcast::<gtk::ToggleButton>() {
+            button.set_active(repeat_or_default.has_weekday(day));
+            day += 1;
+            child = button.next_sibling();
+        }
+        self.set_skip(false);
+        self.refresh_row();
+    }
+
+    fn refresh_frequencies(&self) {
+        // After completion is the last item and only meaningful for tasks
+        let model = self
+            .imp()
+            .frequency_drop_down
+            .model()
+            .and_downcast::<gtk::StringList>()
+            .unwrap();
+        let items_count = RepeatFrequency::ALL.len() as u32 + 1;
+        if self.completion_option() {
+            if model.n_items() < items_count {
+                model.append(&gettext("After completion"));
+            }
+        } else if model.n_items() == items_count {
+            model.remove(items_count - 1);
+        }
+    }
+
+    fn init_weekdays(&self) {
+        let imp = self.imp();
+        for day in 1..=7 {
+            let button = gtk::ToggleButton::with_label(&Repeat::weekday_name(day));
+            button.connect_toggled(glib::clone!(@weak self as obj => move |_| {
+                obj.repeat_changed();
+            }));
+            imp.weekdays_box.append(&button);
+        }
+    }
+
+    fn refresh_row(&self) {
+        let imp = self.imp();
+        if let Some(repeat) = self.repeat() {
+            self.set_subtitle(&repeat.display());
+            imp.interval_box.set_visible(true);
+            imp.weekdays_box
+                .set_visible(repeat.frequency == RepeatFrequency::Weekly);
+            let plural = repeat.interval != 1;
+            let unit = match (repeat.frequency, plural) {
+                (RepeatFrequency::Daily | RepeatFrequency::AfterDone, false) => gettext("day"),
+                (RepeatFrequency::Daily | RepeatFrequency::AfterDone, true) => gettext("days"),
+                (RepeatFrequency::Weekly, false) => gettext("week"),
+                (RepeatFrequency::Weekly, true) => gettext("weeks"),
+                (RepeatFrequency::Monthly | RepeatFrequency::MonthlyWeekday, false) => {
+                    gettext("month")
+                }
+                (RepeatFrequency::Monthly | RepeatFrequency::MonthlyWeekday, true) => {
+                    gettext("months")
+                }
+                (RepeatFrequency::Yearly, false) => gettext("year"),
+                (RepeatFrequency::Yearly, true) => gettext("years"),
+            };
+            let prefix = if repeat.frequency == RepeatFrequency::AfterDone {
+                gettext("After")
+            } else {
+                gettext("Every")
+            };
+            imp.interval_prefix_label.set_label(&prefix);
+            imp.interval_unit_label.set_label(&unit);
+        } else {
+            self.set_subtitle("");
+            imp.interval_box.set_visible(false);
+            imp.weekdays_box.set_visible(false);
+        }
+    }
+
+    fn repeat_changed(&self) {
+        if self.skip() {
+            return;
+        }
+
+        self.refresh_row();
+        let repeat = self
+            .repeat()
+            .map(|repeat| repeat.to_string())
+            .unwrap_or_default();
+        self.emit_by_name::<()>("repeat-changed", &[&repeat]);
+    }
+
+    #[template_callback]
+    fn handle_frequency_selected(&self, _: glib::ParamSpec, _: gtk::DropDown) {
+        self.repeat_changed();
+    }
+
+    #[template_callback]
+    fn handle_interval_changed(&self, _: gtk::SpinButton) {
+        self.repeat_changed();
+    }
+}
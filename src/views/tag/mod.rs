@@ -0,0 +1,2 @@
+mod tag_page;
+pub use tag_page::TagPage;
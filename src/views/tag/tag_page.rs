@@ -0,0 +1,135 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+use crate::db::models::{Tag, Task};
+use crate::db::operations::{find_tag, update_tag, TaskFilter, TaskQuery};
+use crate::views::snippets::MenuItem;
+use crate::views::task::{QueryPage, TaskRow, TasksBoxWrapper};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate, glib::Properties)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/tag/tag_page.ui")]
+    #[properties(wrapper_type=super::TagPage)]
+    pub struct TagPage {
+        #[property(get, set)]
+        pub tag: RefCell<Option<Tag>>,
+        #[template_child]
+        pub page: TemplateChild<QueryPage>,
+        #[template_child]
+        pub options_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub name_entry: TemplateChild<gtk::Entry>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TagPage {
+        const NAME: &'static str = "TagPage";
+        type Type = super::TagPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            MenuItem::ensure_type();
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TagPage {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+    }
+    impl WidgetImpl for TagPage {}
+    impl BoxImpl for TagPage {}
+}
+
+glib::wrapper! {
+    pub struct TagPage(ObjectSubclass<imp::TagPage>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Buildable;
+}
+
+impl Default for TagPage {
+    fn default() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl TagPage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open_tag(&self, tag: Tag) {
+        let query = TaskQuery {
+            text: String::new(),
+            filters: vec![TaskFilter::Tag(tag.name())],
+        };
+        self.imp().page.open_query(
+            &tag.name(),
+            "tag-symbolic",
+            query,
+            TasksBoxWrapper::Tag(tag.id()),
+        );
+        self.set_tag(Some(tag));
+    }
+
+    /// Add, update or remove the task row by its tags
+    pub fn reset_task(&self, task: &Task) {
+        self.imp().page.reset_task(task);
+    }
+
+    pub fn task_row(&self, task_id: i64) -> Option<TaskRow> {
+        self.imp().page.task_row(task_id)
+    }
+
+    #[template_callback]
+    fn handle_options_popover_show(&self, _: gtk::Popover) {
+        let imp = self.imp();
+        imp.name_entry.remove_css_class("error");
+        if let Some(tag) = self.tag() {
+            imp.name_entry.set_text(&tag.name());
+        }
+    }
+
+    #[template_callback]
+    fn handle_name_entry_activate(&self, entry: gtk::Entry) {
+        let imp = self.imp();
+        let tag = self.tag().unwrap();
+        let name = entry.text().trim().to_string();
+        // Names are unique
+        if let Some(other_tag) = find_tag(&name).expect("Failed to read tags") {
+            if other_tag.id() != tag.id() {
+                entry.add_css_class("error");
+                return;
+            }
+        }
+        if name.is_empty() {
+            entry.add_css_class("error");
+            return;
+        }
+
+        tag.set_name(name);
+        update_tag(&tag).expect("Failed to update tag");
+        imp.options_popover.popdown();
+        // The query has the tag name
+        self.open_tag(tag.clone());
+        self.activate_action("tag.changed", Some(&tag.id().to_variant()))
+            .unwrap();
+    }
+}
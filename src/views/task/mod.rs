@@ -1,5 +1,5 @@
 mod task_row;
-pub use task_row::{TaskRow, TimerStatus};
+pub use task_row::TaskRow;
 
 mod subtask_row;
 pub use subtask_row::SubtaskRow;
@@ -13,5 +13,8 @@ pub use task_page::TaskPage;
 mod tasks_box;
 pub use tasks_box::{TasksBox, TasksBoxWrapper};
 
+mod query_page;
+pub use query_page::QueryPage;
+
 mod tasks_done_window;
-pub use tasks_done_window::TasksDoneWindow;
+pub use tasks_done_window::{CompletionPeriod, TasksDoneWindow};
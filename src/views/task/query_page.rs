@@ -0,0 +1,208 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+use crate::application::IPlanApplication;
+use crate::db::models::{Record, Task};
+use crate::db::operations::{query_has_task, query_tasks, TaskQuery};
+use crate::timer::Timer;
+use crate::views::task::{TaskRow, TaskWindow, TasksBox, TasksBoxWrapper};
+use crate::views::ActionScope;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate, glib::Properties)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/task/query_page.ui")]
+    #[properties(wrapper_type=super::QueryPage)]
+    pub struct QueryPage {
+        pub query: RefCell<Option<TaskQuery>>,
+        #[property(get, set)]
+        pub menu: RefCell<Option<gtk::Popover>>,
+        #[property(get, set)]
+        pub menu_tooltip: RefCell<String>,
+        #[template_child]
+        pub page_header: TemplateChild<adw::HeaderBar>,
+        #[template_child]
+        pub toggle_sidebar_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub icon_image: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub name_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub tasks_box: TemplateChild<TasksBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for QueryPage {
+        const NAME: &'static str = "QueryPage";
+        type Type = super::QueryPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+            klass.install_action(
+                "task.changed",
+                Some(Task::static_variant_type().as_str()),
+                |obj, _, value| {
+                    obj.activate_task_action("task.changed", value.unwrap());
+                },
+            );
+            klass.install_action(
+                "task.duration-changed",
+                Some(Task::static_variant_type().as_str()),
+                |obj, _, value| {
+                    obj.activate_task_action("task.duration-changed", value.unwrap());
+                },
+            );
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for QueryPage {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.bind_property("menu", &self.menu_button.get(), "popover")
+                .sync_create()
+                .build();
+            obj.bind_property("menu-tooltip", &self.menu_button.get(), "tooltip-text")
+                .sync_create()
+                .build();
+            obj.connect_timer();
+        }
+    }
+    impl WidgetImpl for QueryPage {}
+    impl BoxImpl for QueryPage {}
+}
+
+glib::wrapper! {
+    /// Undone tasks matching a query, the base of the tag and filter pages
+    pub struct QueryPage(ObjectSubclass<imp::QueryPage>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Buildable;
+}
+
+impl Default for QueryPage {
+    fn default() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl QueryPage {
+    pub fn open_query(
+        &self,
+        name: &str,
+        icon_name: &str,
+        query: TaskQuery,
+        wrapper: TasksBoxWrapper,
+    ) {
+        let imp = self.imp();
+        imp.name_label.set_label(name);
+        imp.icon_image.set_icon_name(Some(icon_name));
+
+        let items = imp.tasks_box.imp().items_box.observe_children();
+        for i in (0..items.n_items()).rev() {
+            if let Some(row) = items.item(i).and_downcast::<TaskRow>() {
+                imp.tasks_box.remove_item(&row);
+            }
+        }
+        imp.tasks_box.set_items_wrapper(wrapper);
+        let tasks = query_tasks(&query, false).expect("Failed to read tasks");
+        imp.tasks_box
+            .add_tasks(tasks.into_iter().map(|(task, _, _)| task).collect());
+        imp.query.replace(Some(query));
+    }
+
+    /// Add, update or remove the task row by the query
+    pub fn reset_task(&self, task: &Task) {
+        let imp = self.imp();
+        let Some(query) = imp.query.borrow().clone() else {
+            return;
+        };
+
+        let is_match = query_has_task(&query, false, task.id()).expect("Failed to read tasks");
+        if let Some(row) = imp.tasks_box.item_by_id(task.id()) {
+            if is_match {
+                row.reset(task.clone());
+                row.changed();
+            } else {
+                imp.tasks_box.remove_item(&row);
+            }
+        } else if is_match {
+            imp.tasks_box.add_task(task.clone());
+        }
+    }
+
+    pub fn task_row(&self, task_id: i64) -> Option<TaskRow> {
+        self.imp().tasks_box.item_by_id(task_id)
+    }
+
+    /// Timers change the running timers view without a task change
+    fn connect_timer(&self) {
+        let timer = IPlanApplication::default().timer();
+        for signal in ["started", "stopped"] {
+            timer.connect_closure(
+                signal,
+                false,
+                glib::closure_local!(@watch self as obj => move |_: Timer, task: Task, _: Record| {
+                    obj.reset_task(&task);
+                }),
+            );
+        }
+    }
+
+    /// The owner page doesn't handle task actions, so they reach the window
+    fn activate_task_action(&self, name: &str, task: &glib::Variant) {
+        self.parent()
+            .unwrap()
+            .activate_action(
+                name,
+                Some(&glib::Variant::from((
+                    task.to_owned(),
+                    ActionScope::None.to_variant(),
+                ))),
+            )
+            .unwrap();
+    }
+
+    #[template_callback]
+    fn task_activated(&self, row: TaskRow, _: gtk::ListBox) {
+        let win = self.root().and_downcast::<gtk::Window>().unwrap();
+        let modal = TaskWindow::new(&win.application().unwrap(), &win, row.task());
+        modal.present();
+        modal.connect_closure(
+            "task-changed",
+            true,
+            glib::closure_local!(@watch row => move |_win: TaskWindow, changed_task: Task| {
+                row.activate_action("task.changed", Some(&changed_task.to_variant())).unwrap();
+            }),
+        );
+        modal.connect_closure(
+            "task-duration-changed",
+            true,
+            glib::closure_local!(@watch row => move |_win: TaskWindow, task: Task| {
+                row.refresh_timer();
+                row.activate_action("task.duration-changed", Some(&task.to_variant())).unwrap();
+            }),
+        );
+    }
+}
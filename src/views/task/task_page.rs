@@ -1,16 +1,22 @@
-use adw::traits::ExpanderRowExt;
+use adw::traits::{ActionRowExt, ExpanderRowExt};
+use gettextrs::gettext;
 use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::unimplemented;
 
-use crate::db::models::{Record, Reminder, Task};
+use crate::application::IPlanApplication;
+use crate::db::models::{Record, Reminder, Tag, Task};
 use crate::db::operations::{
-    create_task, new_subtask_position, read_records, read_reminder, read_reminders, read_tasks,
-    update_task,
+    add_task_blocker, add_task_tag, create_tag, create_task, dependency_has_cycle, find_tag,
+    find_tasks, new_subtask_position, read_blockers, read_records, read_reminder, read_reminders,
+    read_tags, read_task_tags, read_tasks, remove_task_blocker, remove_task_tag,
+    update_relative_reminders, update_task,
 };
+use crate::timer::Timer;
 use crate::views::record::{RecordRow, RecordWindow};
 use crate::views::reminder::{ReminderRow, ReminderWindow};
-use crate::views::snippets::DateRow;
+use crate::views::snippets::{DateRow, RepeatRow, TimeRow};
 use crate::views::task::{TaskRow, TasksBox, TasksBoxWrapper};
 
 mod imp {
@@ -23,6 +29,29 @@ mod imp {
         pub task_row: TemplateChild<TaskRow>,
         #[template_child]
         pub reminders_expander_row: TemplateChild<adw::ExpanderRow>,
+        pub reminder_rows: RefCell<Vec<ReminderRow>>,
+        #[template_child]
+        pub tags_expander_row: TemplateChild<adw::ExpanderRow>,
+        pub tag_rows: RefCell<Vec<adw::ActionRow>>,
+        #[template_child]
+        pub tags_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub tag_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub tags_list: TemplateChild<gtk::ListBox>,
+        /// Tags in the popover that the task doesn't have
+        pub other_tags: RefCell<Vec<Tag>>,
+        #[template_child]
+        pub blockers_expander_row: TemplateChild<adw::ExpanderRow>,
+        pub blocker_rows: RefCell<Vec<adw::ActionRow>>,
+        #[template_child]
+        pub blockers_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub blocker_search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub blocker_candidates_list: TemplateChild<gtk::ListBox>,
+        /// Search results in the popover that can block the task
+        pub blocker_candidates: RefCell<Vec<Task>>,
         #[template_child]
         pub description_expander_row: TemplateChild<adw::ExpanderRow>,
         #[template_child]
@@ -41,6 +70,10 @@ mod imp {
         pub records_box: TemplateChild<gtk::ListBox>,
         #[template_child]
         pub date_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub repeat_row: TemplateChild<RepeatRow>,
+        #[template_child]
+        pub estimate_row: TemplateChild<TimeRow>,
     }
 
     #[glib::object_subclass]
@@ -67,12 +100,7 @@ mod imp {
                         return;
                     }
 
-                    let mut records = read_records(Some(task.id()), false, None, None).unwrap();
-                    if imp.records_box.observe_children().n_items() - 1 < records.len() as u32 {
-                        records.sort_by_key(|record| record.id());
-                        let row = obj.new_record_row(records.last().unwrap().to_owned());
-                        imp.records_box.append(&row);
-                    }
+                    obj.add_new_record(task.id());
                 },
             );
         }
@@ -103,20 +131,22 @@ impl TaskPage {
         let task_description = task.description();
         let task_project = task.project();
         let task_date = task.date();
+        let task_repeat = task.repeat_rule();
+        let task_estimate = task.estimate();
         imp.task_row.reset(task);
-        imp.task_row.reset_timer();
+        imp.task_row.refresh_timer();
 
         imp.date_row.set_clear_option(true);
         let date = task_date;
         if date != 0 {
             imp.date_row.set_datetime_from_unix(date);
         }
+        imp.repeat_row.set_repeat(task_repeat);
+        imp.estimate_row.set_time(task_estimate as i32);
 
-        let reminders = read_reminders(Some(task_id)).expect("Failed to read reminders");
-        for reminder in reminders {
-            let row = obj.new_reminder_row(reminder);
-            imp.reminders_expander_row.add_row(&row);
-        }
+        obj.reset_reminders(task_id);
+        obj.reset_tags(task_id);
+        obj.reset_blockers(task_id);
 
         let task_description = task_description;
         imp.description_expander_row
@@ -148,9 +178,30 @@ impl TaskPage {
             imp.records_box.append(&row);
         }
 
+        IPlanApplication::default().timer().connect_closure(
+            "stopped",
+            false,
+            glib::closure_local!(@watch obj => move |_: Timer, task: Task, _: Record| {
+                if task.id() == obj.task().id() {
+                    obj.add_new_record(task.id());
+                }
+            }),
+        );
+
         obj
     }
 
+    /// Add the row of the last record when it's missing
+    fn add_new_record(&self, task_id: i64) {
+        let imp = self.imp();
+        let mut records = read_records(Some(task_id), false, None, None).unwrap();
+        if imp.records_box.observe_children().n_items() - 1 < records.len() as u32 {
+            records.sort_by_key(|record| record.id());
+            let row = self.new_record_row(records.last().unwrap().to_owned());
+            imp.records_box.append(&row);
+        }
+    }
+
     pub fn task(&self) -> Task {
         self.imp().task_row.task()
     }
@@ -160,10 +211,127 @@ impl TaskPage {
         let reminder = read_reminder(reminder_id).expect("Failed to read record");
         let row = self.new_reminder_row(reminder);
         imp.reminders_expander_row.add_row(&row);
+        imp.reminder_rows.borrow_mut().push(row);
         self.activate_action("task.changed", Some(&self.task().to_variant()))
             .unwrap();
     }
 
+    fn reset_reminders(&self, task_id: i64) {
+        let imp = self.imp();
+        for row in imp.reminder_rows.take() {
+            imp.reminders_expander_row.remove(&row);
+        }
+
+        let reminders = read_reminders(Some(task_id)).expect("Failed to read reminders");
+        let mut reminder_rows = vec![];
+        for reminder in reminders {
+            let row = self.new_reminder_row(reminder);
+            imp.reminders_expander_row.add_row(&row);
+            reminder_rows.push(row);
+        }
+        imp.reminder_rows.replace(reminder_rows);
+    }
+
+    fn reset_tags(&self, task_id: i64) {
+        let imp = self.imp();
+        for row in imp.tag_rows.take() {
+            imp.tags_expander_row.remove(&row);
+        }
+
+        let tags = read_task_tags(task_id).expect("Failed to read tags");
+        let mut tag_rows = vec![];
+        for tag in &tags {
+            let row = adw::ActionRow::builder().title(tag.name()).build();
+            let remove_button = gtk::Button::builder()
+                .icon_name("window-close-symbolic")
+                .tooltip_text(gettext("Remove Tag"))
+                .valign(gtk::Align::Center)
+                .has_frame(false)
+                .build();
+            remove_button.connect_clicked(
+                glib::clone!(@weak self as obj, @strong tag => move |_| {
+                    remove_task_tag(obj.task().id(), tag.id()).expect("Failed to remove tag");
+                    obj.tags_changed();
+                }),
+            );
+            row.add_suffix(&remove_button);
+            imp.tags_expander_row.add_row(&row);
+            tag_rows.push(row);
+        }
+        let names = tags.iter().map(|tag| tag.name()).collect::<Vec<String>>();
+        imp.tags_expander_row.set_subtitle(&names.join(", "));
+        imp.tag_rows.replace(tag_rows);
+    }
+
+    fn tags_changed(&self) {
+        let task = self.task();
+        self.reset_tags(task.id());
+        self.activate_action("task.changed", Some(&task.to_variant()))
+            .unwrap();
+    }
+
+    fn reset_blockers(&self, task_id: i64) {
+        let imp = self.imp();
+        for row in imp.blocker_rows.take() {
+            imp.blockers_expander_row.remove(&row);
+        }
+
+        let blockers = read_blockers(task_id).expect("Failed to read tasks");
+        let mut blocker_rows = vec![];
+        for blocker in &blockers {
+            let row = adw::ActionRow::builder().title(blocker.name()).build();
+            if blocker.done() {
+                row.set_subtitle(&gettext("Done"));
+            }
+            let remove_button = gtk::Button::builder()
+                .icon_name("window-close-symbolic")
+                .tooltip_text(gettext("Remove Blocker"))
+                .valign(gtk::Align::Center)
+                .has_frame(false)
+                .build();
+            remove_button.connect_clicked(
+                glib::clone!(@weak self as obj, @strong blocker => move |_| {
+                    remove_task_blocker(obj.task().id(), blocker.id())
+                        .expect("Failed to remove blocker");
+                    obj.blockers_changed();
+                }),
+            );
+            row.add_suffix(&remove_button);
+            imp.blockers_expander_row.add_row(&row);
+            blocker_rows.push(row);
+        }
+        let names = blockers
+            .iter()
+            .filter(|blocker| !blocker.done())
+            .map(|blocker| blocker.name())
+            .collect::<Vec<String>>();
+        imp.blockers_expander_row.set_subtitle(&names.join(", "));
+        imp.blocker_rows.replace(blocker_rows);
+    }
+
+    fn blockers_changed(&self) {
+        let task = self.task();
+        self.reset_blockers(task.id());
+        self.activate_action("task.changed", Some(&task.to_variant()))
+            .unwrap();
+    }
+
+    fn move_relative_reminders(&self, task: &Task) {
+        let reminders =
+            update_relative_reminders(task.id(), task.date()).expect("Failed to update reminders");
+        let application = self
+            .root()
+            .and_downcast::<gtk::Window>()
+            .unwrap()
+            .application()
+            .and_downcast::<IPlanApplication>()
+            .unwrap();
+        for reminder in reminders {
+            application.send_reminder(reminder);
+        }
+        self.reset_reminders(task.id());
+    }
+
     fn new_reminder_row(&self, reminder: Reminder) -> ReminderRow {
         let row = ReminderRow::new(reminder);
         row.connect_closure(
@@ -176,7 +344,8 @@ impl TaskPage {
         row.connect_closure(
             "removed",
             true,
-            glib::closure_local!(@watch self as obj => move |_: ReminderRow, _: i64| {
+            glib::closure_local!(@watch self as obj => move |row: ReminderRow, _: i64| {
+                obj.imp().reminder_rows.borrow_mut().retain(|reminder_row| reminder_row != &row);
                 obj.activate_action("task.changed", Some(&obj.task().to_variant())).unwrap();
             }),
         );
@@ -207,6 +376,41 @@ impl TaskPage {
         let task = self.task();
         task.set_date(datetime.to_unix());
         update_task(&task).expect("Failed to change update task");
+        self.move_relative_reminders(&task);
+        self.activate_action("task.changed", Some(&task.to_variant()))
+            .unwrap();
+    }
+
+    #[template_callback]
+    fn handle_task_repeat_changed(&self, repeat: String, _: RepeatRow) {
+        let imp = self.imp();
+        let task = self.task();
+        // Repeat rule needs the task date as the first occurrence
+        if !repeat.is_empty() && task.date() == 0 {
+            let now = glib::DateTime::now_local().unwrap();
+            let date =
+                glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                    .unwrap();
+            imp.date_row.set_datetime(&date);
+            task.set_date(date.to_unix());
+            update_task(&task).expect("Failed to update task");
+            self.move_relative_reminders(&task);
+        }
+        task.set_repeat(repeat);
+        update_task(&task).expect("Failed to update task");
+        self.activate_action("task.changed", Some(&task.to_variant()))
+            .unwrap();
+    }
+
+    #[template_callback]
+    fn handle_task_estimate_changed(&self, time: i32, _: TimeRow) {
+        let task = self.task();
+        if task.estimate() == time as i64 {
+            return;
+        }
+        task.set_estimate(time as i64);
+        update_task(&task).expect("Failed to update task");
+        self.imp().task_row.refresh_timer();
         self.activate_action("task.changed", Some(&task.to_variant()))
             .unwrap();
     }
@@ -223,6 +427,119 @@ impl TaskPage {
         modal.present();
     }
 
+    #[template_callback]
+    fn handle_tags_popover_show(&self, _: gtk::Popover) {
+        let imp = self.imp();
+        while let Some(row) = imp.tags_list.first_child() {
+            imp.tags_list.remove(&row);
+        }
+
+        let task_tags = read_task_tags(self.task().id())
+            .expect("Failed to read tags")
+            .iter()
+            .map(|tag| tag.id())
+            .collect::<Vec<i64>>();
+        let other_tags = read_tags()
+            .expect("Failed to read tags")
+            .into_iter()
+            .filter(|tag| !task_tags.contains(&tag.id()))
+            .collect::<Vec<Tag>>();
+        for tag in &other_tags {
+            let label = gtk::Label::builder()
+                .label(tag.name())
+                .halign(gtk::Align::Start)
+                .build();
+            imp.tags_list.append(&label);
+        }
+        imp.tags_list.set_visible(!other_tags.is_empty());
+        imp.other_tags.replace(other_tags);
+    }
+
+    #[template_callback]
+    fn handle_tag_entry_activate(&self, entry: gtk::Entry) {
+        let name = entry.text().trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let tag = match find_tag(&name).expect("Failed to read tags") {
+            Some(tag) => tag,
+            None => create_tag(&name).expect("Failed to create tag"),
+        };
+        add_task_tag(self.task().id(), tag.id()).expect("Failed to add tag");
+        entry.set_text("");
+        self.imp().tags_popover.popdown();
+        self.tags_changed();
+    }
+
+    #[template_callback]
+    fn handle_tags_list_row_activated(&self, row: gtk::ListBoxRow, _: gtk::ListBox) {
+        let imp = self.imp();
+        let tag_id = imp.other_tags.borrow()[row.index() as usize].id();
+        add_task_tag(self.task().id(), tag_id).expect("Failed to add tag");
+        imp.tags_popover.popdown();
+        self.tags_changed();
+    }
+
+    #[template_callback]
+    fn handle_blockers_popover_show(&self, _: gtk::Popover) {
+        let entry = &self.imp().blocker_search_entry;
+        entry.set_text("");
+        self.handle_blocker_search_changed(entry.get());
+    }
+
+    #[template_callback]
+    fn handle_blocker_search_changed(&self, entry: gtk::SearchEntry) {
+        let imp = self.imp();
+        while let Some(row) = imp.blocker_candidates_list.first_child() {
+            imp.blocker_candidates_list.remove(&row);
+        }
+
+        let text = entry.text();
+        if text.trim().is_empty() {
+            imp.blocker_candidates_list.set_visible(false);
+            imp.blocker_candidates.replace(vec![]);
+            return;
+        }
+
+        let task_id = self.task().id();
+        let blockers = read_blockers(task_id)
+            .expect("Failed to read tasks")
+            .iter()
+            .map(|blocker| blocker.id())
+            .collect::<Vec<i64>>();
+        // Tasks that depend on this task can't block it
+        let candidates = find_tasks(text.trim(), false)
+            .expect("Failed to search tasks")
+            .into_iter()
+            .filter(|task| !blockers.contains(&task.id()))
+            .filter(|task| {
+                !dependency_has_cycle(task_id, task.id()).expect("Failed to read dependencies")
+            })
+            .take(8)
+            .collect::<Vec<Task>>();
+        for task in &candidates {
+            let label = gtk::Label::builder()
+                .label(task.name())
+                .halign(gtk::Align::Start)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .max_width_chars(32)
+                .build();
+            imp.blocker_candidates_list.append(&label);
+        }
+        imp.blocker_candidates_list
+            .set_visible(!candidates.is_empty());
+        imp.blocker_candidates.replace(candidates);
+    }
+
+    #[template_callback]
+    fn handle_blocker_candidates_list_row_activated(&self, row: gtk::ListBoxRow, _: gtk::ListBox) {
+        let imp = self.imp();
+        let blocker_id = imp.blocker_candidates.borrow()[row.index() as usize].id();
+        add_task_blocker(self.task().id(), blocker_id).expect("Failed to add blocker");
+        imp.blockers_popover.popdown();
+        self.blockers_changed();
+    }
+
     #[template_callback]
     fn handle_description_buffer_changed(&self, buffer: gtk::TextBuffer) {
         let imp = self.imp();
@@ -302,7 +619,6 @@ impl TaskPage {
 
     #[template_callback]
     fn subtask_activated(&self, subtask_row: TaskRow, _tasks_box: gtk::ListBox) {
-        subtask_row.cancel_timer();
         self.activate_action("subtask.open", Some(&subtask_row.task().id().to_variant()))
             .expect("Failed to send subtask.open action");
     }
@@ -3,26 +3,18 @@ use gtk::glib::{self, Properties};
 use gtk::{gdk, prelude::*, subclass::prelude::*};
 use linkify::{LinkFinder, LinkKind};
 use std::cell::{Cell, RefCell};
-use std::thread;
-use std::time::{Duration, SystemTime};
 
+use crate::application::IPlanApplication;
 use crate::db::models::{Record, Task};
 use crate::db::operations::{
-    create_record, delete_task, read_project, read_reminders, read_subtasks_summary, read_task,
-    update_record, update_task,
+    create_next_occurrence, read_blockers, read_project, read_reminders, read_subtasks_summary,
+    read_task, read_task_tags, revert_next_occurrence, seal_history, trash_task, update_task,
 };
+use crate::timer::Timer;
 use crate::views::snippets::MenuItem;
 use crate::views::task::{SubtaskRow, TaskWindow, TasksDoneWindow};
 use crate::views::IPlanWindow;
 
-#[derive(Default, PartialEq, Clone, Copy)]
-pub enum TimerStatus {
-    On,
-    #[default]
-    Off,
-    Cancel,
-}
-
 pub struct DragBackup {
     position: i32,
     section: i64,
@@ -65,7 +57,6 @@ mod imp {
         pub name_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub name_entry: TemplateChild<gtk::Entry>,
-        pub timer_status: Cell<TimerStatus>,
         #[template_child]
         pub timer_button: TemplateChild<MenuItem>,
         #[template_child]
@@ -81,6 +72,14 @@ mod imp {
         #[template_child]
         pub move_down_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub priority_none_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub priority_low_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub priority_medium_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub priority_high_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
         pub description: TemplateChild<gtk::Label>,
         #[template_child]
         pub body: TemplateChild<gtk::Box>,
@@ -93,8 +92,20 @@ mod imp {
         #[template_child]
         pub date_indicator: TemplateChild<gtk::Label>,
         #[template_child]
+        pub repeat_indicator: TemplateChild<gtk::Image>,
+        #[template_child]
         pub reminders_indicator: TemplateChild<gtk::Image>,
         #[template_child]
+        pub blocked_indicator: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub estimate_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub estimate_level: TemplateChild<gtk::LevelBar>,
+        #[template_child]
+        pub estimate_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub tags_box: TemplateChild<gtk::Box>,
+        #[template_child]
         pub project_label: TemplateChild<gtk::Label>,
     }
 
@@ -108,6 +119,27 @@ mod imp {
             MenuItem::ensure_type();
             klass.bind_template();
             klass.bind_template_instance_callbacks();
+            klass.install_action("task.set-priority", Some("y"), move |obj, _, value| {
+                let priority: u8 = value.unwrap().get().unwrap();
+                obj.set_priority(priority);
+            });
+            // Ctrl+1 for high priority to Ctrl+3 for low and Ctrl+0 for none
+            for (key, priority) in [
+                (gdk::Key::_0, 0u8),
+                (gdk::Key::_1, 3),
+                (gdk::Key::_2, 2),
+                (gdk::Key::_3, 1),
+            ] {
+                let shortcut = gtk::Shortcut::builder()
+                    .trigger(&gtk::KeyvalTrigger::new(
+                        key,
+                        gdk::ModifierType::CONTROL_MASK,
+                    ))
+                    .action(&gtk::NamedAction::new("task.set-priority"))
+                    .arguments(&priority.to_variant())
+                    .build();
+                klass.add_shortcut(&shortcut);
+            }
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -122,6 +154,12 @@ mod imp {
             let imp = obj.imp();
 
             obj.add_bindings();
+            obj.connect_timer();
+
+            // One color for the progress, the overrun class changes it
+            for offset in ["low", "high", "full"] {
+                imp.estimate_level.remove_offset_value(Some(offset));
+            }
 
             // Cancel name entry on Escape key pressed
             let name_entry_controller = gtk::EventControllerKey::new();
@@ -175,7 +213,7 @@ impl TaskRow {
         obj.set_compact(compact);
         obj.set_visible_project_label(visible_project_label);
         obj.reset(task);
-        obj.reset_timer();
+        obj.refresh_timer();
         obj.set_draggable(true);
         obj
     }
@@ -183,6 +221,22 @@ impl TaskRow {
     pub fn reset(&self, task: Task) {
         let imp = self.imp();
 
+        for css_class in ["priority-low", "priority-medium", "priority-high"] {
+            imp.checkbox.remove_css_class(css_class);
+        }
+        let (css_class, priority_button) = match task.priority() {
+            1 => (Some("priority-low"), &imp.priority_low_button),
+            2 => (Some("priority-medium"), &imp.priority_medium_button),
+            3 => (Some("priority-high"), &imp.priority_high_button),
+            _ => (None, &imp.priority_none_button),
+        };
+        if let Some(css_class) = css_class {
+            imp.checkbox.add_css_class(css_class);
+        }
+        imp.checkbox
+            .set_tooltip_text(task.priority_display().as_deref());
+        priority_button.set_active(true);
+
         if self.compact() {
             self.remove_css_class("card");
             self.set_margin_bottom(0);
@@ -221,6 +275,14 @@ impl TaskRow {
                 imp.date_indicator.set_visible(false);
             }
 
+            if let Some(repeat) = task.repeat_rule() {
+                imp.repeat_indicator
+                    .set_tooltip_text(Some(&repeat.display()));
+                imp.repeat_indicator.set_visible(true);
+            } else {
+                imp.repeat_indicator.set_visible(false);
+            }
+
             let reminders = read_reminders(Some(task.id())).unwrap();
             if reminders.is_empty() {
                 imp.reminders_indicator.set_visible(false);
@@ -228,6 +290,33 @@ impl TaskRow {
                 imp.reminders_indicator.set_visible(true);
             }
 
+            let blockers = read_blockers(task.id()).unwrap();
+            let undone_blockers = blockers
+                .iter()
+                .filter(|blocker| !blocker.done())
+                .map(|blocker| blocker.name())
+                .collect::<Vec<String>>();
+            if undone_blockers.is_empty() {
+                imp.blocked_indicator.set_visible(false);
+            } else {
+                imp.blocked_indicator.set_tooltip_text(Some(
+                    &gettext("Blocked by {}").replace("{}", &undone_blockers.join(", ")),
+                ));
+                imp.blocked_indicator.set_visible(true);
+            }
+
+            while let Some(chip) = imp.tags_box.first_child() {
+                imp.tags_box.remove(&chip);
+            }
+            let tags = read_task_tags(task.id()).unwrap();
+            for tag in &tags {
+                let chip = gtk::Label::new(Some(&tag.name()));
+                chip.add_css_class("caption");
+                chip.add_css_class("tag");
+                imp.tags_box.append(&chip);
+            }
+            imp.tags_box.set_visible(!tags.is_empty());
+
             if self.visible_project_label() {
                 let mut project_id = task.project();
                 let mut parent = task.parent();
@@ -251,7 +340,10 @@ impl TaskRow {
             }
 
             if !imp.date_indicator.get_visible()
+                && !imp.repeat_indicator.get_visible()
                 && !imp.reminders_indicator.get_visible()
+                && !imp.blocked_indicator.get_visible()
+                && !imp.tags_box.get_visible()
                 && !imp.project_label.get_visible()
             {
                 imp.footer.set_visible(false);
@@ -268,6 +360,21 @@ impl TaskRow {
         }
     }
 
+    pub fn set_priority(&self, priority: u8) {
+        let task = self.task();
+        if task.priority() == priority {
+            return;
+        }
+        task.set_priority(priority);
+        update_task(&task).expect("Failed to update task");
+        self.imp().options_popover.popdown();
+        self.reset(task.clone());
+        // Sort again by the new priority
+        self.changed();
+        self.activate_action("task.changed", Some(&task.to_variant()))
+            .unwrap();
+    }
+
     pub fn reset_subtasks(&self) {
         let imp = self.imp();
         let task = self.task();
@@ -286,6 +393,8 @@ impl TaskRow {
                 imp.subtasks.append(&subtask_row);
             }
         }
+        // The estimate sums the subtasks
+        self.refresh_estimate();
     }
 
     pub fn add_subtask(&self, subtask: Task) {
@@ -295,30 +404,28 @@ impl TaskRow {
         imp.subtasks.prepend(&subtask_row);
     }
 
-    pub fn cancel_timer(&self) {
-        self.imp().timer_status.set(TimerStatus::Cancel);
-        self.refresh_timer(); // FIXME: restore previous label
-    }
-
-    pub fn reset_timer(&self) {
+    pub fn refresh_timer(&self) {
         let imp = self.imp();
-        let task = self.task();
-        imp.timer_status.set(TimerStatus::Cancel); // FIXME: Check for removing this
-        if let Some(record) = task.incomplete_record() {
-            record.set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
-            self.start_timer(record);
+        let button: &MenuItem = imp.timer_button.as_ref();
+        let task_id = self.task().id();
+        let timer = IPlanApplication::default().timer();
+
+        if timer.is_running(task_id) {
+            button.add_css_class("destructive-action");
+            button.remove_css_class("flat");
         } else {
-            self.refresh_timer();
+            button.remove_css_class("destructive-action");
+            button.add_css_class("flat");
         }
-    }
-
-    pub fn refresh_timer(&self) {
-        let imp = self.imp();
 
-        if imp.timer_status.get() == TimerStatus::On {
+        if let Some(label) = timer.label(task_id) {
+            button.set_label(label);
+            self.move_timer_button(true);
+            self.refresh_estimate();
             return;
         }
 
+        self.refresh_estimate();
         let duration = self.task().duration();
         if duration == 0 {
             imp.timer_button.set_label(gettext("Start _Timer"));
@@ -330,6 +437,48 @@ impl TaskRow {
         }
     }
 
+    /// Tracked time over the estimate of the task tree
+    pub fn refresh_estimate(&self) {
+        let imp = self.imp();
+        let task = self.task();
+        let estimate = if self.compact() {
+            0
+        } else {
+            task.total_estimate()
+        };
+        if estimate == 0 {
+            imp.estimate_box.set_visible(false);
+            return;
+        }
+
+        let mut duration = task.duration();
+        if let Some(record) = IPlanApplication::default().timer().record(task.id()) {
+            duration += record.duration();
+        }
+        imp.estimate_level
+            .set_value((duration as f64 / estimate as f64).min(1.0));
+        imp.estimate_label.set_label(&format!(
+            "{} / {}",
+            Record::duration_display(duration),
+            Record::duration_display(estimate)
+        ));
+        if duration > estimate {
+            imp.estimate_level.add_css_class("overrun");
+            imp.estimate_label.add_css_class("warning");
+            imp.estimate_box.set_tooltip_text(Some(
+                &gettext("Over the estimate by {}")
+                    .replace("{}", &Record::duration_display(duration - estimate)),
+            ));
+        } else {
+            imp.estimate_level.remove_css_class("overrun");
+            imp.estimate_label.remove_css_class("warning");
+            imp.estimate_box
+                .set_tooltip_text(Some(&gettext("Tracked time over the estimate")));
+        }
+        imp.estimate_box.set_visible(true);
+        imp.footer.set_visible(true);
+    }
+
     pub fn keep_after_dnd(&self) {
         self.set_moving_out(false);
         let drag_backup = self.imp().drag_backup.take().unwrap();
@@ -366,11 +515,35 @@ impl TaskRow {
                 let task = obj.task();
                 task.set_done(active);
                 update_task(&task).expect("Failed to update task");
+                let application = obj
+                    .root()
+                    .and_downcast::<gtk::Window>()
+                    .unwrap()
+                    .application()
+                    .and_downcast::<IPlanApplication>()
+                    .unwrap();
                 if active {
-                    imp.timer_status.set(TimerStatus::Off);
+                    application
+                        .timer()
+                        .stop(&task)
+                        .expect("Failed to stop timer");
+                    if let Some(next_task) =
+                        create_next_occurrence(&task).expect("Failed to create next occurrence")
+                    {
+                        for reminder in read_reminders(Some(next_task.id())).unwrap() {
+                            application.send_reminder(reminder);
+                        }
+                        obj.activate_action("task.changed", Some(&next_task.to_variant()))
+                            .unwrap();
+                    }
+                } else if let Some(next_task) =
+                    revert_next_occurrence(&task).expect("Failed to revert next occurrence")
+                {
+                    application.remove_task_row(&next_task);
                 }
                 obj.activate_action("task.changed", Some(&task.to_variant()))
                     .unwrap();
+                application.refresh_dependents(&task);
                 Some(active)
             })
             .sync_create()
@@ -459,81 +632,54 @@ impl TaskRow {
         }
     }
 
-    pub fn start_timer(&self, record: Record) {
-        let imp = self.imp();
-
-        if imp.timer_status.get() == TimerStatus::On {
-            return;
-        }
-
-        let button: &MenuItem = imp.timer_button.as_ref();
-        imp.timer_status.set(TimerStatus::On);
-        button.add_css_class("destructive-action");
-        button.remove_css_class("flat");
-        self.move_timer_button(true);
-
-        let (tx, rx) = glib::MainContext::channel(glib::Priority::DEFAULT);
-        let duration = Duration::from_secs(record.duration() as u64);
-        let start = SystemTime::now().checked_sub(duration).unwrap();
-        thread::spawn(move || loop {
-            if tx.send(start.elapsed().unwrap().as_secs()).is_err() {
-                break;
-            }
-            thread::sleep(Duration::from_secs_f32(0.3));
-        });
-        rx.attach(None,glib::clone!(@weak button, @weak self as obj => @default-return glib::ControlFlow::Break,
-            move |duration| {
+    /// Follow the timer of the task from the application timer
+    fn connect_timer(&self) {
+        let timer = IPlanApplication::default().timer();
+        timer.connect_closure(
+            "tick",
+            false,
+            glib::closure_local!(@watch self as obj => move |timer: Timer| {
                 let imp = obj.imp();
-                match imp.timer_status.get() {
-                    TimerStatus::On => {
-                        button.set_label(
-                            Record::duration_display(duration as i64)
-                        );
-                        glib::ControlFlow::Continue
-                    },
-                    TimerStatus::Off => {
-                        button.remove_css_class("destructive-action");
-                        button.add_css_class("flat");
-                        record.set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
-                        update_record(&record).expect("Failed to update record");
-                        let task = obj.task();
-                        imp.timer_button.set_label(task.duration_display());
-                        if obj.parent().is_some() {
-                            obj.activate_action("timer.stop", Some(&task.to_variant())).unwrap();
-                            obj.activate_action("task.duration-changed", Some(&task.to_variant())).unwrap();
-                        }
-                        glib::ControlFlow::Break
-                    },
-                    TimerStatus::Cancel => {
-                        button.remove_css_class("destructive-action");
-                        button.add_css_class("flat");
-                        glib::ControlFlow::Break
+                if let Some(label) = timer.label(obj.task().id()) {
+                    if imp.timer_button.parent().as_ref() == Some(imp.header.upcast_ref::<gtk::Widget>()) {
+                        imp.timer_button.set_label(label);
+                        obj.refresh_estimate();
+                    } else {
+                        obj.refresh_timer();
                     }
                 }
-            }
-            ),
+            }),
+        );
+        for signal in ["started", "stopped"] {
+            timer.connect_closure(
+                signal,
+                false,
+                glib::closure_local!(@watch self as obj => move |_: Timer, task: Task, _: Record| {
+                    if task.id() == obj.task().id() {
+                        obj.refresh_timer();
+                    }
+                }),
+            );
+        }
+        timer.connect_closure(
+            "pomodoro-finished",
+            false,
+            glib::closure_local!(@watch self as obj => move |_: Timer, task: Task, _: bool| {
+                if task.id() == obj.task().id() {
+                    obj.refresh_timer();
+                }
+            }),
         );
     }
 
     #[template_callback]
     fn handle_timer_button_clicked(&self, _button: &gtk::Button) {
         let task = self.task();
-        if self.imp().timer_status.get() != TimerStatus::On {
-            let record = task.incomplete_record().unwrap_or_else(|| {
-                create_record(glib::DateTime::now_local().unwrap().to_unix(), task.id(), 0).unwrap()
-            });
-            let record_variant = record.to_variant();
-            self.start_timer(record);
-            self.activate_action(
-                "timer.start",
-                Some(&glib::Variant::from((
-                    self.task().to_variant(),
-                    record_variant,
-                ))),
-            )
-            .unwrap();
+        let timer = IPlanApplication::default().timer();
+        if timer.is_running(task.id()) {
+            timer.stop(&task).expect("Failed to stop timer");
         } else {
-            self.imp().timer_status.set(TimerStatus::Off);
+            timer.start(&task).expect("Failed to start timer");
         }
     }
 
@@ -563,7 +709,12 @@ impl TaskRow {
             move |_toast| {
                 let task = obj.task();
                 if task.suspended() {    // Checking Undo button
-                    delete_task(task.id()).unwrap();
+                    trash_task(task.id()).unwrap();
+                    seal_history().expect("Failed to update history");
+                    IPlanApplication::default()
+                        .timer()
+                        .remove_trashed_records()
+                        .expect("Failed to stop timer");
                 }
             }
         ));
@@ -4,7 +4,7 @@ use std::cell::Cell;
 use std::unimplemented;
 
 use crate::application::IPlanApplication;
-use crate::db::models::{Record, Task};
+use crate::db::models::Task;
 use crate::db::operations::read_task;
 use crate::views::task::{TaskPage, TasksDoneWindow};
 use crate::views::{ActionScope, IPlanWindow};
@@ -61,40 +61,6 @@ mod imp {
                     obj.emit_by_name::<()>("task-changed", &[&task]);
                 },
             );
-            klass.install_action(
-                "timer.start",
-                Some(&format!(
-                    "({}{})",
-                    Task::static_variant_type().as_str(),
-                    Record::static_variant_type().as_str()
-                )),
-                move |obj, _, value| {
-                    let app = obj
-                        .application()
-                        .and_downcast::<IPlanApplication>()
-                        .unwrap();
-                    let main_win = app
-                        .window_by_name("IPlanWindow")
-                        .and_downcast::<IPlanWindow>()
-                        .unwrap();
-                    main_win.activate_action("timer.start", value).unwrap();
-                },
-            );
-            klass.install_action(
-                "timer.stop",
-                Some(Task::static_variant_type().as_str()),
-                move |obj, _, value| {
-                    let app = obj
-                        .application()
-                        .and_downcast::<IPlanApplication>()
-                        .unwrap();
-                    let main_win = app
-                        .window_by_name("IPlanWindow")
-                        .and_downcast::<IPlanWindow>()
-                        .unwrap();
-                    main_win.activate_action("timer.stop", value).unwrap();
-                },
-            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -222,6 +188,28 @@ impl TaskWindow {
         imp.toast_overlay.add_toast(toast);
     }
 
+    /// Open the visible page again when the task or its subtask changed from outside
+    pub fn reset_task(&self, task_id: i64) {
+        let imp = self.imp();
+        let page = self.visible_page();
+        let page_task_id = page.task().id();
+        let is_subtask = page.imp().subtasks_box.item_by_id(task_id).is_some()
+            || read_task(task_id).is_ok_and(|task| task.parent() == page_task_id);
+        if task_id != page_task_id && !is_subtask {
+            return;
+        }
+
+        if let Ok(task) = read_task(page_task_id) {
+            let name = page_task_id.to_string();
+            imp.task_pages_stack.remove(&page);
+            imp.task_pages_stack
+                .add_named(&TaskPage::new(task), Some(&name));
+            imp.task_pages_stack.set_visible_child_name(&name);
+        } else {
+            self.close();
+        }
+    }
+
     fn visible_page(&self) -> TaskPage {
         self.imp()
             .task_pages_stack
@@ -275,7 +263,7 @@ impl TaskWindow {
         imp.task_pages_stack.remove(&from_page);
         if let Some(task_row) = target_page.imp().subtasks_box.item_by_id(from_task.id()) {
             task_row.reset(from_task);
-            task_row.reset_timer();
+            task_row.refresh_timer();
             task_row.changed();
         }
         let task = target_page.task();
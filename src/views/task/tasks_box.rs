@@ -8,7 +8,8 @@ use std::time::Duration;
 
 use crate::db::models::Task;
 use crate::db::operations::{
-    create_task, new_subtask_position, new_task_position, read_task, update_task,
+    add_task_tag, create_task, new_subtask_position, new_task_position, read_task, seal_history,
+    update_task,
 };
 use crate::views::task::TaskRow;
 
@@ -17,6 +18,10 @@ pub enum TasksBoxWrapper {
     Section(i64, i64),
     Task(i64, i64),
     Date(i64),
+    /// Tasks with the tag from all projects
+    Tag(i64),
+    /// Tasks of a smart view or saved filter with the date for the new tasks
+    Filter(i64),
 }
 
 mod imp {
@@ -34,6 +39,8 @@ mod imp {
         pub scroll: Cell<i8>,
         #[property(get, set)]
         pub hscroll_controller: RefCell<Option<gtk::EventControllerScroll>>,
+        #[property(get, set = Self::set_priority_sort, name = "priority-sort")]
+        pub priority_sort: Cell<bool>,
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
         #[template_child]
@@ -72,6 +79,7 @@ mod imp {
                 scrollable: Cell::new(true),
                 scroll: Cell::new(0),
                 hscroll_controller: RefCell::new(None),
+                priority_sort: Cell::new(false),
                 scrolled_window: gtk::TemplateChild::default(),
                 items_box: gtk::TemplateChild::default(),
                 bottom_add_task: gtk::TemplateChild::default(),
@@ -158,6 +166,18 @@ mod imp {
             self.scrolled_window.set_vscrollbar_policy(policy_type);
             self.scrollable.set(scrollable);
         }
+
+        pub fn set_priority_sort(&self, priority_sort: bool) {
+            self.priority_sort.set(priority_sort);
+            // Manual moving is only for position order
+            let items = self.items_box.observe_children();
+            for i in 0..items.n_items() {
+                if let Some(row) = items.item(i).and_downcast::<TaskRow>() {
+                    row.set_hide_move_arrows(priority_sort);
+                }
+            }
+            self.items_box.invalidate_sort();
+        }
     }
 }
 
@@ -319,14 +339,22 @@ impl TasksBox {
             ]))
             .unwrap(),
             TasksBoxWrapper::Date(date) => create_task(Task::new(&[("date", &date)])).unwrap(),
+            TasksBoxWrapper::Tag(id) => {
+                let task = create_task(Task::new(&[])).unwrap();
+                add_task_tag(task.id(), id).unwrap();
+                task
+            }
+            TasksBoxWrapper::Filter(date) => create_task(Task::new(&[("date", &date)])).unwrap(),
         }
     }
 
     fn create_task_row(&self, task: Task) -> TaskRow {
-        let visible_project_label =
-            matches!(self.items_wrapper().unwrap(), TasksBoxWrapper::Date(_));
+        let visible_project_label = matches!(
+            self.items_wrapper().unwrap(),
+            TasksBoxWrapper::Date(_) | TasksBoxWrapper::Tag(_) | TasksBoxWrapper::Filter(_)
+        );
         let row = TaskRow::new(task, false, visible_project_label);
-        if let TasksBoxWrapper::Date(_) = self.items_wrapper().unwrap() {
+        if visible_project_label || self.priority_sort() {
             row.set_hide_move_arrows(true);
         }
         row
@@ -336,7 +364,7 @@ impl TasksBox {
         let imp = self.imp();
 
         imp.items_box.set_sort_func(glib::clone!(@weak self as obj => @default-return gtk::Ordering::Larger, move |row1, row2| {
-            if let Some(TasksBoxWrapper::Date(_)) = obj.items_wrapper() {
+            if let Some(TasksBoxWrapper::Date(_) | TasksBoxWrapper::Tag(_) | TasksBoxWrapper::Filter(_)) = obj.items_wrapper() {
                 return gtk::Ordering::Larger;
             }
             let row1 = if let Some(row1) = row1.downcast_ref::<TaskRow>() {
@@ -350,7 +378,16 @@ impl TasksBox {
                 return gtk::Ordering::Smaller;
             };
 
-            if row1.task().position() < row2.task().position() {
+            let (task1, task2) = (row1.task(), row2.task());
+            if obj.priority_sort() && task1.priority() != task2.priority() {
+                return if task1.priority() > task2.priority() {
+                    gtk::Ordering::Smaller
+                } else {
+                    gtk::Ordering::Larger
+                };
+            }
+
+            if task1.position() < task2.position() {
                 gtk::Ordering::Larger
             } else {
                 gtk::Ordering::Smaller
@@ -478,7 +515,9 @@ impl TasksBox {
         let task_db = read_task(task.id()).unwrap();
         let items_wrapper = self.items_wrapper().unwrap();
 
-        if let TasksBoxWrapper::Date(_) = items_wrapper {
+        if let TasksBoxWrapper::Date(_) | TasksBoxWrapper::Tag(_) | TasksBoxWrapper::Filter(_) =
+            items_wrapper
+        {
             update_task(&task).unwrap();
         } else if row.moving_out() {
             let task_parent = task.parent();
@@ -488,7 +527,7 @@ impl TasksBox {
             imp.items_box.remove(&row);
             if let Some(parent_row) = self.item_by_id(task_parent) {
                 parent_row.add_subtask(task);
-                parent_row.reset_timer();
+                parent_row.refresh_timer();
                 parent_row.imp().subtask_drop_target.set_visible(false);
             }
         } else if task_db.position() != task.position() || task_db.section() != task.section() {
@@ -520,7 +559,12 @@ impl TasksBox {
             return gdk::DragAction::empty();
         };
 
-        if let TasksBoxWrapper::Date(_) = self.items_wrapper().unwrap() {
+        if let TasksBoxWrapper::Date(_) | TasksBoxWrapper::Tag(_) | TasksBoxWrapper::Filter(_) =
+            self.items_wrapper().unwrap()
+        {
+            return gdk::DragAction::MOVE;
+        }
+        if self.priority_sort() {
             return gdk::DragAction::MOVE;
         }
 
@@ -609,6 +653,7 @@ impl TasksBox {
             TasksBoxWrapper::Section(id, _) => row.task().section() == id,
             TasksBoxWrapper::Task(id, _) => row.task().parent() == id,
             TasksBoxWrapper::Date(date) => row.task().date() == date,
+            TasksBoxWrapper::Tag(_) | TasksBoxWrapper::Filter(_) => true,
         };
         // Check moving_out to Avoid running at drag start
         if is_same_box && row.moving_out() {
@@ -677,6 +722,7 @@ impl TasksBox {
         item_task.set_position(target_item_task_position);
         self.imp().items_box.invalidate_sort();
         update_task(&item_task).unwrap();
+        seal_history().expect("Failed to update history");
     }
 
     fn start_scroll(&self) {
@@ -713,6 +759,7 @@ impl TasksBox {
     #[template_callback]
     fn new_task(&self, _button: gtk::Button) {
         let task = self.create_empty_task();
+        seal_history().expect("Failed to update history");
         let task_row = self.create_task_row(task);
         let imp = self.imp();
         imp.items_box.prepend(&task_row);
@@ -736,6 +783,7 @@ impl TasksBox {
         let task = self.create_empty_task();
         task.set_position(0);
         update_task(&task).unwrap();
+        seal_history().expect("Failed to update history");
 
         if let TasksBoxWrapper::Task(_, _) = self.items_wrapper().unwrap() {
             self.activate_action("task.changed", Some(&task.to_variant()))
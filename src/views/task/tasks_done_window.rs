@@ -3,12 +3,48 @@ use gettextrs::gettext;
 use glib::{once_cell::sync::Lazy, subclass::Signal};
 use gtk::{glib, glib::Properties};
 use std::cell::RefCell;
+use std::collections::HashMap;
 
 use crate::db::models::{Section, Task};
-use crate::db::operations::{read_task, read_tasks};
+use crate::db::operations::{read_done_tasks, read_task};
 use crate::views::task::{TaskRow, TaskWindow};
 use crate::views::{ActionScope, IPlanWindow};
 
+/// Periods of the completion time filter, in the order of the dropdown items
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompletionPeriod {
+    AnyTime,
+    Today,
+    Week,
+    Month,
+}
+
+impl CompletionPeriod {
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            1 => Self::Today,
+            2 => Self::Week,
+            3 => Self::Month,
+            _ => Self::AnyTime,
+        }
+    }
+
+    /// Unix time of the period start, the week and month are the last 7 and 30 days
+    pub fn start(&self) -> i64 {
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap();
+        let days = match self {
+            Self::AnyTime => return 0,
+            Self::Today => 0,
+            Self::Week => 6,
+            Self::Month => 29,
+        };
+        today.add_days(-days).unwrap().to_unix()
+    }
+}
+
 mod imp {
     use super::*;
 
@@ -21,7 +57,11 @@ mod imp {
         #[template_child]
         pub name_label: TemplateChild<gtk::Label>,
         #[template_child]
+        pub period_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
         pub tasks_box: TemplateChild<gtk::ListBox>,
+        /// Completion time of the tasks by id
+        pub completed: RefCell<HashMap<i64, i64>>,
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
         #[template_child]
@@ -108,35 +148,38 @@ impl TasksDoneWindow {
         win.set_transient_for(Some(app_window));
         let imp = win.imp();
         imp.name_label.set_label(&gettext("Done Tasks"));
-        let tasks = read_tasks(
-            Some(section.project()),
-            Some(section.id()),
-            Some(true),
-            Some(0),
-            None,
-            false,
-        )
-        .unwrap();
-        for task in tasks {
+        let tasks = read_done_tasks(section.id()).unwrap();
+        for (task, completed_at) in tasks {
+            imp.completed.borrow_mut().insert(task.id(), completed_at);
             let task_row = TaskRow::new(task, false, false);
             imp.tasks_box.append(&task_row);
         }
-        imp.tasks_box.set_sort_func(|row1, row2| {
-            let row1_p = row1.property::<Task>("task").position();
-            let row2_p = row2.property::<Task>("task").position();
+        // Latest completed first, the position for the tasks done at the same time
+        imp.tasks_box.set_sort_func(glib::clone!(
+        @weak imp => @default-return gtk::Ordering::Equal,
+        move |row1, row2| {
+            let task1 = row1.property::<Task>("task");
+            let task2 = row2.property::<Task>("task");
+            let completed = imp.completed.borrow();
+            let row1_c = completed.get(&task1.id()).copied().unwrap_or_default();
+            let row2_c = completed.get(&task2.id()).copied().unwrap_or_default();
 
-            if row1_p < row2_p {
-                gtk::Ordering::Larger
-            } else {
-                gtk::Ordering::Smaller
-            }
-        });
+            (row2_c, task2.position()).cmp(&(row1_c, task1.position())).into()
+        }));
 
         imp.tasks_box.set_filter_func(glib::clone!(
         @weak imp => @default-return false,
         move |row| {
             let row = row.downcast_ref::<TaskRow>().unwrap();
-            if row.task().suspended() {
+            let period_start =
+                CompletionPeriod::from_index(imp.period_dropdown.selected()).start();
+            let completed_at = imp
+                .completed
+                .borrow()
+                .get(&row.task().id())
+                .copied()
+                .unwrap_or_default();
+            if row.task().suspended() || completed_at < period_start {
                 false
             } else {
                 !row.imp().moving_out.get()
@@ -208,6 +251,11 @@ impl TasksDoneWindow {
         );
     }
 
+    #[template_callback]
+    fn handle_period_selected(&self, _: glib::ParamSpec, _: gtk::DropDown) {
+        self.imp().tasks_box.invalidate_filter();
+    }
+
     #[template_callback]
     fn handle_tasks_box_row_activated(&self, row: gtk::ListBoxRow, _tasks_box: gtk::ListBox) {
         let obj = self.root().and_downcast::<gtk::Window>().unwrap();
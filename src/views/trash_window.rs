@@ -0,0 +1,188 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib;
+use std::cell::RefCell;
+
+use crate::db::operations::{
+    delete_trash_item, empty_trash, read_task, read_trash, restore_trash_item, seal_history,
+    TrashItem,
+};
+use crate::IPlanApplication;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/trash_window.ui")]
+    pub struct TrashWindow {
+        #[template_child]
+        pub empty_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub retention_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub items_box: TemplateChild<gtk::ListBox>,
+        pub items: RefCell<Vec<TrashItem>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TrashWindow {
+        const NAME: &'static str = "TrashWindow";
+        type Type = super::TrashWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TrashWindow {}
+    impl WidgetImpl for TrashWindow {}
+    impl WindowImpl for TrashWindow {}
+    impl AdwWindowImpl for TrashWindow {}
+}
+
+glib::wrapper! {
+    pub struct TrashWindow(ObjectSubclass<imp::TrashWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+#[gtk::template_callbacks]
+impl TrashWindow {
+    pub fn new(application: &IPlanApplication, app_window: &gtk::Window) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        let retention = application.settings().unwrap().int("trash-retention");
+        win.imp().retention_label.set_label(
+            &gettext("Items are deleted permanently after {} days in the trash")
+                .replace("{}", &retention.to_string()),
+        );
+        win.refresh();
+        win
+    }
+
+    fn refresh(&self) {
+        let imp = self.imp();
+        while let Some(row) = imp.items_box.first_child() {
+            imp.items_box.remove(&row);
+        }
+        let items = read_trash().expect("Failed to read trash");
+        for (index, (item, deleted_at)) in items.iter().enumerate() {
+            imp.items_box
+                .append(&self.create_row(index, item, *deleted_at));
+        }
+        imp.empty_button.set_sensitive(!items.is_empty());
+        imp.items
+            .replace(items.into_iter().map(|(item, _)| item).collect());
+    }
+
+    fn create_row(&self, index: usize, item: &TrashItem, deleted_at: i64) -> adw::ActionRow {
+        let kind = match item {
+            TrashItem::Project(_) => gettext("Project"),
+            TrashItem::Section(_) => gettext("Section"),
+            TrashItem::Task(_) => gettext("Task"),
+        };
+        let deleted_at = glib::DateTime::from_unix_local(deleted_at)
+            .unwrap()
+            .format("%B %e, %R")
+            .unwrap();
+        let row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&item.name()))
+            .subtitle(format!("{kind} · {deleted_at}"))
+            .build();
+
+        let restore_button = gtk::Button::builder()
+            .icon_name("edit-undo-symbolic")
+            .tooltip_text(gettext("Restore"))
+            .valign(gtk::Align::Center)
+            .build();
+        restore_button.add_css_class("flat");
+        restore_button.connect_clicked(glib::clone!(@weak self as obj => move |_| {
+            obj.restore_item(index);
+        }));
+        row.add_suffix(&restore_button);
+
+        let delete_button = gtk::Button::builder()
+            .icon_name("user-trash-symbolic")
+            .tooltip_text(gettext("Delete Permanently"))
+            .valign(gtk::Align::Center)
+            .build();
+        delete_button.add_css_class("flat");
+        delete_button.connect_clicked(glib::clone!(@weak self as obj => move |_| {
+            obj.delete_item(index);
+        }));
+        row.add_suffix(&delete_button);
+        row
+    }
+
+    fn restore_item(&self, index: usize) {
+        let application = IPlanApplication::default();
+        {
+            let items = self.imp().items.borrow();
+            let item = &items[index];
+            let task_ids = restore_trash_item(item).expect("Failed to restore item");
+            seal_history().expect("Failed to update history");
+            // The project, section or parent task may come back with it
+            if let Some(window) = application.main_window() {
+                window.reset();
+            }
+            if let TrashItem::Task(task) = item {
+                if let Ok(task) = read_task(task.id()) {
+                    application.refresh_task(&task);
+                }
+            }
+            for task_id in task_ids {
+                application.send_task_reminders(task_id);
+            }
+        }
+        self.refresh();
+    }
+
+    fn delete_item(&self, index: usize) {
+        let name = self.imp().items.borrow()[index].name();
+        let dialog_heading = gettext("Delete “{}” permanently?").replace("{}", &name);
+        self.confirm(
+            &dialog_heading,
+            &gettext("Its tasks, time records and reminders will be permanently lost."),
+            move |obj| {
+                delete_trash_item(&obj.imp().items.borrow()[index]).expect("Failed to delete item");
+            },
+        );
+    }
+
+    fn confirm<F: Fn(&Self) + 'static>(&self, heading: &str, body: &str, on_delete: F) {
+        let dialog =
+            gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/snippets/delete_dialog.ui")
+                .object::<adw::MessageDialog>("dialog")
+                .unwrap();
+        dialog.set_transient_for(Some(self));
+        dialog.set_heading(Some(heading));
+        dialog.set_body(body);
+        dialog.connect_response(
+            Some("delete"),
+            glib::clone!(@weak self as obj => move |_, response| {
+                if response == "delete" {
+                    on_delete(&obj);
+                    obj.refresh();
+                }
+            }),
+        );
+        dialog.present();
+    }
+
+    #[template_callback]
+    fn handle_empty_button_clicked(&self, _button: gtk::Button) {
+        self.confirm(
+            &gettext("Empty the trash?"),
+            &gettext("All items in the trash will be permanently lost."),
+            |_| empty_trash().expect("Failed to empty trash"),
+        );
+    }
+}
@@ -24,11 +24,18 @@ use gtk::glib::FromVariant;
 use gtk::{gdk, gio, glib, glib::Properties, prelude::*};
 use std::cell::{Cell, RefCell};
 
-use crate::db::models::{Project, Record, Task};
-use crate::db::operations::{create_project, create_section, read_projects};
+use crate::application::IPlanApplication;
+use crate::db::models::{Project, Task};
+use crate::db::operations::{
+    create_project, create_section, delete_filter, delete_tag, read_filter, read_project,
+    read_projects, read_tag, read_tag_tasks, seal_history, SmartView,
+};
+use crate::views::filter::{FilterPage, TaskView};
 use crate::views::project::{ProjectEditWindow, ProjectLayout, ProjectPage};
+use crate::views::sidebar::{SidebarFilters, SidebarProjects, SidebarTags};
 use crate::views::snippets::MenuItem;
-use crate::views::{calendar::CalendarPage, sidebar::SidebarProjects};
+use crate::views::task::TasksBox;
+use crate::views::{calendar::CalendarPage, tag::TagPage};
 
 #[derive(PartialEq, Debug)]
 pub enum ActionScope {
@@ -85,12 +92,20 @@ mod imp {
         #[template_child]
         pub sidebar_projects: TemplateChild<SidebarProjects>,
         #[template_child]
+        pub sidebar_tags: TemplateChild<SidebarTags>,
+        #[template_child]
+        pub sidebar_filters: TemplateChild<SidebarFilters>,
+        #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
         #[template_child]
         pub stack_pages: TemplateChild<gtk::Stack>,
         #[template_child]
         pub calendar: TemplateChild<CalendarPage>,
         #[template_child]
+        pub tag_page: TemplateChild<TagPage>,
+        #[template_child]
+        pub filter_page: TemplateChild<FilterPage>,
+        #[template_child]
         pub calendar_button: TemplateChild<gtk::Button>,
     }
 
@@ -127,7 +142,9 @@ mod imp {
                     true,
                     glib::closure_local!(@watch obj => move |_: ProjectEditWindow, project: Project| {
                         if let Some(page) = obj.visible_project_page() {
-                            page.imp().project_header.open_project(&project);
+                            let project_header = &page.imp().project_header;
+                            project_header.open_project(&project);
+                            project_header.set_stat_updated(false);
                         }
                         obj.imp().sidebar_projects.update_project(&project);
                     })
@@ -135,6 +152,7 @@ mod imp {
                 modal.present();
             });
             klass.install_action("project.delete", None, move |obj, _, _| {
+                seal_history().expect("Failed to update history");
                 // FIXME: send project via action
                 let projects_section = &obj.imp().sidebar_projects;
                 projects_section.delete_project(obj.visible_project_id().unwrap());
@@ -144,6 +162,82 @@ mod imp {
                     .remove(&obj.visible_project_page().unwrap());
                 obj.change_project(home_project);
             });
+            klass.install_action("tag.open", Some("x"), move |obj, _, value| {
+                let tag_id = value.unwrap().get::<i64>().unwrap();
+                let imp = obj.imp();
+                obj.close_sidebar();
+                let tag = read_tag(tag_id).expect("Failed to read tag");
+                imp.tag_page.open_tag(tag);
+                imp.stack_pages.set_visible_child_name("tag");
+                imp.calendar_button.add_css_class("flat");
+                let projects_box: &gtk::ListBox = imp.sidebar_projects.imp().projects_box.as_ref();
+                if let Some(row) = projects_box.selected_row() {
+                    projects_box.unselect_row(&row);
+                }
+                imp.sidebar_tags.select_tag(Some(tag_id));
+                imp.sidebar_filters.select_view(None);
+            });
+            klass.install_action("tag.changed", Some("x"), move |obj, _, value| {
+                let tag_id = value.unwrap().get::<i64>().unwrap();
+                seal_history().expect("Failed to update history");
+                let imp = obj.imp();
+                imp.sidebar_tags.refresh();
+                // Renaming the tag renames it in the saved filters
+                if let Some(TaskView::Saved(filter)) = imp.filter_page.view() {
+                    if let Ok(filter) = read_filter(filter.id()) {
+                        imp.filter_page.open_view(TaskView::Saved(filter));
+                    }
+                }
+                // Tag chips of the task rows
+                for task in read_tag_tasks(tag_id).expect("Failed to read tasks") {
+                    obj.refresh_task(task);
+                }
+            });
+            klass.install_action("tag.delete", None, move |obj, _, _| {
+                let imp = obj.imp();
+                let tag = imp.tag_page.tag().unwrap();
+                let tasks = read_tag_tasks(tag.id()).expect("Failed to read tasks");
+                delete_tag(tag.id()).expect("Failed to delete tag");
+                seal_history().expect("Failed to update history");
+                for task in tasks {
+                    obj.refresh_task(task);
+                }
+                imp.sidebar_tags.refresh();
+                obj.change_project(obj.home_project());
+            });
+            // The parameter is (0, smart view index) or (1, saved filter id)
+            klass.install_action("filter.open", Some("(yx)"), move |obj, _, value| {
+                let (kind, id): (u8, i64) = value.unwrap().get().unwrap();
+                let imp = obj.imp();
+                let view = if kind == 0 {
+                    TaskView::Smart(SmartView::from_index(id as u8).unwrap())
+                } else {
+                    TaskView::Saved(read_filter(id).expect("Failed to read filter"))
+                };
+                obj.close_sidebar();
+                imp.filter_page.open_view(view.clone());
+                imp.stack_pages.set_visible_child_name("filter");
+                imp.calendar_button.add_css_class("flat");
+                let projects_box: &gtk::ListBox = imp.sidebar_projects.imp().projects_box.as_ref();
+                if let Some(row) = projects_box.selected_row() {
+                    projects_box.unselect_row(&row);
+                }
+                imp.sidebar_tags.select_tag(None);
+                imp.sidebar_filters.select_view(Some(&view));
+            });
+            klass.install_action("filter.changed", Some("x"), move |obj, _, _| {
+                seal_history().expect("Failed to update history");
+                obj.imp().sidebar_filters.refresh();
+            });
+            klass.install_action("filter.delete", None, move |obj, _, _| {
+                let imp = obj.imp();
+                if let Some(TaskView::Saved(filter)) = imp.filter_page.view() {
+                    delete_filter(filter.id()).expect("Failed to delete filter");
+                    seal_history().expect("Failed to update history");
+                }
+                imp.sidebar_filters.refresh();
+                obj.change_project(obj.home_project());
+            });
             klass.install_action("section.new", None, move |obj, _, _| {
                 // FIXME: send project via action
                 obj.visible_project_page()
@@ -159,7 +253,13 @@ mod imp {
                 )),
                 |obj, _, value| {
                     let (task, scope): (Task, ActionScope) = value.unwrap().get().unwrap();
+                    // The task changes of an action end here
+                    seal_history().expect("Failed to update history");
                     let imp = obj.imp();
+                    obj.emit_tasks_signal("TaskChanged", (task.id(),).to_variant());
+                    imp.tag_page.reset_task(&task);
+                    imp.filter_page.reset_task(&task);
+                    imp.sidebar_tags.refresh();
 
                     let update_project_page = || {
                         let page_name = task.project().to_string();
@@ -194,6 +294,7 @@ mod imp {
                 )),
                 move |obj: &super::IPlanWindow, _, value| {
                     let (task, scope): (Task, ActionScope) = value.unwrap().get().unwrap();
+                    seal_history().expect("Failed to update history");
                     let imp = obj.imp();
 
                     let update_project_page = || {
@@ -205,6 +306,14 @@ mod imp {
                         }
                     };
 
+                    if let Some(task_row) = imp.tag_page.task_row(task.id()) {
+                        task_row.refresh_timer();
+                    }
+                    if let Some(task_row) = imp.filter_page.task_row(task.id()) {
+                        task_row.refresh_timer();
+                    }
+                    imp.sidebar_projects.refresh_project_goal(task.project());
+
                     match scope {
                         ActionScope::Project(_) => {
                             let task_id = task.id();
@@ -224,46 +333,6 @@ mod imp {
                     }
                 },
             );
-            klass.install_action(
-                "timer.start",
-                Some(&format!(
-                    "({}{})",
-                    Task::static_variant_type().as_str(),
-                    Record::static_variant_type().as_str()
-                )),
-                |obj, _, value| {
-                    let (task, record): (Task, Record) = value.unwrap().get().unwrap();
-
-                    if let Some(project_page) = obj.project_by_id(task.project()) {
-                        if let Some(task_row) = project_page.task_row(&task) {
-                            task_row.start_timer(record.to_owned());
-                        }
-                    }
-
-                    let imp = obj.imp();
-                    if let Some((_, task_row)) = imp.calendar.task_row(task.id()) {
-                        task_row.start_timer(record)
-                    }
-                },
-            );
-            klass.install_action(
-                "timer.stop",
-                Some(Task::static_variant_type().as_str()),
-                |obj, _, value| {
-                    let task: Task = value.unwrap().get().unwrap();
-
-                    if let Some(project_page) = obj.project_by_id(task.project()) {
-                        if let Some(task_row) = project_page.task_row(&task) {
-                            task_row.cancel_timer();
-                        }
-                    }
-
-                    let imp = obj.imp();
-                    if let Some((_, task_row)) = imp.calendar.task_row(task.id()) {
-                        task_row.cancel_timer()
-                    }
-                },
-            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -363,6 +432,58 @@ impl IPlanWindow {
             .sync_create()
             .build();
 
+        let tag_page_imp = imp.tag_page.imp().page.imp();
+        tag_page_imp
+            .toggle_sidebar_button
+            .bind_property("active", &imp.flap.get(), "reveal-flap")
+            .sync_create()
+            .bidirectional()
+            .build();
+
+        imp.flap
+            .bind_property(
+                "folded",
+                &tag_page_imp.page_header.get(),
+                "show-start-title-buttons",
+            )
+            .sync_create()
+            .build();
+
+        imp.flap
+            .bind_property(
+                "folded",
+                &tag_page_imp.toggle_sidebar_button.get(),
+                "visible",
+            )
+            .sync_create()
+            .build();
+
+        let filter_page_imp = imp.filter_page.imp().page.imp();
+        filter_page_imp
+            .toggle_sidebar_button
+            .bind_property("active", &imp.flap.get(), "reveal-flap")
+            .sync_create()
+            .bidirectional()
+            .build();
+
+        imp.flap
+            .bind_property(
+                "folded",
+                &filter_page_imp.page_header.get(),
+                "show-start-title-buttons",
+            )
+            .sync_create()
+            .build();
+
+        imp.flap
+            .bind_property(
+                "folded",
+                &filter_page_imp.toggle_sidebar_button.get(),
+                "visible",
+            )
+            .sync_create()
+            .build();
+
         let (tx, rx) = glib::MainContext::channel(glib::Priority::DEFAULT);
         glib::idle_add_once(move || tx.send(()).unwrap());
         rx.attach(
@@ -386,6 +507,8 @@ impl IPlanWindow {
         project_page.select_task(None);
         imp.sidebar_projects.check_archive_hidden();
         imp.sidebar_projects.select_active_project();
+        imp.sidebar_tags.select_tag(None);
+        imp.sidebar_filters.select_view(None);
         imp.calendar_button.add_css_class("flat");
     }
 
@@ -419,17 +542,23 @@ impl IPlanWindow {
 
     pub fn reset(&self) {
         let imp = self.imp();
+        let visible_project = self
+            .visible_project_id()
+            .and_then(|project_id| read_project(project_id).ok());
 
         let pages = imp.stack_pages.observe_children();
-        for _ in 0..pages.n_items() {
-            let page = &pages.item(0).and_downcast::<gtk::Widget>().unwrap();
-            imp.stack_pages.remove(page);
+        for i in (0..pages.n_items()).rev() {
+            if let Some(page) = pages.item(i).and_downcast::<ProjectPage>() {
+                imp.stack_pages.remove(&page);
+            }
         }
         imp.calendar.refresh();
+        imp.sidebar_tags.refresh();
+        imp.sidebar_filters.refresh();
 
         let home_project = self.home_project();
         imp.sidebar_projects.reset();
-        self.change_project(home_project);
+        self.change_project(visible_project.unwrap_or(home_project));
     }
 
     pub fn add_delete_toast(&self, task: &Task, toast: adw::Toast) {
@@ -441,6 +570,36 @@ impl IPlanWindow {
         self.imp().toast_overlay.add_toast(toast);
     }
 
+    pub fn remove_task_row(&self, task: &Task) {
+        if let Some(project_page) = self.project_by_id(task.project()) {
+            if let Some(task_row) = project_page.task_row(task) {
+                let tasks_box = task_row
+                    .ancestor(TasksBox::static_type())
+                    .and_downcast::<TasksBox>()
+                    .unwrap();
+                tasks_box.remove_item(&task_row);
+            }
+        }
+    }
+
+    /// Update the task rows without a change in the task itself
+    fn refresh_task(&self, task: Task) {
+        self.activate_action(
+            "task.changed",
+            Some(&glib::Variant::from((
+                task.to_variant(),
+                ActionScope::None.to_variant(),
+            ))),
+        )
+        .expect("Failed to send task.changed action");
+    }
+
+    fn emit_tasks_signal(&self, signal: &str, parameters: glib::Variant) {
+        if let Some(application) = self.application().and_downcast::<IPlanApplication>() {
+            application.emit_tasks_signal(signal, parameters);
+        }
+    }
+
     fn home_project(&self) -> Project {
         let projects = read_projects(true).unwrap();
         if let Some(project) = projects.first() {
@@ -541,12 +700,14 @@ impl IPlanWindow {
         let imp = self.imp();
         self.close_sidebar();
 
-        if self.visible_project_page().is_none() {
+        if imp.stack_pages.visible_child_name().unwrap() == "calendar" {
             return;
         }
 
         button.remove_css_class("flat");
-        self.imp().stack_pages.set_visible_child_name("calendar");
+        imp.stack_pages.set_visible_child_name("calendar");
+        imp.sidebar_tags.select_tag(None);
+        imp.sidebar_filters.select_view(None);
         let projects_box: &gtk::ListBox = imp.sidebar_projects.imp().projects_box.as_ref();
         if let Some(row) = projects_box.selected_row() {
             projects_box.unselect_row(&row);